@@ -0,0 +1,155 @@
+//! Stable entry points for embedding wallman in other programs.
+
+use std::{io, path::Path};
+
+use crate::{
+    FillMode, OutputResolver, OutputTarget,
+    error::{Result, WallmanError},
+    trigger::{OutputChange, TriggerResult},
+};
+
+/// Show `image` on `output`, or on every detected output when `output` is `None`.
+///
+/// Runs the same apply path as the daemon: per-output swaybg processes are
+/// tracked and replaced, and the `onChange` hook and palette export run when a
+/// config has been loaded into `APP_STATE`. The palette has been written by
+/// the time this returns. The swaybg processes belong to the calling process.
+///
+/// `fill` is how the image fills each output; an image's `.meta.toml`
+/// sidecar overrides it.
+pub fn set_wallpaper(output: Option<&str>, image: &Path, fill: FillMode) -> Result<()> {
+    let target = output.map_or(OutputTarget::All, |name| {
+        OutputTarget::Named(name.to_string())
+    });
+    set_wallpaper_on(&target, image, fill)
+}
+
+/// `set_wallpaper` on the outputs `target` selects: all detected outputs,
+/// the primary (else focused) one, or a named output.
+///
+/// Fails with `WallmanError::Output` when `OutputTarget::Primary` cannot be
+/// determined. A named output is used without detecting outputs.
+pub fn set_wallpaper_on(target: &OutputTarget, image: &Path, fill: FillMode) -> Result<()> {
+    let (image_path, outputs) = resolve(target, image)?;
+
+    tracing::debug!(
+        "set_wallpaper: '{}' on {:?} ({:?})",
+        image_path,
+        outputs,
+        fill
+    );
+    let result = TriggerResult {
+        changes: outputs
+            .into_iter()
+            .map(|output| OutputChange {
+                output,
+                image_path: image_path.clone(),
+                fill_mode: fill.clone(),
+            })
+            .collect(),
+    };
+    let report = crate::wallpaper::apply::apply(result)?;
+    crate::wallpaper::colors::wait_for_palette_export();
+    report.into_result()
+}
+
+/// The canonical path of `image` and the outputs `target` selects.
+pub(crate) fn resolve(target: &OutputTarget, image: &Path) -> Result<(String, Vec<String>)> {
+    if !image.is_file() {
+        return Err(WallmanError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("image not found: {}", image.display()),
+        )));
+    }
+    let image_path = image.canonicalize()?.to_string_lossy().to_string();
+
+    let outputs = match target {
+        OutputTarget::Named(name) => vec![name.clone()],
+        _ => target.select(&OutputResolver::detect()?)?,
+    };
+    Ok((image_path, outputs))
+}
+
+/// `set_wallpaper` for async callers (the `async` feature): outputs are
+/// detected through `tokio::process` and the apply runs on tokio's blocking
+/// pool, so the caller's runtime is never blocked.
+#[cfg(feature = "async")]
+pub async fn set_wallpaper_async(output: Option<&str>, image: &Path, fill: FillMode) -> Result<()> {
+    if !image.is_file() {
+        return Err(WallmanError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("image not found: {}", image.display()),
+        )));
+    }
+    let image_path = image.canonicalize()?.to_string_lossy().to_string();
+
+    let outputs = match output {
+        Some(name) => vec![name.to_string()],
+        None => OutputResolver::detect_async().await?.outputs().to_vec(),
+    };
+    if outputs.is_empty() {
+        return Err(WallmanError::Output(
+            "no active outputs detected".to_string(),
+        ));
+    }
+
+    tracing::debug!(
+        "set_wallpaper_async: '{}' on {:?} ({:?})",
+        image_path,
+        outputs,
+        fill
+    );
+    let result = TriggerResult {
+        changes: outputs
+            .into_iter()
+            .map(|output| OutputChange {
+                output,
+                image_path: image_path.clone(),
+                fill_mode: fill.clone(),
+            })
+            .collect(),
+    };
+    let report = crate::wallpaper::apply::apply_async(result).await?;
+    let _ = tokio::task::spawn_blocking(crate::wallpaper::colors::wait_for_palette_export).await;
+    report.into_result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_image_is_rejected_before_detection() {
+        let err = set_wallpaper(
+            Some("DP-1"),
+            Path::new("/nonexistent/wallman.png"),
+            FillMode::Fill,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("image not found"));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_apply_rejects_missing_image_and_skips_empty_batches() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let err = set_wallpaper_async(
+                Some("DP-1"),
+                Path::new("/nonexistent/wallman.png"),
+                FillMode::Fill,
+            )
+            .await
+            .unwrap_err();
+            assert!(err.to_string().contains("image not found"));
+
+            let report = crate::wallpaper::apply::apply_async(TriggerResult { changes: vec![] })
+                .await
+                .unwrap();
+            assert!(report.is_ok());
+        });
+    }
+}
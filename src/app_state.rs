@@ -4,11 +4,15 @@ use std::{
     sync::{Arc, Mutex, OnceLock},
 };
 
+#[derive(Clone)]
 pub struct AppState {
     pub config_path: String,
     pub images_pool: Option<String>,
     pub is_pool: bool,
     pub config: Config,
+    /// The config file as loaded, before the theme was merged in; `set_pool`
+    /// re-merges from it instead of re-reading the file.
+    pub user_config: Option<Config>,
 }
 
 // Global application state using OnceLock for lazy initialization
@@ -23,6 +27,7 @@ impl Default for AppState {
             images_pool: None,
             is_pool: false,
             config: Config::default(),
+            user_config: None,
         }
     }
 }
@@ -33,12 +38,13 @@ impl AppState {
         config_path: String,
         images_pool: Option<String>,
         is_pool: bool,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
+    ) -> crate::Result<Self> {
         Ok(AppState {
             config_path,
             images_pool,
             is_pool,
             config,
+            user_config: None,
         })
     }
 
@@ -73,14 +79,18 @@ impl AppState {
             .background
             .as_ref()
             .and_then(|bg| bg.values().next())
-            .map(|config| config.fill_mode.clone())
-            .unwrap_or(crate::config::FillMode::Fill)
+            .map(|bg| self.config.fill_mode_for(bg))
+            .unwrap_or_else(|| self.config.default_fill_mode.clone().unwrap_or_default())
     }
 
     pub fn update_background(&mut self, image_path: String, fill_mode: crate::config::FillMode) {
         let background_config = crate::config::BackgroundConfig {
             image: Some(image_path),
-            fill_mode,
+            fill_mode: Some(fill_mode),
+            mode: None,
+            blur_sigma: None,
+            brightness: None,
+            fallback_color: None,
         };
 
         self.config.background = Some(std::collections::HashMap::from([(
@@ -89,16 +99,24 @@ impl AppState {
         )]));
     }
 
-    pub fn save_config(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let config_path = crate::constants::config_folder().join("config.toml");
-        self.config.save_to_file(&config_path)
+    /// Write the config back to the file it was loaded from.
+    pub fn save_config(&self) -> crate::Result<()> {
+        self.config.save_to_file(&PathBuf::from(&self.config_path))
     }
 
-    pub fn reload_config(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut config = Config::load(PathBuf::from(&self.config_path))?;
+    /// Re-read the config file and re-merge the active theme.
+    ///
+    /// A config read from stdin (`--config -`) cannot be read again, so the
+    /// kept user config is re-merged instead.
+    pub fn reload_config(&mut self) -> crate::Result<()> {
+        let mut config = match &self.user_config {
+            Some(user) if self.config_path == crate::STDIN_CONFIG => user.clone(),
+            _ => Config::load(PathBuf::from(&self.config_path))?,
+        };
+        self.user_config = Some(config.clone());
 
         // If a theme pool is active, merge its manifest settings.
-        if let Some(pool) = &config.pool {
+        if let Some(pool) = config.primary_pool() {
             let pool_path = PathBuf::from(pool);
             if let Err(e) = config.merge_theme(pool_path) {
                 tracing::warn!("Failed to merge theme manifest during reload: {}", e);
@@ -106,30 +124,320 @@ impl AppState {
         }
 
         self.config = config;
-        self.images_pool = self.config.pool.clone();
+        self.images_pool = self.config.primary_pool();
         self.is_pool = self.config.pool.is_some();
+        crate::OutputResolver::set_fixed_outputs(self.config.outputs.clone());
+        crate::logging::set_config_level(self.config.log_level.as_deref())?;
+        Ok(())
+    }
+
+    /// Switch to the theme in `pool` without re-reading the config file.
+    ///
+    /// The theme is merged into a copy of the user config and only swapped in
+    /// once that succeeded, so a broken manifest leaves the current theme.
+    pub fn set_pool(&mut self, pool: String) -> crate::Result<()> {
+        let mut user = self
+            .user_config
+            .clone()
+            .unwrap_or_else(|| self.config.clone());
+        user.pool = Some(crate::PoolConfig::Single(pool.clone()));
+
+        let mut config = user.clone();
+        config.merge_theme(PathBuf::from(&pool))?;
+
+        self.user_config = Some(user);
+        self.config = config;
+        self.images_pool = Some(pool);
+        self.is_pool = true;
         Ok(())
     }
 
-    /// Resolve an image path against the current theme pool if it is relative.
+    /// Resolve an image path for a specific output.
+    ///
+    /// Like `resolve_image_path`, but against the output's own pool when
+    /// `[pool]` is a per-output table, then expands `{width}`/`{height}`
+    /// templates to the variant that best fits the output's resolution.
+    /// Generated `gradient:`/`pattern:` images are sized to the output.
+    pub fn resolve_image_for_output(
+        &self,
+        output: &str,
+        path: &str,
+        geometry: Option<crate::outputs::OutputGeometry>,
+    ) -> String {
+        let pool = self.pool_for(output);
+        crate::outputs::variant::select_variant(
+            &self.resolve_in_pool(path, pool.as_deref(), geometry),
+            geometry,
+        )
+    }
+
+    /// The theme pool images for `output` come from.
+    pub fn pool_for(&self, output: &str) -> Option<String> {
+        match &self.config.pool {
+            Some(pool @ crate::PoolConfig::PerOutput(_)) => pool.for_output(output),
+            _ => self.images_pool.clone(),
+        }
+    }
+
+    /// Referenced images that swaybg would fail to show, as `image: reason`.
+    ///
+    /// Resolution templates are checked through their generic file, and only
+    /// when it exists.
+    pub fn image_problems(&self) -> Vec<String> {
+        use crate::outputs::variant;
+
+        self.config
+            .referenced_images()
+            .into_iter()
+            .filter_map(|image| {
+                let path = variant::select_variant(&self.resolve_image_path(image), None);
+                let path = std::path::Path::new(&path);
+                if variant::is_template(image) && !path.exists() {
+                    return None;
+                }
+                crate::format::pack::check_image_file(path)
+                    .err()
+                    .map(|reason| format!("{}: {}", image, reason))
+            })
+            .collect()
+    }
+
+    /// Resolve a relative image path against the current theme pool, or
+    /// against the config file's directory when no pool is active.
+    ///
+    /// In a theme, a file under its images folder (`Config::images_dir`) wins
+    /// over one relative to the theme root, so `day/forest.png` works for
+    /// themes that organise art in their own subfolders.
+    ///
+    /// `color:`, `builtin:`, `gradient:` and `pattern:` values resolve to a
+    /// generated image (see `wallpaper::generated`).
     pub fn resolve_image_path(&self, path: &str) -> String {
+        self.resolve_in_pool(path, self.images_pool.as_deref(), None)
+    }
+
+    fn resolve_in_pool(
+        &self,
+        path: &str,
+        pool: Option<&str>,
+        geometry: Option<crate::outputs::OutputGeometry>,
+    ) -> String {
+        if crate::wallpaper::generated::is_generated(path) {
+            let size = geometry.map(|g| (g.width, g.height));
+            return match crate::wallpaper::generated::generated_image(path, size) {
+                Ok(generated) => generated.to_string_lossy().to_string(),
+                Err(e) => {
+                    tracing::warn!("Cannot generate '{}': {}", path, e);
+                    path.to_string()
+                }
+            };
+        }
+
         let p = std::path::Path::new(path);
         if p.is_absolute() {
             return path.to_string();
         }
 
-        if let Some(pool) = &self.images_pool {
+        if let Some(pool) = pool {
             let pool_path = std::path::Path::new(pool);
-            // Themes usually have an 'images' subfolder.
-            let theme_images = pool_path.join("images");
-            let final_path = if theme_images.exists() {
-                theme_images.join(path)
-            } else {
-                pool_path.join(path)
+            let theme_images = pool_path.join(self.config.images_dir());
+            let candidates = [theme_images.join(path), pool_path.join(path)];
+            let final_path = match candidates.iter().find(|c| c.exists()) {
+                Some(found) => found.clone(),
+                // Nothing on disk (yet, or a `{width}` template): guess by layout.
+                None if theme_images.is_dir() => theme_images.join(path),
+                None => pool_path.join(path),
             };
             return final_path.to_string_lossy().to_string();
         }
 
-        path.to_string()
+        // Never depend on the CWD: a daemon's is arbitrary.
+        match std::path::Path::new(&self.config_path).parent() {
+            Some(config_dir) => config_dir.join(path).to_string_lossy().to_string(),
+            None => path.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(pool: Option<String>) -> AppState {
+        AppState::new(
+            Config::default(),
+            "/home/user/.config/wallman/config.toml".to_string(),
+            pool.clone(),
+            pool.is_some(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_absolute_path_unchanged() {
+        let state = state(Some("/themes/forest".to_string()));
+        assert_eq!(state.resolve_image_path("/pics/x.png"), "/pics/x.png");
+    }
+
+    #[test]
+    fn test_resolve_relative_path_against_pool() {
+        let state = state(Some("/nonexistent/themes/forest".to_string()));
+        assert_eq!(
+            state.resolve_image_path("x.png"),
+            "/nonexistent/themes/forest/x.png"
+        );
+    }
+
+    #[test]
+    fn test_resolve_prefers_default_images_dir() {
+        let pool = std::env::temp_dir().join("wallman_test_resolve_default");
+        let _ = std::fs::remove_dir_all(&pool);
+        std::fs::create_dir_all(pool.join("images")).unwrap();
+        std::fs::write(pool.join("images/x.png"), b"").unwrap();
+        std::fs::write(pool.join("x.png"), b"").unwrap();
+        std::fs::create_dir_all(pool.join("day")).unwrap();
+        std::fs::write(pool.join("day/forest.png"), b"").unwrap();
+        let state = state(Some(pool.to_string_lossy().to_string()));
+
+        let resolve = |p: &str| PathBuf::from(state.resolve_image_path(p));
+        assert_eq!(resolve("x.png"), pool.join("images/x.png"));
+        assert_eq!(resolve("day/forest.png"), pool.join("day/forest.png"));
+        assert_eq!(resolve("missing.png"), pool.join("images/missing.png"));
+        std::fs::remove_dir_all(&pool).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_uses_images_dir_override() {
+        let pool = std::env::temp_dir().join("wallman_test_resolve_override");
+        let _ = std::fs::remove_dir_all(&pool);
+        std::fs::create_dir_all(pool.join("art")).unwrap();
+        std::fs::create_dir_all(pool.join("images")).unwrap();
+        std::fs::write(pool.join("art/x.png"), b"").unwrap();
+        std::fs::write(pool.join("images/x.png"), b"").unwrap();
+        let mut state = state(Some(pool.to_string_lossy().to_string()));
+        state.config.images_dir = Some("art".to_string());
+
+        let resolve = |p: &str| PathBuf::from(state.resolve_image_path(p));
+        assert_eq!(resolve("x.png"), pool.join("art/x.png"));
+        assert_eq!(resolve("missing.png"), pool.join("art/missing.png"));
+        std::fs::remove_dir_all(&pool).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_against_per_output_pool() {
+        let config: Config = toml::from_str(
+            r#"
+                [pool]
+                HDMI-1 = "/nonexistent/themes/nature"
+                "*" = "/nonexistent/themes/abstract"
+            "#,
+        )
+        .unwrap();
+        let primary = config.primary_pool();
+        assert_eq!(primary.as_deref(), Some("/nonexistent/themes/abstract"));
+        let per_output =
+            AppState::new(config, "/cfg/config.toml".to_string(), primary, true).unwrap();
+
+        assert_eq!(
+            per_output.resolve_image_for_output("HDMI-1", "x.png", None),
+            "/nonexistent/themes/nature/x.png"
+        );
+        assert_eq!(
+            per_output.resolve_image_for_output("DP-1", "x.png", None),
+            "/nonexistent/themes/abstract/x.png"
+        );
+        // A single-string pool still applies to every output.
+        let single = state(Some("/nonexistent/themes/forest".to_string()));
+        assert_eq!(
+            single.resolve_image_for_output("HDMI-1", "x.png", None),
+            "/nonexistent/themes/forest/x.png"
+        );
+    }
+
+    #[test]
+    fn test_resolve_relative_path_against_config_dir() {
+        let state = state(None);
+        assert_eq!(
+            state.resolve_image_path("pics/x.png"),
+            "/home/user/.config/wallman/pics/x.png"
+        );
+    }
+
+    #[test]
+    fn test_set_pool_swaps_theme_without_reading_config_file() {
+        let dir = std::env::temp_dir().join("wallman_test_set_pool");
+        let _ = std::fs::remove_dir_all(&dir);
+        let write_theme = |name: &str, manifest: &str| {
+            let theme = dir.join(name);
+            std::fs::create_dir_all(&theme).unwrap();
+            std::fs::write(theme.join("manifest.toml"), manifest).unwrap();
+            theme.to_string_lossy().to_string()
+        };
+        let forest = write_theme("forest", "[background.DP-1]\nimage = \"pine.png\"\n");
+        let ocean = write_theme("ocean", "[background.HDMI-1]\nimage = \"wave.png\"\n");
+        let broken = write_theme("broken", "[background\n");
+
+        let user: Config = toml::from_str("lat = 40.0\n").unwrap();
+        let mut merged = user.clone();
+        merged.merge_theme(PathBuf::from(&forest)).unwrap();
+        // The config file does not exist: set_pool must not need it.
+        let mut state = AppState::new(
+            merged,
+            dir.join("missing.toml").to_string_lossy().to_string(),
+            Some(forest),
+            true,
+        )
+        .unwrap();
+        state.user_config = Some(user);
+
+        state.set_pool(ocean.clone()).unwrap();
+        let backgrounds = state.config.background.as_ref().unwrap();
+        assert!(backgrounds.contains_key("HDMI-1"));
+        assert!(!backgrounds.contains_key("DP-1"));
+        assert_eq!(state.config.lat, Some(40.0));
+        assert_eq!(state.images_pool.as_deref(), Some(ocean.as_str()));
+
+        // A manifest that fails to load leaves the current theme in place.
+        assert!(state.set_pool(broken).is_err());
+        assert_eq!(state.images_pool.as_deref(), Some(ocean.as_str()));
+        assert!(
+            state
+                .config
+                .background
+                .as_ref()
+                .unwrap()
+                .contains_key("HDMI-1")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_theme_transition_applies_after_theme_set() {
+        let dir = std::env::temp_dir().join("wallman_test_theme_transition");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("manifest.toml"),
+            "[transition.\"*\"]\nfade_ms = 750\n\n[transition.HDMI-1]\nfade_ms = 300\n",
+        )
+        .unwrap();
+
+        // The user's own HDMI-1 entry survives a deep merge.
+        let user: Config =
+            toml::from_str("mergeStrategy = \"deep\"\n\n[transition.HDMI-1]\nfade_ms = 0\n")
+                .unwrap();
+        let mut state = AppState::new(
+            user.clone(),
+            dir.join("config.toml").to_string_lossy().to_string(),
+            None,
+            false,
+        )
+        .unwrap();
+        state.user_config = Some(user);
+        assert_eq!(state.config.fade_ms_for("DP-1"), 0);
+
+        state.set_pool(dir.to_string_lossy().to_string()).unwrap();
+        assert_eq!(state.config.fade_ms_for("DP-1"), 750);
+        assert_eq!(state.config.fade_ms_for("HDMI-1"), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
 use super::commands::{CompletionCommand, ConfigCommand, DaemonCommand, PackCommand, ThemeCommand};
@@ -24,6 +26,27 @@ pub struct Cli {
     /// Enable debug-level tracing output
     #[arg(global = true, long)]
     pub debug: bool,
+
+    /// Use this config file instead of searching the default locations (`-` reads TOML from stdin)
+    #[arg(global = true, long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Log the wallpapers that would be applied instead of starting swaybg
+    #[arg(global = true, long)]
+    pub dry_run: bool,
+
+    /// Suppress success messages; errors and requested output (listings, reports) still print
+    #[arg(global = true, short, long)]
+    pub quiet: bool,
+
+    /// Query swaymsg for outputs even when `outputCache` has a recent copy
+    #[arg(global = true, long)]
+    pub refresh_outputs: bool,
+
+    /// Also write logs to this file, rotated daily
+    /// (the detached daemon defaults to <data_dir>/wallman/daemon.log)
+    #[arg(global = true, long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
 }
 
 /// Top-level commands
@@ -37,6 +60,11 @@ pub enum Command {
 
     /// Control the wallman background daemon
     Daemon {
+        /// PID file to use instead of <data_dir>/wallman/daemon.pid
+        /// (also read from $WALLMAN_PID_FILE)
+        #[arg(global = true, long, value_name = "PATH")]
+        pid_file: Option<PathBuf>,
+
         #[command(subcommand)]
         sub: DaemonCommand,
     },
@@ -53,6 +81,32 @@ pub enum Command {
         sub: PackCommand,
     },
 
+    /// Show an image right away, without the daemon's triggers
+    Set {
+        /// Image file to show
+        #[arg(long, short)]
+        image: PathBuf,
+        /// `all` (default), `primary` (sway's primary, else focused, output) or an output name
+        #[arg(long, short, default_value = "all")]
+        output: String,
+    },
+
+    /// List the detected monitors and their connector names for per-output config
+    Outputs {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check the config, backend, outputs, images and directories for common problems
+    Doctor,
+
+    /// Show which trigger the daemon would run for the current config, and why
+    Explain,
+
+    /// List every image the current config could apply, per output, and whether it exists
+    Images,
+
     /// Generate shell completion scripts
     Completion {
         #[command(subcommand)]
@@ -80,8 +80,8 @@ pub fn install_completion(force: bool) -> io::Result<()> {
 
     std::fs::write(&completion_file, buf)?;
 
-    println!("Completion installed to: {}", completion_file.display());
-    println!(
+    crate::cli::say!("Completion installed to: {}", completion_file.display());
+    crate::cli::say!(
         "Restart your shell or run: source {}",
         completion_file.display()
     );
@@ -97,9 +97,9 @@ pub fn uninstall_completion() -> io::Result<()> {
 
     if completion_file.exists() {
         std::fs::remove_file(&completion_file)?;
-        println!("Completion uninstalled from: {}", completion_file.display());
+        crate::cli::say!("Completion uninstalled from: {}", completion_file.display());
     } else {
-        println!("No completion file found at: {}", completion_file.display());
+        crate::cli::say!("No completion file found at: {}", completion_file.display());
     }
 
     Ok(())
@@ -4,17 +4,50 @@ use clap::Subcommand;
 #[derive(Debug, Subcommand)]
 pub enum ConfigCommand {
     /// Create a default config file if none exists
-    Init,
+    Init {
+        /// Ask which outputs, wallpaper and trigger to set up, then offer to
+        /// start the daemon (falls back to the default file without a terminal)
+        #[arg(long, short = 'i')]
+        interactive: bool,
+    },
 
     /// Open the config file in $EDITOR
-    Edit,
+    Edit {
+        /// Validate after the editor exits and re-open it until the config is valid
+        #[arg(long)]
+        validate: bool,
+    },
 
     /// Parse and validate the current config file
-    Validate,
+    Validate {
+        /// Treat warnings (e.g. conflicting trigger sections) as errors
+        #[arg(long)]
+        strict: bool,
+    },
 
     /// Print the path to the active config file
     Path,
 
+    /// Print the effective config (after merging the active theme) as TOML
+    Show,
+
+    /// Upgrade the config file to the current schema version
+    Migrate,
+
+    /// Print a single setting, e.g. `wallman config get background.*.fillMode`
+    Get {
+        /// Dotted key path (camelCase or snake_case segments)
+        key: String,
+    },
+
+    /// Change a single setting, e.g. `wallman config set weather.*.weather.sunny sun.jpg`
+    Set {
+        /// Dotted key path (camelCase or snake_case segments)
+        key: String,
+        /// New value; numbers, booleans and `null` are parsed, anything else is a string
+        value: String,
+    },
+
     /// Set the latitude for location-based triggers (e.g., 40.7128)
     SetLat {
         /// Latitude value (-90 to 90)
@@ -8,14 +8,41 @@ pub enum DaemonCommand {
         /// Run in the foreground instead of detaching
         #[arg(long)]
         foreground: bool,
+        /// Start even without WAYLAND_DISPLAY/SWAYSOCK in the environment
+        #[arg(long)]
+        force: bool,
     },
 
     /// Stop the running daemon
     Stop,
 
     /// Restart the daemon (stop + start)
-    Restart,
+    Restart {
+        /// Start even without WAYLAND_DISPLAY/SWAYSOCK in the environment
+        #[arg(long)]
+        force: bool,
+        /// Only restart a running daemon; do nothing (and succeed) when it is stopped
+        #[arg(long)]
+        if_running: bool,
+    },
 
     /// Show daemon status (running / stopped + PID)
-    Status,
+    Status {
+        /// Print PID, uptime, trigger, config path and current wallpapers as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Install a systemd user service that runs the daemon
+    InstallService {
+        /// Overwrite an existing unit file
+        #[arg(short, long)]
+        force: bool,
+        /// Run `systemctl --user enable --now` after installing
+        #[arg(long)]
+        enable: bool,
+    },
+
+    /// Disable and remove the systemd user service
+    UninstallService,
 }
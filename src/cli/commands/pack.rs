@@ -7,7 +7,7 @@ pub enum PackCommand {
     Build {
         /// Source theme directory
         path: String,
-        /// Output file path
+        /// Output file path, or `-` for stdout
         #[arg(short, long)]
         output: Option<String>,
     },
@@ -16,5 +16,31 @@ pub enum PackCommand {
     Inspect {
         /// .wallman file to inspect
         file: String,
+        /// Only list image files
+        #[arg(long)]
+        images_only: bool,
+        /// Only list entries whose path matches this glob (`*` and `?`, e.g. `*.png`)
+        #[arg(long, value_name = "GLOB")]
+        filter: Option<String>,
+    },
+
+    /// Recompress a .wallman pack at another zstd level without its source directory
+    Repack {
+        /// .wallman file to recompress
+        input: String,
+        /// Output .wallman file (may be the input itself)
+        output: String,
+        /// zstd compression level (1 fastest … 22 smallest; `pack build` uses 3)
+        #[arg(long, required_unless_present = "store", conflicts_with = "store")]
+        level: Option<i32>,
+        /// Use the lightest compression, for packs of already-compressed images
+        #[arg(long)]
+        store: bool,
+    },
+
+    /// Check a .wallman pack (archive, manifest, referenced images, image formats, paths) without installing it
+    Verify {
+        /// .wallman file to verify
+        file: String,
     },
 }
@@ -16,24 +16,46 @@ pub enum ThemeCommand {
     Pack {
         /// Source theme directory
         path: String,
-        /// Output .wallman file path (default: <name>.wallman)
+        /// Output .wallman file path, or `-` for stdout (default: <name>.wallman)
         #[arg(short, long)]
         output: Option<String>,
     },
 
-    /// Install a .wallman pack file
+    /// Install a .wallman pack file from disk or an http(s) URL
     Install {
-        /// Path to the .wallman file
+        /// Path or URL of the .wallman file
         file: String,
     },
 
     /// List all installed themes
     List,
 
+    /// Show the manifest, outputs and disk usage of an installed theme
+    Info {
+        /// Theme name as shown by `wallman theme list`
+        name: String,
+    },
+
+    /// Check that the external tools an installed theme requires are in PATH
+    Deps {
+        /// Theme name as shown by `wallman theme list`
+        name: String,
+    },
+
+    /// Show the active theme's image for a state (e.g. `rainy`, `night`) right away
+    Preview {
+        /// Weather key from `[weather]`, or `day`/`night` from `[timeConfig]`
+        #[arg(long)]
+        state: String,
+    },
+
     /// Activate a theme by name
     Set {
         /// Theme name as shown by `wallman theme list`
         name: String,
+        /// Also switch the running daemon now, without a full config reload
+        #[arg(long, hide = !crate::daemon::CAN_SET_POOL)]
+        apply: bool,
     },
 
     /// Remove an installed theme
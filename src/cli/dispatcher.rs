@@ -1,14 +1,16 @@
 use std::{fs, path::PathBuf, process};
 
 use crate::{
-    Config,
+    CURRENT_CONFIG_VERSION, Config, WallmanError,
     cli::{
         app::Command,
         commands::{CompletionCommand, ConfigCommand, DaemonCommand, PackCommand, ThemeCommand},
+        say, say_err,
     },
-    constants::{config_folder, decompresion_folder},
+    constants::decompresion_folder,
     daemon::DaemonManager,
-    format::{install::PackInstaller, pack::Packager},
+    format::{download, install::PackInstaller, pack::Packager},
+    wallpaper::missing_binaries,
 };
 use clap::CommandFactory;
 
@@ -19,6 +21,27 @@ pub enum ExitCode {
     InvalidConfig = 2,
     PackError = 3,
     DaemonError = 4,
+    AlreadyRunning = 5,
+}
+
+impl From<&WallmanError> for ExitCode {
+    fn from(e: &WallmanError) -> Self {
+        match e {
+            WallmanError::Config(_) => ExitCode::InvalidConfig,
+            WallmanError::Pack(_) => ExitCode::PackError,
+            WallmanError::Daemon(_) => ExitCode::DaemonError,
+            WallmanError::AlreadyRunning { .. } => ExitCode::AlreadyRunning,
+            WallmanError::Io(_) | WallmanError::Network(_) | WallmanError::Output(_) => {
+                ExitCode::Error
+            }
+        }
+    }
+}
+
+/// Turn a library error into CLI output, with the exit code for its kind.
+fn fail(e: WallmanError) -> (String, ExitCode) {
+    let code = ExitCode::from(&e);
+    (format!("Error: {e}"), code)
 }
 
 /// Route a parsed `Command` to the appropriate service function.
@@ -28,13 +51,175 @@ pub enum ExitCode {
 pub fn dispatch(command: Command) -> Result<(), (String, ExitCode)> {
     match command {
         Command::Theme { sub } => dispatch_theme(sub),
-        Command::Daemon { sub } => dispatch_daemon(sub),
+        Command::Daemon { pid_file, sub } => dispatch_daemon(sub, pid_file),
         Command::Config { sub } => dispatch_config(sub),
         Command::Pack { sub } => dispatch_pack(sub),
+        Command::Set { image, output } => set(image, output),
+        Command::Outputs { json } => outputs(json),
+        Command::Doctor => doctor(),
+        Command::Explain => explain(),
+        Command::Images => images(),
         Command::Completion { sub } => dispatch_completion(sub),
     }
 }
 
+// ── Set ───────────────────────────────────────────────────────────────────────
+
+fn set(image: PathBuf, output: String) -> Result<(), (String, ExitCode)> {
+    let target = crate::OutputTarget::parse(&output);
+    let fill = crate::APP_STATE
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .config
+        .base_fill_mode();
+    crate::daemon::DaemonManager::new()
+        .set_wallpaper(&target, &image, fill)
+        .map_err(fail)?;
+    say!("Set {} on {}", image.display(), output);
+    Ok(())
+}
+
+// ── Outputs ───────────────────────────────────────────────────────────────────
+
+fn outputs(json: bool) -> Result<(), (String, ExitCode)> {
+    let outputs = crate::OutputResolver::detect_info().map_err(fail)?;
+
+    if json {
+        let body = serde_json::to_string_pretty(&outputs).map_err(|e| fail(e.into()))?;
+        println!("{body}");
+        return Ok(());
+    }
+    if outputs.is_empty() {
+        return Err((
+            "Error: no active outputs detected (is sway running?)".to_string(),
+            ExitCode::Error,
+        ));
+    }
+    for output in &outputs {
+        let size = output.geometry.map_or("-".to_string(), |g| {
+            format!("{}x{} @{}x", g.width, g.height, g.scale)
+        });
+        let model = [output.make.as_deref(), output.model.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!("{:<12}{:<18}{}", output.name, size, model);
+    }
+    Ok(())
+}
+
+// ── Doctor ────────────────────────────────────────────────────────────────────
+
+fn doctor() -> Result<(), (String, ExitCode)> {
+    use crate::doctor::CheckStatus;
+
+    let checks = crate::doctor::run(&active_config_path());
+    for check in &checks {
+        let mark = match check.status {
+            CheckStatus::Pass => " ok ",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "FAIL",
+        };
+        println!("[{}] {:<12}{}", mark, check.name, check.detail);
+    }
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    let failed = count(CheckStatus::Fail);
+    println!(
+        "\n{} passed, {} warning(s), {} failed",
+        count(CheckStatus::Pass),
+        count(CheckStatus::Warn),
+        failed
+    );
+    if failed > 0 {
+        return Err((format!("Error: {failed} check(s) failed"), ExitCode::Error));
+    }
+    Ok(())
+}
+
+fn explain() -> Result<(), (String, ExitCode)> {
+    let (config, config_path) = {
+        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+        (state.config.clone(), state.config_path.clone())
+    };
+    let detected = crate::OutputResolver::detect().map_err(fail)?;
+    let explanation = crate::explain::explain(&config, detected.outputs());
+
+    println!("Config:   {}", config_path);
+    if let Some(pool) = config.primary_pool() {
+        println!("Theme:    {} (merged)", pool);
+    }
+    let priority: Vec<String> = crate::TriggerKind::PRIORITY
+        .iter()
+        .map(|k| k.section().to_string())
+        .collect();
+    println!("Priority: {}", priority.join(" > "));
+    println!("Sections:");
+    for section in &explanation.sections {
+        let status = match &section.outputs {
+            None => "not configured".to_string(),
+            Some(_) if section.kind == explanation.selected => "selected".to_string(),
+            Some(_) => "ignored (lower priority)".to_string(),
+        };
+        let outputs = section.outputs.as_deref().unwrap_or_default().join(", ");
+        let line = format!("  {:<12}{:<26}{}", section.kind.section(), status, outputs);
+        println!("{}", line.trim_end());
+    }
+    println!(
+        "Trigger:  {} — {}",
+        explanation.selected.name(),
+        explanation.reason
+    );
+
+    println!("Outputs:");
+    if explanation.outputs.is_empty() {
+        println!("  (none detected)");
+    }
+    for plan in &explanation.outputs {
+        match &plan.key {
+            Some(key) => println!(
+                "  {:<12}[{}.\"{}\"]",
+                plan.output,
+                explanation.selected.section(),
+                key
+            ),
+            None => println!("  {:<12}not covered — skipped", plan.output),
+        }
+    }
+    Ok(())
+}
+
+fn images() -> Result<(), (String, ExitCode)> {
+    let resolver = crate::OutputResolver::detect().map_err(fail)?;
+    let listed = {
+        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+        crate::images::list(&state, &resolver)
+    };
+
+    if listed.is_empty() {
+        println!("No outputs detected.");
+    }
+    for output in &listed {
+        println!("{}:", output.output);
+        if output.entries.is_empty() {
+            println!("  (no configured section covers this output)");
+        }
+        for entry in &output.entries {
+            println!(
+                "  {:<12}{:<20}{:<9}{}",
+                entry.section.section(),
+                entry.state,
+                if entry.exists { "ok" } else { "missing" },
+                entry.resolved
+            );
+        }
+    }
+    Ok(())
+}
+
 // ── Theme ─────────────────────────────────────────────────────────────────────
 
 fn dispatch_theme(cmd: ThemeCommand) -> Result<(), (String, ExitCode)> {
@@ -43,7 +228,10 @@ fn dispatch_theme(cmd: ThemeCommand) -> Result<(), (String, ExitCode)> {
         ThemeCommand::Pack { path, output } => theme_pack(path, output),
         ThemeCommand::Install { file } => theme_install(file),
         ThemeCommand::List => theme_list(),
-        ThemeCommand::Set { name } => theme_set(name),
+        ThemeCommand::Info { name } => theme_info(name),
+        ThemeCommand::Deps { name } => theme_deps(name),
+        ThemeCommand::Preview { state } => theme_preview(&state),
+        ThemeCommand::Set { name, apply } => theme_set(name, apply),
         ThemeCommand::Remove { name } => theme_remove(name),
     }
 }
@@ -67,9 +255,11 @@ fn theme_create(path: String, name: Option<String>) -> Result<(), (String, ExitC
     })?;
 
     // Write a minimal manifest.toml (serialized from Config::default()).
-    let mut default_cfg = Config::default();
-    default_cfg.name = Some(theme_name.clone());
-    default_cfg.description = Some("A wallman theme".to_string());
+    let default_cfg = Config {
+        name: Some(theme_name.clone()),
+        description: Some("A wallman theme".to_string()),
+        ..Config::default()
+    };
 
     let manifest_path = dir.join("manifest.toml");
     default_cfg.save_to_file(&manifest_path).map_err(|e| {
@@ -79,12 +269,12 @@ fn theme_create(path: String, name: Option<String>) -> Result<(), (String, ExitC
         )
     })?;
 
-    println!("Theme '{}' created at {}", theme_name, dir.display());
-    println!(
+    say!("Theme '{}' created at {}", theme_name, dir.display());
+    say!(
         "  Place your wallpaper images inside:  {}",
         images_dir.display()
     );
-    println!(
+    say!(
         "  Edit the manifest:                   {}",
         manifest_path.display()
     );
@@ -103,6 +293,15 @@ fn theme_pack(path: String, output: Option<String>) -> Result<(), (String, ExitC
         )
     })?;
 
+    // `-` streams the pack to stdout; the message goes to stderr to keep the stream clean.
+    if output.as_deref() == Some("-") {
+        Packager::new(config, &dir)
+            .pack_to(std::io::stdout().lock())
+            .map_err(|e| (format!("Pack error: {e}"), ExitCode::PackError))?;
+        say_err!("Theme packed → stdout");
+        return Ok(());
+    }
+
     let out_path = output.map(PathBuf::from).unwrap_or_else(|| {
         let stem = config.name.clone().unwrap_or_else(|| "theme".to_string());
         PathBuf::from(format!("{}.wallman", stem.replace(" ", "-")))
@@ -113,17 +312,39 @@ fn theme_pack(path: String, output: Option<String>) -> Result<(), (String, ExitC
         .pack(&out_path)
         .map_err(|e| (format!("Pack error: {e}"), ExitCode::PackError))?;
 
-    println!("Theme packed → {}", out_path.display());
+    say!("Theme packed → {}", out_path.display());
     Ok(())
 }
 
 fn theme_install(file: String) -> Result<(), (String, ExitCode)> {
-    let mut installer = PackInstaller::new(&file);
-    installer
-        .install()
-        .map_err(|e| (format!("Error: {e}"), ExitCode::PackError))?;
-
-    println!("Theme installed successfully from {}", file);
+    // Keep the download alive until the install is done; dropping it deletes it.
+    let download = if download::is_url(&file) {
+        Some(download::download_pack(&file).map_err(|e| {
+            let code = ExitCode::from(&e);
+            (format!("Error: could not download '{}': {e}", file), code)
+        })?)
+    } else {
+        None
+    };
+    let source = download
+        .as_ref()
+        .map_or_else(|| PathBuf::from(&file), |d| d.path().to_path_buf());
+
+    let mut installer = PackInstaller::new(&source);
+    installer.install().map_err(fail)?;
+
+    say!("Theme installed successfully from {}", file);
+    let requires = Config::load(installer.installed_dir().join("manifest.toml"))
+        .ok()
+        .and_then(|manifest| manifest.requires)
+        .unwrap_or_default();
+    let missing = missing_binaries(&requires);
+    if !missing.is_empty() {
+        eprintln!(
+            "Warning: this theme requires tools not found in PATH: {}",
+            missing.join(", ")
+        );
+    }
     Ok(())
 }
 
@@ -171,7 +392,77 @@ fn theme_list() -> Result<(), (String, ExitCode)> {
     Ok(())
 }
 
-fn theme_set(name: String) -> Result<(), (String, ExitCode)> {
+fn theme_info(name: String) -> Result<(), (String, ExitCode)> {
+    let theme_dir = decompresion_folder().join(&name);
+    if !theme_dir.exists() {
+        return Err((
+            format!(
+                "Error: theme '{}' is not installed. Run `wallman theme list` to see available themes.",
+                name
+            ),
+            ExitCode::Error,
+        ));
+    }
+
+    let manifest = Config::load(theme_dir.join("manifest.toml")).map_err(|e| {
+        (
+            format!("Error: manifest.toml not found or invalid: {e}"),
+            ExitCode::InvalidConfig,
+        )
+    })?;
+
+    let field = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+    println!("Name:         {}", field(&manifest.name));
+    println!("Description:  {}", field(&manifest.description));
+    println!("Author:       {}", field(&manifest.author));
+    println!("License:      {}", field(&manifest.license));
+    println!(
+        "Version:      {}",
+        manifest.version.map_or("-".to_string(), |v| v.to_string())
+    );
+    println!("Location:     {}", theme_dir.display());
+
+    let active = manifest.trigger_kind();
+    println!("Triggers:");
+    for kind in crate::TriggerKind::PRIORITY {
+        let Some(outputs) = manifest.section_outputs(kind) else {
+            continue;
+        };
+        let marker = if kind == active { " (active)" } else { "" };
+        println!("  {:<12}{}{}", kind.section(), outputs.join(", "), marker);
+    }
+
+    // Count images in the images folder when present, otherwise everything but the manifest.
+    let (files, total) = dir_usage(&theme_dir);
+    let images_dir = theme_dir.join(manifest.images_dir());
+    let images = if images_dir.is_dir() {
+        dir_usage(&images_dir).0
+    } else {
+        files.saturating_sub(1)
+    };
+    println!("Images:       {}", images);
+    println!("Size on disk: {:.1} MiB", total as f64 / (1024.0 * 1024.0));
+    Ok(())
+}
+
+/// File count and total size in bytes of everything below `dir`.
+fn dir_usage(dir: &std::path::Path) -> (usize, u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    entries
+        .flatten()
+        .fold((0, 0), |(count, size), entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => {
+                let (c, s) = dir_usage(&entry.path());
+                (count + c, size + s)
+            }
+            Ok(meta) => (count + 1, size + meta.len()),
+            Err(_) => (count, size),
+        })
+}
+
+fn theme_deps(name: String) -> Result<(), (String, ExitCode)> {
     let theme_dir = decompresion_folder().join(&name);
     if !theme_dir.exists() {
         return Err((
@@ -183,10 +474,137 @@ fn theme_set(name: String) -> Result<(), (String, ExitCode)> {
         ));
     }
 
+    let manifest = Config::load(theme_dir.join("manifest.toml")).map_err(|e| {
+        (
+            format!("Error: manifest.toml not found or invalid: {e}"),
+            ExitCode::InvalidConfig,
+        )
+    })?;
+
+    let requires = manifest.requires.unwrap_or_default();
+    if requires.is_empty() {
+        println!("Theme '{}' does not require any external tools.", name);
+        return Ok(());
+    }
+    let missing = missing_binaries(&requires);
+    for binary in &requires {
+        let status = if missing.contains(binary) {
+            "missing"
+        } else {
+            "ok"
+        };
+        println!("{:<8} {}", status, binary);
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err((
+            format!(
+                "Error: theme '{}' requires tools not found in PATH: {}",
+                name,
+                missing.join(", ")
+            ),
+            ExitCode::Error,
+        ))
+    }
+}
+
+fn theme_preview(state_name: &str) -> Result<(), (String, ExitCode)> {
+    let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+    let images = state.config.state_images(state_name);
+    if images.is_empty() {
+        let valid = state.config.preview_states();
+        return Err((
+            if valid.is_empty() {
+                "Error: the active config has no [weather] or [timeConfig] states to preview."
+                    .to_string()
+            } else {
+                format!(
+                    "Error: unknown state '{}'. Valid states: {}",
+                    state_name,
+                    valid.join(", ")
+                )
+            },
+            ExitCode::InvalidConfig,
+        ));
+    }
+
+    let resolver = crate::OutputResolver::detect().map_err(|e| {
+        (
+            format!("Error: could not detect outputs: {e}"),
+            ExitCode::Error,
+        )
+    })?;
+    let changes: Vec<crate::trigger::OutputChange> = resolver
+        .resolve_map(&images)
+        .into_iter()
+        .map(|(output, image)| crate::trigger::OutputChange {
+            image_path: state.resolve_image_for_output(&output, &image, resolver.geometry(&output)),
+            output,
+            fill_mode: state.config.base_fill_mode(),
+        })
+        .collect();
+    drop(state);
+
+    if changes.is_empty() {
+        return Err((
+            format!(
+                "Error: no detected output has an image for '{}'.",
+                state_name
+            ),
+            ExitCode::Error,
+        ));
+    }
+    for change in &changes {
+        say!("{} → {}", change.output, change.image_path);
+    }
+    let report =
+        crate::wallpaper::apply::apply(crate::trigger::TriggerResult { changes }).map_err(fail)?;
+    let failed: Vec<String> = report
+        .failed()
+        .map(|(output, error)| format!("{output}: {error}"))
+        .collect();
+    if failed.is_empty() {
+        return Ok(());
+    }
+    Err((
+        format!(
+            "Error: {} of {} output(s) failed — {}",
+            failed.len(),
+            report.outputs.len(),
+            failed.join("; ")
+        ),
+        ExitCode::Error,
+    ))
+}
+
+fn theme_set(name: String, apply: bool) -> Result<(), (String, ExitCode)> {
+    let theme_dir = decompresion_folder().join(&name);
+    if !theme_dir.exists() {
+        return Err((
+            format!(
+                "Error: theme '{}' is not installed. Run `wallman theme list` to see available themes.",
+                name
+            ),
+            ExitCode::Error,
+        ));
+    }
+    // Refuse before touching the config rather than saving and then failing.
+    if apply && !crate::daemon::CAN_SET_POOL {
+        return Err((
+            "Error: --apply needs a build with `--features dbus`; \
+             run `wallman theme set` without it and restart the daemon"
+                .to_string(),
+            ExitCode::Error,
+        ));
+    }
+
     // Update the user config to point at this theme.
     let state_arc = crate::APP_STATE.get().unwrap().clone();
     let mut state = state_arc.lock().unwrap();
-    state.config.pool = Some(theme_dir.to_string_lossy().to_string());
+    state.config.pool = Some(crate::PoolConfig::Single(
+        theme_dir.to_string_lossy().to_string(),
+    ));
     state.save_config().map_err(|e| {
         (
             format!("Error: could not save config: {e}"),
@@ -195,8 +613,13 @@ fn theme_set(name: String) -> Result<(), (String, ExitCode)> {
     })?;
     drop(state);
 
-    println!("Active theme set to '{}'.", name);
-    println!("Run `wallman daemon restart` for the change to take effect.");
+    say!("Active theme set to '{}'.", name);
+    if apply {
+        crate::daemon::request_set_pool(&name).map_err(fail)?;
+        say!("Running daemon switched to '{}'.", name);
+    } else {
+        say!("Run `wallman daemon restart` for the change to take effect.");
+    }
     Ok(())
 }
 
@@ -216,38 +639,86 @@ fn theme_remove(name: String) -> Result<(), (String, ExitCode)> {
         )
     })?;
 
-    println!("Theme '{}' removed.", name);
+    say!("Theme '{}' removed.", name);
     Ok(())
 }
 
 // ── Daemon ────────────────────────────────────────────────────────────────────
 
-fn dispatch_daemon(cmd: DaemonCommand) -> Result<(), (String, ExitCode)> {
-    let dm = DaemonManager::new();
+fn dispatch_daemon(
+    cmd: DaemonCommand,
+    pid_file: Option<PathBuf>,
+) -> Result<(), (String, ExitCode)> {
+    let dm = pid_file
+        .map(DaemonManager::with_pid_file)
+        .unwrap_or_default();
     match cmd {
-        DaemonCommand::Start { foreground } => dm
-            .start(foreground)
-            .map_err(|e| (format!("Error: {e}"), ExitCode::DaemonError)),
-        DaemonCommand::Stop => dm
-            .stop()
-            .map_err(|e| (format!("Error: {e}"), ExitCode::DaemonError)),
-        DaemonCommand::Restart => dm
-            .restart()
-            .map_err(|e| (format!("Error: {e}"), ExitCode::DaemonError)),
-        DaemonCommand::Status => dm
-            .status()
-            .map_err(|e| (format!("Error: {e}"), ExitCode::DaemonError)),
+        DaemonCommand::Start { foreground, force } => dm.start(foreground, force).map_err(fail),
+        DaemonCommand::Stop => dm.stop().map_err(fail),
+        DaemonCommand::Restart { force, if_running } => dm.restart(force, if_running).map_err(fail),
+        DaemonCommand::Status { json } => dm.status(json).map_err(fail),
+        DaemonCommand::InstallService { force, enable } => {
+            crate::daemon::service::install_service(force, enable).map_err(|e| {
+                (
+                    format!("Error installing service: {e}"),
+                    ExitCode::DaemonError,
+                )
+            })
+        }
+        DaemonCommand::UninstallService => {
+            crate::daemon::service::uninstall_service().map_err(|e| {
+                (
+                    format!("Error uninstalling service: {e}"),
+                    ExitCode::DaemonError,
+                )
+            })
+        }
     }
 }
 
 // ── Config ────────────────────────────────────────────────────────────────────
 
+/// The config file this invocation works on: `--config` or the discovered one.
+fn active_config_path() -> PathBuf {
+    let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+    PathBuf::from(&state.config_path)
+}
+
+/// The config as read from stdin, when running with `--config -`.
+fn stdin_config() -> Option<Config> {
+    let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+    (state.config_path == crate::STDIN_CONFIG)
+        .then(|| state.user_config.clone())
+        .flatten()
+}
+
+/// `active_config_path` for commands that write the file; an error when the
+/// config came from stdin.
+fn writable_config_path(command: &str) -> Result<PathBuf, (String, ExitCode)> {
+    let cfg_path = active_config_path();
+    if cfg_path.as_os_str() == crate::STDIN_CONFIG {
+        return Err((
+            format!(
+                "Error: `wallman config {}` needs a config file; the config was read from stdin (--config -)",
+                command
+            ),
+            ExitCode::InvalidConfig,
+        ));
+    }
+    Ok(cfg_path)
+}
+
 fn dispatch_config(cmd: ConfigCommand) -> Result<(), (String, ExitCode)> {
     match cmd {
-        ConfigCommand::Init => config_init(),
-        ConfigCommand::Edit => config_edit(),
-        ConfigCommand::Validate => config_validate(),
+        ConfigCommand::Init { interactive: false } => config_init(),
+        ConfigCommand::Init { interactive: true } => config_init_interactive(),
+        ConfigCommand::Edit { validate } => config_edit(validate),
+        ConfigCommand::Validate { strict } => config_validate(strict),
         ConfigCommand::Path => config_path(),
+        ConfigCommand::Show => config_show(),
+        ConfigCommand::Migrate => config_migrate(),
+        ConfigCommand::Get { key } => config_get(&key),
+        ConfigCommand::Set { key, value } => config_set(&key, &value),
         ConfigCommand::SetLat { value } => config_set_lat(value),
         ConfigCommand::SetLon { value } => config_set_lon(value),
         ConfigCommand::SetDayRange { value } => config_set_day_range(value),
@@ -255,10 +726,10 @@ fn dispatch_config(cmd: ConfigCommand) -> Result<(), (String, ExitCode)> {
 }
 
 fn config_init() -> Result<(), (String, ExitCode)> {
-    let cfg_path = config_folder().join("config.toml");
+    let cfg_path = writable_config_path("init")?;
 
     if cfg_path.exists() {
-        println!("Config already exists at {}", cfg_path.display());
+        say!("Config already exists at {}", cfg_path.display());
         return Ok(());
     }
 
@@ -269,12 +740,52 @@ fn config_init() -> Result<(), (String, ExitCode)> {
         )
     })?;
 
-    println!("Config initialised at {}", cfg_path.display());
+    say!("Config initialised at {}", cfg_path.display());
     Ok(())
 }
 
-fn config_edit() -> Result<(), (String, ExitCode)> {
-    let cfg_path = config_folder().join("config.toml");
+fn config_init_interactive() -> Result<(), (String, ExitCode)> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        say_err!("stdin is not a terminal; writing the default config instead");
+        return config_init();
+    }
+    let cfg_path = writable_config_path("init")?;
+    if cfg_path.exists() {
+        say!(
+            "Config already exists at {} (use `wallman config edit` to change it)",
+            cfg_path.display()
+        );
+        return Ok(());
+    }
+
+    let detected = match crate::OutputResolver::detect() {
+        Ok(resolver) => resolver.outputs().to_vec(),
+        Err(e) => {
+            say_err!("Could not detect outputs ({e}); the config will apply to every output");
+            Vec::new()
+        }
+    };
+    let answers =
+        crate::cli::setup::ask(&detected).map_err(|e| (format!("Error: {e}"), ExitCode::Error))?;
+
+    answers.to_config().save_to_file(&cfg_path).map_err(|e| {
+        (
+            format!("Error: could not write config: {e}"),
+            ExitCode::Error,
+        )
+    })?;
+    say!("Config initialised at {}", cfg_path.display());
+
+    if confirm("Start the daemon now?")? {
+        DaemonManager::new().start(false, false).map_err(fail)?;
+    }
+    Ok(())
+}
+
+fn config_edit(validate: bool) -> Result<(), (String, ExitCode)> {
+    let cfg_path = writable_config_path("edit")?;
 
     // Ensure the file exists first.
     if !cfg_path.exists() {
@@ -285,28 +796,118 @@ fn config_edit() -> Result<(), (String, ExitCode)> {
         .or_else(|_| std::env::var("VISUAL"))
         .unwrap_or_else(|_| "nano".to_string());
 
-    let status = process::Command::new(&editor)
-        .arg(&cfg_path)
-        .status()
-        .map_err(|e| {
-            (
-                format!("Error: could not launch editor '{}': {e}", editor),
+    loop {
+        let status = process::Command::new(&editor)
+            .arg(&cfg_path)
+            .status()
+            .map_err(|e| {
+                (
+                    format!("Error: could not launch editor '{}': {e}", editor),
+                    ExitCode::Error,
+                )
+            })?;
+
+        if !status.success() {
+            return Err((
+                format!("Editor '{}' exited with non-zero status.", editor),
                 ExitCode::Error,
-            )
-        })?;
+            ));
+        }
 
-    if !status.success() {
+        if !validate {
+            return Ok(());
+        }
+        // Like visudo: keep editing until the file is valid or the user gives up.
+        let (message, code) = match config_validate(false) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        eprintln!("{message}");
+        if !confirm("Edit again?")? {
+            return Err((
+                format!("Error: {} was left invalid", cfg_path.display()),
+                code,
+            ));
+        }
+    }
+}
+
+/// Ask a yes/no question on stderr; an empty answer means yes.
+fn confirm(question: &str) -> Result<bool, (String, ExitCode)> {
+    eprint!("{question} [Y/n] ");
+    let _ = std::io::Write::flush(&mut std::io::stderr());
+    let mut answer = String::new();
+    // A closed stdin counts as "no" so the loop cannot spin forever.
+    let read = std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| fail(e.into()))?;
+    Ok(read > 0 && matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes"))
+}
+
+fn config_validate(strict: bool) -> Result<(), (String, ExitCode)> {
+    let (cfg_path, config) = load_active_config()?;
+
+    let pool = config.primary_pool();
+    let state = crate::AppState::new(
+        config,
+        cfg_path.to_string_lossy().to_string(),
+        pool.clone(),
+        pool.is_some(),
+    )
+    .map_err(fail)?;
+    let problems: Vec<String> = state
+        .config
+        .trigger_conflict()
+        .into_iter()
+        .chain(state.image_problems())
+        .collect();
+    for problem in &problems {
+        eprintln!("{}: {problem}", if strict { "Error" } else { "Warning" });
+    }
+    if let Some(outputs) = &state.config.outputs {
+        say_err!(
+            "Note: output detection is off; using the fixed list {}",
+            outputs.join(", ")
+        );
+    }
+    if strict && !problems.is_empty() {
         return Err((
-            format!("Editor '{}' exited with non-zero status.", editor),
-            ExitCode::Error,
+            format!("Error: {} problem(s) found", problems.len()),
+            ExitCode::InvalidConfig,
         ));
     }
 
+    say!("Config is valid.");
     Ok(())
 }
 
-fn config_validate() -> Result<(), (String, ExitCode)> {
-    let cfg_path = config_folder().join("config.toml");
+fn config_path() -> Result<(), (String, ExitCode)> {
+    let cfg_path = active_config_path();
+    println!("{}", cfg_path.display());
+    Ok(())
+}
+
+fn config_show() -> Result<(), (String, ExitCode)> {
+    let (cfg_path, raw) = load_active_config()?;
+    let mut effective = raw.clone();
+    if let Some(pool) = raw.primary_pool() {
+        effective.merge_theme(PathBuf::from(pool)).map_err(fail)?;
+    }
+    let from_theme = raw.changed_keys(&effective).map_err(fail)?;
+    let toml = toml::to_string_pretty(&effective).map_err(|e| fail(e.into()))?;
+
+    println!("# Effective config for {}", cfg_path.display());
+    match raw.primary_pool() {
+        None => println!("# No theme active; this is the file as loaded."),
+        Some(pool) if from_theme.is_empty() => println!("# Theme {pool} changes nothing."),
+        Some(pool) => println!("# From theme {pool}: {}", from_theme.join(", ")),
+    }
+    print!("{toml}");
+    Ok(())
+}
+
+fn config_migrate() -> Result<(), (String, ExitCode)> {
+    let cfg_path = writable_config_path("migrate")?;
 
     if !cfg_path.exists() {
         return Err((
@@ -318,26 +919,87 @@ fn config_validate() -> Result<(), (String, ExitCode)> {
         ));
     }
 
-    Config::load(cfg_path).map_err(|e| {
+    let from = Config::migrate_file(&cfg_path).map_err(|e| {
+        (
+            format!("Error: could not migrate config — {e}"),
+            ExitCode::InvalidConfig,
+        )
+    })?;
+
+    if from < CURRENT_CONFIG_VERSION {
+        say!(
+            "Config migrated from version {} to {}.",
+            from,
+            CURRENT_CONFIG_VERSION
+        );
+    } else {
+        say!("Config is already at version {}.", CURRENT_CONFIG_VERSION);
+    }
+    Ok(())
+}
+
+/// Load the active config file itself (without theme merging) for key edits,
+/// or the config read from stdin.
+fn load_active_config() -> Result<(PathBuf, Config), (String, ExitCode)> {
+    let cfg_path = active_config_path();
+    if let Some(config) = stdin_config() {
+        return Ok((cfg_path, config));
+    }
+    if !cfg_path.exists() {
+        return Err((
+            format!(
+                "Error: config not found at {}. Run `wallman config init` to create one.",
+                cfg_path.display()
+            ),
+            ExitCode::InvalidConfig,
+        ));
+    }
+    let config = Config::load(cfg_path.clone()).map_err(|e| {
         (
             format!("Error: invalid config — {e}"),
             ExitCode::InvalidConfig,
         )
     })?;
+    Ok((cfg_path, config))
+}
 
-    println!("Config is valid.");
+fn config_get(key: &str) -> Result<(), (String, ExitCode)> {
+    let (_, config) = load_active_config()?;
+    let value = config
+        .get_key(key)
+        .map_err(|e| (format!("Error: {e}"), ExitCode::InvalidConfig))?;
+
+    // Print scalars bare so scripts can use the output directly.
+    match value {
+        serde_json::Value::String(s) => println!("{}", s),
+        serde_json::Value::Object(_) | serde_json::Value::Array(_) => println!(
+            "{}",
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        ),
+        other => println!("{}", other),
+    }
     Ok(())
 }
 
-fn config_path() -> Result<(), (String, ExitCode)> {
-    let cfg_path = config_folder().join("config.toml");
-    println!("{}", cfg_path.display());
+fn config_set(key: &str, value: &str) -> Result<(), (String, ExitCode)> {
+    let (cfg_path, mut config) = load_active_config()?;
+    config
+        .set_key(key, value)
+        .map_err(|e| (format!("Error: {e}"), ExitCode::InvalidConfig))?;
+    config.save_to_file(&cfg_path).map_err(|e| {
+        (
+            format!("Error: could not save config: {e}"),
+            ExitCode::InvalidConfig,
+        )
+    })?;
+
+    say!("{} = {}", key, value);
     Ok(())
 }
 
 fn config_set_lat(value: f64) -> Result<(), (String, ExitCode)> {
     // Validate latitude range
-    if value < -90.0 || value > 90.0 {
+    if !(-90.0..=90.0).contains(&value) {
         return Err((
             "Error: latitude must be between -90 and 90".to_string(),
             ExitCode::InvalidConfig,
@@ -355,14 +1017,14 @@ fn config_set_lat(value: f64) -> Result<(), (String, ExitCode)> {
     })?;
     drop(state);
 
-    println!("Latitude set to {}", value);
-    println!("Run `wallman daemon restart` for the change to take effect.");
+    say!("Latitude set to {}", value);
+    say!("Run `wallman daemon restart` for the change to take effect.");
     Ok(())
 }
 
 fn config_set_lon(value: f64) -> Result<(), (String, ExitCode)> {
     // Validate longitude range
-    if value < -180.0 || value > 180.0 {
+    if !(-180.0..=180.0).contains(&value) {
         return Err((
             "Error: longitude must be between -180 and 180".to_string(),
             ExitCode::InvalidConfig,
@@ -380,37 +1042,13 @@ fn config_set_lon(value: f64) -> Result<(), (String, ExitCode)> {
     })?;
     drop(state);
 
-    println!("Longitude set to {}", value);
-    println!("Run `wallman daemon restart` for the change to take effect.");
+    say!("Longitude set to {}", value);
+    say!("Run `wallman daemon restart` for the change to take effect.");
     Ok(())
 }
 
 fn config_set_day_range(value: String) -> Result<(), (String, ExitCode)> {
-    // Validate day_range format (expecting HH-HH format)
-    let parts: Vec<&str> = value.split('-').collect();
-    if parts.len() != 2 {
-        return Err((
-            "Error: day_range must be in HH-HH format (e.g., 06-18)".to_string(),
-            ExitCode::InvalidConfig,
-        ));
-    }
-
-    let start_hour: Result<u32, _> = parts[0].parse();
-    let end_hour: Result<u32, _> = parts[1].parse();
-
-    if let (Ok(start), Ok(end)) = (start_hour, end_hour) {
-        if start > 23 || end > 23 {
-            return Err((
-                "Error: hour values must be between 0 and 23".to_string(),
-                ExitCode::InvalidConfig,
-            ));
-        }
-    } else {
-        return Err((
-            "Error: day_range must be in HH-HH format (e.g., 06-18)".to_string(),
-            ExitCode::InvalidConfig,
-        ));
-    }
+    crate::parse_day_range(&value).map_err(fail)?;
 
     let display_value = value.clone();
     let state_arc = crate::APP_STATE.get().unwrap().clone();
@@ -424,8 +1062,8 @@ fn config_set_day_range(value: String) -> Result<(), (String, ExitCode)> {
     })?;
     drop(state);
 
-    println!("Day range set to {}", display_value);
-    println!("Run `wallman daemon restart` for the change to take effect.");
+    say!("Day range set to {}", display_value);
+    say!("Run `wallman daemon restart` for the change to take effect.");
     Ok(())
 }
 
@@ -434,12 +1072,27 @@ fn config_set_day_range(value: String) -> Result<(), (String, ExitCode)> {
 fn dispatch_pack(cmd: PackCommand) -> Result<(), (String, ExitCode)> {
     match cmd {
         PackCommand::Build { path, output } => theme_pack(path, output),
-        PackCommand::Inspect { file } => pack_inspect(file),
+        PackCommand::Inspect {
+            file,
+            images_only,
+            filter,
+        } => pack_inspect(file, images_only, filter),
+        PackCommand::Verify { file } => pack_verify(file),
+        PackCommand::Repack {
+            input,
+            output,
+            level,
+            store,
+        } => pack_repack(input, output, level, store),
     }
 }
 
-fn pack_inspect(file: String) -> Result<(), (String, ExitCode)> {
-    use std::fs::File;
+fn pack_inspect(
+    file: String,
+    images_only: bool,
+    filter: Option<String>,
+) -> Result<(), (String, ExitCode)> {
+    use std::{fs::File, io::Read};
     use tar::Archive;
     use zstd::Decoder;
 
@@ -449,29 +1102,121 @@ fn pack_inspect(file: String) -> Result<(), (String, ExitCode)> {
             ExitCode::PackError,
         )
     })?;
+    let compressed = f.metadata().map(|m| m.len()).unwrap_or(0);
 
     let decoder = Decoder::new(f).map_err(|e| (format!("Error: {e}"), ExitCode::PackError))?;
     let mut archive = Archive::new(decoder);
 
     println!("Contents of {}:", file);
-    println!("{:<50}  {}", "Entry", "Size (bytes)");
+    println!("{:<50}  Size (bytes)", "Entry");
     println!("{}", "-".repeat(62));
 
+    // Listed entries, and every entry for the compression ratio.
+    let (mut listed, mut listed_size) = (0usize, 0u64);
+    let (mut total, mut total_size) = (0usize, 0u64);
     for entry in archive
         .entries()
         .map_err(|e| (format!("Error reading pack: {e}"), ExitCode::PackError))?
     {
-        let entry = entry.map_err(|e| (format!("{e}"), ExitCode::PackError))?;
+        let mut entry = entry.map_err(|e| (format!("{e}"), ExitCode::PackError))?;
         let path = entry
             .path()
-            .map_err(|e| (format!("{e}"), ExitCode::PackError))?;
+            .map_err(|e| (format!("{e}"), ExitCode::PackError))?
+            .to_string_lossy()
+            .to_string();
         let size = entry.size();
-        println!("{:<50}  {}", path.display(), size);
+        total += 1;
+        total_size += size;
+
+        if filter
+            .as_deref()
+            .is_some_and(|pattern| !crate::format::pack::glob_matches(pattern, &path))
+        {
+            continue;
+        }
+        if images_only {
+            // Sniff the contents like `pack build` does, not the extension.
+            let mut head = Vec::new();
+            (&mut entry)
+                .take(64)
+                .read_to_end(&mut head)
+                .map_err(|e| (format!("{e}"), ExitCode::PackError))?;
+            if !entry.header().entry_type().is_file() || image::guess_format(&head).is_err() {
+                continue;
+            }
+        }
+        listed += 1;
+        listed_size += size;
+        println!("{:<50}  {}", path, size);
     }
 
+    println!("{}", "-".repeat(62));
+    let count = if listed == total {
+        format!("{} entries", total)
+    } else {
+        format!("{} of {} entries", listed, total)
+    };
+    println!("{:<50}  {}", count, listed_size);
+    let ratio = if compressed == 0 {
+        0.0
+    } else {
+        total_size as f64 / compressed as f64
+    };
+    println!(
+        "Compressed: {} bytes for {} uncompressed (ratio {:.2}:1)",
+        compressed, total_size, ratio
+    );
+
     Ok(())
 }
 
+fn pack_repack(
+    input: String,
+    output: String,
+    level: Option<i32>,
+    store: bool,
+) -> Result<(), (String, ExitCode)> {
+    let level = match level {
+        Some(level) if !store => level,
+        _ => crate::format::pack::store_level(),
+    };
+    crate::format::pack::repack(&input, &output, level).map_err(fail)?;
+    say!("Repacked {} into {} at level {}", input, output, level);
+    Ok(())
+}
+
+fn pack_verify(file: String) -> Result<(), (String, ExitCode)> {
+    let report = crate::format::verify::verify_pack(&file).map_err(|e| {
+        (
+            format!("Error: cannot read '{}': {e}", file),
+            ExitCode::PackError,
+        )
+    })?;
+
+    println!("Verifying {}:", file);
+    println!("  entries:  {}", report.entries);
+    if let Some(manifest) = &report.manifest {
+        println!(
+            "  manifest: {} ({} referenced image(s))",
+            manifest.name.as_deref().unwrap_or("unnamed"),
+            manifest.referenced_images().len()
+        );
+    }
+    for problem in &report.problems {
+        println!("  [FAIL] {}", problem);
+    }
+
+    if report.is_ok() {
+        println!("Pack OK.");
+        Ok(())
+    } else {
+        Err((
+            format!("Pack has {} problem(s).", report.problems.len()),
+            ExitCode::PackError,
+        ))
+    }
+}
+
 // ── Completion ────────────────────────────────────────────────────────────────
 
 fn dispatch_completion(cmd: CompletionCommand) -> Result<(), (String, ExitCode)> {
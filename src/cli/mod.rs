@@ -1,6 +1,44 @@
 pub mod app;
 pub mod commands;
 pub mod dispatcher;
+pub mod setup;
+
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub use app::Cli;
 pub use dispatcher::dispatch;
+
+/// When set, status messages (`say!`) are not printed. Errors still are.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable `--quiet`.
+pub fn set_quiet(enabled: bool) {
+    QUIET.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// `println!` for success/status messages, silenced by `--quiet`.
+///
+/// Output a command exists to produce (listings, `config get`, reports)
+/// keeps using `println!`.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !$crate::cli::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// `eprintln!` for progress and status notes on stderr, silenced by `--quiet`.
+macro_rules! say_err {
+    ($($arg:tt)*) => {
+        if !$crate::cli::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {say, say_err};
@@ -0,0 +1,288 @@
+//! `wallman config init --interactive`: a short first-run questionnaire.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    Config,
+    config::{BackgroundConfig, DayTimeConfig, PoolConfig, WeatherConfig, parse_day_range},
+};
+
+/// Weather keys offered by the wizard, in the order they are asked.
+const WEATHER_KEYS: [&str; 5] = ["sunny", "cloudy", "raining", "snowing", "lighting"];
+
+/// dayRange used when the user accepts the suggestion.
+const DEFAULT_DAY_RANGE: &str = "07-19";
+
+/// What switches the wallpaper once the daemon runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Switching {
+    None,
+    /// Day and night images, with the `dayRange` hours.
+    DayTime {
+        day: String,
+        night: String,
+        day_range: String,
+    },
+    /// Images per weather key, at a location.
+    Weather {
+        images: HashMap<String, String>,
+        lat: f64,
+        lon: f64,
+    },
+}
+
+/// Everything the wizard asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupAnswers {
+    /// Outputs to manage; empty means every output (`"*"`).
+    pub outputs: Vec<String>,
+    /// A theme pool directory, an image file or a `color:` spec.
+    pub wallpaper: Option<String>,
+    pub switching: Switching,
+}
+
+impl SetupAnswers {
+    /// Build the config these answers describe.
+    pub fn to_config(&self) -> Config {
+        let keys = if self.outputs.is_empty() {
+            vec!["*".to_string()]
+        } else {
+            self.outputs.clone()
+        };
+        let mut config = Config::default();
+        match &self.wallpaper {
+            Some(dir) if Path::new(dir).is_dir() => {
+                config.pool = Some(PoolConfig::Single(dir.clone()));
+            }
+            Some(image) => {
+                config.background = Some(per_output(&keys, || BackgroundConfig {
+                    image: Some(image.clone()),
+                    fill_mode: None,
+                    mode: None,
+                    blur_sigma: None,
+                    brightness: None,
+                    fallback_color: None,
+                }));
+            }
+            None => {}
+        }
+        match &self.switching {
+            Switching::None => {}
+            Switching::DayTime {
+                day,
+                night,
+                day_range,
+            } => {
+                config.day_range = Some(day_range.clone());
+                config.time_config = Some(per_output(&keys, || DayTimeConfig {
+                    day: day.clone(),
+                    night: night.clone(),
+                    rules: Vec::new(),
+                    fallback_color: None,
+                }));
+            }
+            Switching::Weather { images, lat, lon } => {
+                config.lat = Some(*lat);
+                config.lon = Some(*lon);
+                config.weather = Some(per_output(&keys, || WeatherConfig {
+                    weather: images.clone(),
+                    fallback_color: None,
+                    quiet_hours: None,
+                }));
+            }
+        }
+        config
+    }
+}
+
+/// The same entry under every key.
+fn per_output<T>(keys: &[String], value: impl Fn() -> T) -> HashMap<String, T> {
+    keys.iter().map(|key| (key.clone(), value())).collect()
+}
+
+/// Ask the setup questions on stderr, reading answers from stdin.
+///
+/// `detected` are the connected outputs offered as choices. Closing stdin
+/// (Ctrl-D) cancels with an `UnexpectedEof` error.
+pub fn ask(detected: &[String]) -> io::Result<SetupAnswers> {
+    let outputs = if detected.is_empty() {
+        Vec::new()
+    } else {
+        eprintln!("Detected outputs: {}", detected.join(", "));
+        loop {
+            let answer = prompt("Outputs to manage, comma-separated", "all")?;
+            if answer == "all" {
+                break Vec::new();
+            }
+            let chosen: Vec<String> = answer
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect();
+            match chosen.iter().find(|name| !detected.contains(name)) {
+                Some(unknown) => eprintln!("  '{}' is not a detected output", unknown),
+                None if chosen.len() == detected.len() => break Vec::new(),
+                None => break chosen,
+            }
+        }
+    };
+
+    let wallpaper = optional(prompt(
+        "Default wallpaper: an image, `color:#rrggbb` or a theme directory (blank to skip)",
+        "",
+    )?);
+    if let Some(path) = &wallpaper
+        && !path.starts_with("color:")
+        && !Path::new(path).exists()
+    {
+        eprintln!("  Note: '{}' does not exist yet", path);
+    }
+
+    let switching = loop {
+        match prompt(
+            "Switch wallpapers by [n]othing, [t]ime of day or [w]eather",
+            "n",
+        )?
+        .to_lowercase()
+        .as_str()
+        {
+            "n" | "nothing" | "none" => break Switching::None,
+            "t" | "time" => break ask_day_time()?,
+            "w" | "weather" => break ask_weather()?,
+            other => eprintln!("  Unknown choice '{}'", other),
+        }
+    };
+
+    Ok(SetupAnswers {
+        outputs,
+        wallpaper,
+        switching,
+    })
+}
+
+fn ask_day_time() -> io::Result<Switching> {
+    let day = required("Daytime image")?;
+    let night = required("Night image")?;
+    let day_range = loop {
+        let range = prompt("Daytime hours (dayRange)", DEFAULT_DAY_RANGE)?;
+        match parse_day_range(&range) {
+            Ok(_) => break range,
+            Err(e) => eprintln!("  {}", e),
+        }
+    };
+    Ok(Switching::DayTime {
+        day,
+        night,
+        day_range,
+    })
+}
+
+fn ask_weather() -> io::Result<Switching> {
+    let (lat, lon) = loop {
+        let answer = required("Location as `latitude, longitude`")?;
+        let parsed = answer
+            .split_once(',')
+            .and_then(|(lat, lon)| Some((lat.trim().parse().ok()?, lon.trim().parse().ok()?)));
+        match parsed {
+            Some((lat, lon))
+                if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) =>
+            {
+                break (lat, lon);
+            }
+            _ => eprintln!("  Expected something like `48.85, 2.35`"),
+        }
+    };
+    let mut images = HashMap::new();
+    for key in WEATHER_KEYS {
+        if let Some(image) = optional(prompt(&format!("Image when {} (blank to skip)", key), "")?) {
+            images.insert(key.to_string(), image);
+        }
+    }
+    Ok(Switching::Weather { images, lat, lon })
+}
+
+/// Ask `question`, returning the trimmed answer or `default` when blank.
+fn prompt(question: &str, default: &str) -> io::Result<String> {
+    if default.is_empty() {
+        eprint!("{question}: ");
+    } else {
+        eprint!("{question} [{default}]: ");
+    }
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer)? == 0 {
+        eprintln!();
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "setup cancelled",
+        ));
+    }
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// `prompt` without a default, asked again until the answer is not blank.
+fn required(question: &str) -> io::Result<String> {
+    loop {
+        let answer = prompt(question, "")?;
+        if !answer.is_empty() {
+            return Ok(answer);
+        }
+    }
+}
+
+fn optional(answer: String) -> Option<String> {
+    (!answer.is_empty()).then_some(answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answers_build_a_loadable_config() {
+        let dir = std::env::temp_dir().join("wallman_test_setup");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        let answers = SetupAnswers {
+            outputs: vec!["DP-1".into(), "HDMI-1".into()],
+            wallpaper: Some("color:#1e1e2e".into()),
+            switching: Switching::DayTime {
+                day: "day.png".into(),
+                night: "night.png".into(),
+                day_range: "08-20".into(),
+            },
+        };
+        answers.to_config().save_to_file(&path).unwrap();
+        let config = Config::load(path.clone()).unwrap();
+        assert_eq!(config.day_range.as_deref(), Some("08-20"));
+        let background = config.background.unwrap();
+        assert_eq!(background.len(), 2);
+        assert_eq!(background["HDMI-1"].image.as_deref(), Some("color:#1e1e2e"));
+        assert_eq!(config.time_config.unwrap()["DP-1"].night, "night.png");
+
+        // A directory becomes the theme pool; no outputs means every output.
+        let answers = SetupAnswers {
+            outputs: Vec::new(),
+            wallpaper: Some(dir.to_string_lossy().to_string()),
+            switching: Switching::Weather {
+                images: HashMap::from([("sunny".into(), "sun.png".into())]),
+                lat: 48.85,
+                lon: 2.35,
+            },
+        };
+        answers.to_config().save_to_file(&path).unwrap();
+        let config = Config::load(path).unwrap();
+        assert!(matches!(config.pool, Some(PoolConfig::Single(_))));
+        assert!(config.background.is_none());
+        assert_eq!(config.lat, Some(48.85));
+        assert_eq!(config.weather.unwrap()["*"].weather["sunny"], "sun.png");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
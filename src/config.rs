@@ -1,48 +1,548 @@
-use std::{collections::HashMap, fs::File, io::Read, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    error::{Result, WallmanError},
+    migration::CURRENT_CONFIG_VERSION,
+};
+
+/// Default theme image folder, relative to the theme root.
+pub const DEFAULT_IMAGES_DIR: &str = "images";
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
-    pub pool: Option<String>,
+    pub pool: Option<PoolConfig>, // Theme pool path, or [pool] per output
     pub version: Option<i32>,
     pub name: Option<String>,
     pub description: Option<String>,
+    pub author: Option<String>,  // Theme manifests only
+    pub license: Option<String>, // Theme manifests only
+    #[serde(alias = "images_dir")]
+    pub images_dir: Option<String>, // Theme manifests only, see `Config::images_dir`
+    pub requires: Option<Vec<String>>, // Theme manifests only: external tools the theme needs
     pub theme: Option<String>,
     pub background: Option<HashMap<String, BackgroundConfig>>, // [background.HDMI-1]
     pub time_config: Option<HashMap<String, DayTimeConfig>>,   // [timeConfig.HDMI-1]
     pub weather: Option<HashMap<String, WeatherConfig>>, // [weather.HDMI-1] or [weather.*]  for all
-    pub lat: Option<f64>,        // Main config latitude
-    pub lon: Option<f64>,        // Main config longitude
-    pub day_range: Option<String>, // Main config day range
+    pub network: Option<HashMap<String, NetworkConfig>>, // [network.HDMI-1] per Wi-Fi SSID
+    pub workspace: Option<HashMap<String, WorkspaceConfig>>, // [workspace.HDMI-1] per sway workspace/app
+    pub lat: Option<f64>,                                    // Main config latitude
+    pub lon: Option<f64>,                                    // Main config longitude
+    pub day_range: Option<String>,                           // Main config day range
+    #[serde(alias = "equal_day_range")]
+    pub equal_day_range: Option<EqualDayRange>, // What a dayRange like "08-08" means
+    #[serde(alias = "on_change")]
+    pub on_change: Option<String>, // Script run after each wallpaper change
+    pub colors: Option<ColorsConfig>,                        // [colors] palette export
+    #[serde(alias = "default_fill_mode")]
+    pub default_fill_mode: Option<FillMode>, // For entries without fill_mode, and other triggers
+    #[serde(alias = "merge_strategy")]
+    pub merge_strategy: Option<MergeStrategy>, // How `merge_theme` combines trigger sections
+    #[serde(alias = "output_cache")]
+    pub output_cache: Option<bool>, // Let one-shot CLI commands reuse outputs.json
+    #[serde(alias = "ca_bundle")]
+    pub ca_bundle: Option<String>, // Extra PEM CA certificates for HTTPS requests
+    #[serde(alias = "swap_grace_ms")]
+    pub swap_grace_ms: Option<u64>, // Keep the old swaybg this long after starting the new one
+    #[serde(alias = "heartbeat_secs")]
+    pub heartbeat_secs: Option<u64>, // Daemon touches <data_dir>/wallman/heartbeat this often
+    #[serde(alias = "locked_outputs")]
+    pub locked_outputs: Option<Vec<String>>, // Outputs that keep their first wallpaper
+    #[serde(alias = "apply_command")]
+    pub apply_command: Option<String>, // Replaces swaybg: "mybg -o {output} -i {image} -m {fill}"
+    pub backend: Option<String>, // "swaybg" (default) or "portal"; applyCommand wins
+    #[serde(alias = "backend_fallbacks")]
+    pub backend_fallbacks: Option<Vec<String>>, // "swaybg" or command templates, tried in order
+    pub seasons: Option<SeasonsConfig>, // [seasons] installed theme per season
+    pub transition: Option<HashMap<String, TransitionConfig>>, // [transition.HDMI-1] fade per output
+    pub outputs: Option<Vec<String>>, // Fixed output list; skips swaymsg detection
+    pub preload: Option<bool>,        // Warm referenced images on daemon start and theme switch
+    #[serde(alias = "log_level")]
+    pub log_level: Option<String>, // Extra filter directives: "wallman::triggers::daytime_trigger=warn"
+}
+
+/// `pool = "..."` for every output, or a `[pool]` table per output
+/// (`"HDMI-1" = "nature"`, `"*" = "abstract"`).
+///
+/// Values are theme directories or installed theme names.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PoolConfig {
+    Single(String),
+    PerOutput(HashMap<String, String>),
+}
+
+impl PoolConfig {
+    /// The pool used where no output is involved: the single pool, or the
+    /// `"*"` entry of a per-output table. Its manifest is the one merged.
+    pub fn primary(&self) -> Option<String> {
+        match self {
+            Self::Single(pool) => Some(pool.clone()),
+            Self::PerOutput(map) => map.get("*").map(|pool| Self::dir(pool)),
+        }
+    }
+
+    /// The pool for `output`, falling back to the `"*"` entry.
+    pub fn for_output(&self, output: &str) -> Option<String> {
+        match self {
+            Self::Single(pool) => Some(pool.clone()),
+            Self::PerOutput(map) => {
+                crate::OutputResolver::matching_key(map, output).map(|key| Self::dir(&map[key]))
+            }
+        }
+    }
+
+    /// A value naming an installed theme resolves to its folder; paths are kept.
+    pub fn dir(value: &str) -> String {
+        let installed = crate::constants::decompresion_folder().join(value);
+        if !Path::new(value).is_absolute() && installed.is_dir() {
+            installed.to_string_lossy().to_string()
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+/// `equalDayRange = "..."` — what a `dayRange` whose bounds are equal
+/// (e.g. `"08-08"`) means.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EqualDayRange {
+    /// It is always day.
+    #[default]
+    Day,
+    /// It is always night.
+    Night,
+}
+
+/// Parse a `dayRange` (`"HH-HH"`) into its day start and night start hours.
+///
+/// Both hours must be 0–23; the window may wrap midnight (`"22-08"`).
+pub fn parse_day_range(range: &str) -> Result<(u32, u32)> {
+    let invalid =
+        |reason: &str| WallmanError::Config(format!("invalid dayRange '{}': {}", range, reason));
+    let (start, end) = range
+        .split_once('-')
+        .ok_or_else(|| invalid("expected HH-HH, e.g. 06-18"))?;
+    let hour = |value: &str| -> Result<u32> {
+        let hour: u32 = value
+            .trim()
+            .parse()
+            .map_err(|_| invalid("expected HH-HH, e.g. 06-18"))?;
+        if hour > 23 {
+            return Err(invalid("hours must be between 0 and 23"));
+        }
+        Ok(hour)
+    };
+    Ok((hour(start)?, hour(end)?))
+}
+
+/// Parse an `"HH:MM-HH:MM"` range (e.g. `quiet_hours`) into its start and end
+/// as minutes since midnight.
+///
+/// Like `dayRange`, the window may wrap midnight (`"23:00-07:00"`).
+pub fn parse_time_range(range: &str) -> Result<(u32, u32)> {
+    let invalid = || {
+        WallmanError::Config(format!(
+            "invalid time range '{}': expected HH:MM-HH:MM",
+            range
+        ))
+    };
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    let minutes = |value: &str| -> Result<u32> {
+        let (hour, minute) = value.trim().split_once(':').ok_or_else(invalid)?;
+        let hour: u32 = hour.parse().map_err(|_| invalid())?;
+        let minute: u32 = minute.parse().map_err(|_| invalid())?;
+        if hour > 23 || minute > 59 {
+            return Err(invalid());
+        }
+        Ok(hour * 60 + minute)
+    };
+    Ok((minutes(start)?, minutes(end)?))
+}
+
+/// Whether `minute` (since midnight) falls in the `[start, end)` window of
+/// `parse_time_range`, wrapping midnight when `end < start`.
+pub fn in_time_range((start, end): (u32, u32), minute: u32) -> bool {
+    if start <= end {
+        (start..end).contains(&minute)
+    } else {
+        minute >= start || minute < end
+    }
+}
+
+/// `mergeStrategy = "..."` — how a theme's trigger sections (`background`,
+/// `timeConfig`, `weather`, `network`, `workspace`, `transition`) combine with the user's.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// A section the theme defines replaces the user's entirely.
+    #[default]
+    ThemeWins,
+    /// A section the user defines is kept; the theme only fills in missing ones.
+    UserWins,
+    /// Merge per output: the user's entries win, the theme adds the others
+    /// (e.g. keep your `[background.HDMI-1]` and take the theme's `"*"`).
+    Deep,
+}
+
+impl MergeStrategy {
+    /// Combine one per-output section of the theme into the user's.
+    fn merge<T>(self, user: &mut Option<HashMap<String, T>>, theme: Option<HashMap<String, T>>) {
+        let Some(theme) = theme else {
+            return;
+        };
+        match (self, user.as_mut()) {
+            (MergeStrategy::ThemeWins, _) | (_, None) => *user = Some(theme),
+            (MergeStrategy::UserWins, Some(_)) => {}
+            (MergeStrategy::Deep, Some(user)) => {
+                for (output, value) in theme {
+                    user.entry(output).or_insert(value);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct BackgroundConfig {
     pub image: Option<String>,
-    pub fill_mode: FillMode,
+    /// Unset means the top-level `defaultFillMode` (see `Config::fill_mode_for`).
+    #[serde(default, alias = "fillMode", skip_serializing_if = "Option::is_none")]
+    pub fill_mode: Option<FillMode>,
+    /// How the image is chosen; unset means use `image`.
+    #[serde(default)]
+    pub mode: Option<BackgroundMode>,
+    /// Blur the image by this Gaussian sigma (pixels) before showing it.
+    #[serde(default)]
+    pub blur_sigma: Option<f32>,
+    /// Multiply brightness by this factor (e.g. `0.6` to dim) before showing it.
+    #[serde(default)]
+    pub brightness: Option<f32>,
+    /// Solid color (`#RRGGBB`) shown instead of an image file that is missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_color: Option<String>,
+}
+
+/// File name suffix of a per-image sidecar, e.g. `forest.jpg.meta.toml`.
+pub const IMAGE_META_SUFFIX: &str = ".meta.toml";
+
+/// `<image>.meta.toml` — presentation settings stored next to one image.
+///
+/// They apply wherever the image is shown and take precedence over the
+/// per-output `fill_mode` / `blur_sigma`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ImageMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill_mode: Option<FillMode>,
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate: Option<u32>,
+    /// Gaussian blur sigma in pixels (`0` turns off a per-output blur).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blur: Option<f32>,
+}
+
+impl ImageMeta {
+    /// Where the sidecar for `image` lives.
+    pub fn sidecar_path(image: &Path) -> PathBuf {
+        let mut name = image.as_os_str().to_owned();
+        name.push(IMAGE_META_SUFFIX);
+        PathBuf::from(name)
+    }
+
+    /// Whether `path` names a sidecar rather than an image.
+    pub fn is_sidecar(path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(IMAGE_META_SUFFIX))
+    }
+
+    /// Parse and validate a sidecar's contents.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let meta: Self = toml::from_str(contents)?;
+        if let Some(rotate) = meta.rotate
+            && !matches!(rotate, 0 | 90 | 180 | 270)
+        {
+            return Err(WallmanError::Config(format!(
+                "rotate must be 0, 90, 180 or 270 (got {})",
+                rotate
+            )));
+        }
+        Ok(meta)
+    }
+
+    /// The sidecar for `image`, if there is one. A broken sidecar is logged
+    /// and ignored so the image is still shown.
+    pub fn for_image(image: &Path) -> Option<Self> {
+        let path = Self::sidecar_path(image);
+        let contents = std::fs::read_to_string(&path).ok()?;
+        Self::parse(&contents)
+            .inspect_err(|e| tracing::warn!("Ignoring {}: {}", path.display(), e))
+            .ok()
+    }
+
+    /// Write this sidecar next to `image`.
+    pub fn save_for(&self, image: &Path) -> Result<()> {
+        std::fs::write(Self::sidecar_path(image), toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// `[background.<output>] mode = "..."`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundMode {
+    /// Show `image`.
+    Image,
+    /// Pick a random image from the theme pool at startup.
+    Random,
+}
+
+/// `[colors]` — export a color palette extracted from the applied wallpaper.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ColorsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Also write `colors.css` next to `colors.json`.
+    #[serde(default)]
+    pub css: bool,
+}
+
+/// `[transition.<output>]` — how the wallpaper changes on that output.
+///
+/// Themes may ship it; it merges like the trigger sections. swaybg cannot
+/// animate, so only command backends that take `{fade}` use it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct TransitionConfig {
+    /// Fade duration in milliseconds; 0 switches instantly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade_ms: Option<u64>,
+}
+
+/// `[seasons]` — switch the active theme (`pool`) with the season.
+///
+/// Values are installed theme names, as for `wallman theme set`. Seasons
+/// without a theme keep whatever is active.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct SeasonsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spring: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autumn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub winter: Option<String>,
+    /// Shift every season by six months.
+    #[serde(default)]
+    pub southern_hemisphere: bool,
+}
+
+impl SeasonsConfig {
+    /// Theme configured for `season`, if any.
+    pub fn theme_for(&self, season: Season) -> Option<&str> {
+        match season {
+            Season::Spring => self.spring.as_deref(),
+            Season::Summer => self.summer.as_deref(),
+            Season::Autumn => self.autumn.as_deref(),
+            Season::Winter => self.winter.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    /// Meteorological season of `month` (1–12): spring is March–May, summer
+    /// June–August, and so on; inverted for the southern hemisphere.
+    pub fn for_month(month: u32, southern_hemisphere: bool) -> Self {
+        let month = if southern_hemisphere {
+            (month + 5) % 12 + 1
+        } else {
+            month
+        };
+        match month {
+            3..=5 => Self::Spring,
+            6..=8 => Self::Summer,
+            9..=11 => Self::Autumn,
+            _ => Self::Winter,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Spring => "spring",
+            Self::Summer => "summer",
+            Self::Autumn => "autumn",
+            Self::Winter => "winter",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct DayTimeConfig {
     pub day: String,
     pub night: String,
+    /// `[[timeConfig.<output>.rules]]` — day/night images for specific weekdays.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<DayTimeRule>,
+    /// Solid color (`#RRGGBB`) shown instead of an image file that is missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_color: Option<String>,
+}
+
+/// A day/night pair that only applies on `days` (every day when unset).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DayTimeRule {
+    #[serde(default)]
+    pub days: Option<Vec<Weekday>>,
+    pub day: String,
+    pub night: String,
+}
+
+/// Day names accepted in `days = [...]`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    #[serde(alias = "monday")]
+    Mon,
+    #[serde(alias = "tuesday")]
+    Tue,
+    #[serde(alias = "wednesday")]
+    Wed,
+    #[serde(alias = "thursday")]
+    Thu,
+    #[serde(alias = "friday")]
+    Fri,
+    #[serde(alias = "saturday")]
+    Sat,
+    #[serde(alias = "sunday")]
+    Sun,
+}
+
+impl From<chrono::Weekday> for Weekday {
+    fn from(day: chrono::Weekday) -> Self {
+        match day {
+            chrono::Weekday::Mon => Self::Mon,
+            chrono::Weekday::Tue => Self::Tue,
+            chrono::Weekday::Wed => Self::Wed,
+            chrono::Weekday::Thu => Self::Thu,
+            chrono::Weekday::Fri => Self::Fri,
+            chrono::Weekday::Sat => Self::Sat,
+            chrono::Weekday::Sun => Self::Sun,
+        }
+    }
+}
+
+impl DayTimeConfig {
+    /// The `(day, night)` images in effect on `weekday`.
+    ///
+    /// The rule scoped to the fewest days that includes `weekday` wins; rules
+    /// without `days` and the top-level `day`/`night` apply otherwise, in that
+    /// order.
+    pub fn images_for(&self, weekday: impl Into<Weekday>) -> (&str, &str) {
+        let weekday = weekday.into();
+        self.rules
+            .iter()
+            .filter(|rule| {
+                rule.days
+                    .as_ref()
+                    .is_none_or(|days| days.contains(&weekday))
+            })
+            .min_by_key(|rule| rule.days.as_ref().map_or(usize::MAX, Vec::len))
+            .map_or((&self.day, &self.night), |rule| (&rule.day, &rule.night))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct WeatherConfig {
     pub weather: HashMap<String, String>,
+    /// Solid color (`#RRGGBB`) shown instead of an image file that is missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_color: Option<String>,
+    /// `"HH:MM-HH:MM"` window in which the weather API is not called and the
+    /// last known weather is used (see `parse_time_range`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<String>,
 }
 
+/// `[network.<output>]` — images per Wi-Fi SSID.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NetworkConfig {
+    /// SSID → image, from `[network.<output>.ssid]`.
+    #[serde(default)]
+    pub ssid: HashMap<String, String>,
+    /// Used for unknown SSIDs and when not connected.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Solid color (`#RRGGBB`) shown instead of an image file that is missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_color: Option<String>,
+}
+
+/// `[workspace.<output>]` — images per focused sway workspace or application.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WorkspaceConfig {
+    /// Workspace name → image, from `[workspace.<output>.name]`.
+    #[serde(default)]
+    pub name: HashMap<String, String>,
+    /// Focused window's app_id (or X11 class) → image, from
+    /// `[workspace.<output>.app]`. Takes precedence over `name`.
+    #[serde(default)]
+    pub app: HashMap<String, String>,
+    /// Used when neither the workspace nor the app has an entry.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Solid color (`#RRGGBB`) shown instead of an image file that is missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_color: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum FillMode {
+    #[default]
     Fill,
     Crop,
     Scale,
 }
 
+impl FillMode {
+    /// Every fill mode, in declaration order.
+    pub const ALL: [FillMode; 3] = [FillMode::Fill, FillMode::Crop, FillMode::Scale];
+
+    /// The name used in config files, also passed to `applyCommand` as `{fill}`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FillMode::Fill => "fill",
+            FillMode::Crop => "crop",
+            FillMode::Scale => "scale",
+        }
+    }
+
+    /// swaybg `-m` value for this mode.
+    pub fn as_swaybg_mode(&self) -> &'static str {
+        match self {
+            FillMode::Fill => "fill",
+            FillMode::Crop => "center",
+            FillMode::Scale => "stretch",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum WeatherStates {
@@ -59,33 +559,569 @@ pub struct WeatherImagesConf {
     pub weather: WeatherStates,
 }
 
+/// Trigger families that can drive the daemon; only one runs at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerKind {
+    Workspace,
+    Weather,
+    Network,
+    DayTime,
+    Static,
+}
+
+impl TriggerKind {
+    /// Every trigger, highest priority first (see `Config::trigger_kind`).
+    pub const PRIORITY: [TriggerKind; 5] = [
+        TriggerKind::Workspace,
+        TriggerKind::Weather,
+        TriggerKind::Network,
+        TriggerKind::DayTime,
+        TriggerKind::Static,
+    ];
+
+    /// Short name used in logs and `daemon status`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Workspace => "workspace",
+            Self::Weather => "weather",
+            Self::Network => "network",
+            Self::DayTime => "daytime",
+            Self::Static => "static",
+        }
+    }
+
+    /// Config section that configures this trigger.
+    pub fn section(self) -> &'static str {
+        match self {
+            Self::Workspace => "workspace",
+            Self::Weather => "weather",
+            Self::Network => "network",
+            Self::DayTime => "timeConfig",
+            Self::Static => "background",
+        }
+    }
+}
+
+/// On-disk config formats, chosen by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+/// File extensions tried during config discovery, in priority order.
+pub const CONFIG_EXTENSIONS: &[&str] = &["toml", "json", "yaml", "yml"];
+
+/// `--config` value that reads a TOML config from stdin instead of a file.
+pub const STDIN_CONFIG: &str = "-";
+
+impl ConfigFormat {
+    /// Pick the format from the path's extension; anything unknown is TOML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::Json,
+            Some("yaml") | Some("yml") => Self::Yaml,
+            _ => Self::Toml,
+        }
+    }
+}
+
+#[cfg(not(feature = "yaml"))]
+fn yaml_disabled() -> WallmanError {
+    WallmanError::Config(
+        "YAML config support is not enabled (rebuild with `--features yaml`)".to_string(),
+    )
+}
+
 impl Config {
-    pub fn load(config_file: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Load a config file, migrating older schema versions in memory.
+    pub fn load(config_file: PathBuf) -> Result<Self> {
+        let (config, original_version) = Self::load_migrated(&config_file)?;
+        if original_version < CURRENT_CONFIG_VERSION {
+            tracing::info!(
+                "{} uses config version {} — run `wallman config migrate` to update the file",
+                config_file.display(),
+                original_version
+            );
+        }
+        Ok(config)
+    }
+
+    /// Migrate a config file on disk to the current schema version.
+    ///
+    /// Returns the version the file had before; the file is only rewritten
+    /// when it was older than `CURRENT_CONFIG_VERSION`.
+    pub fn migrate_file(path: &PathBuf) -> Result<i32> {
+        let (config, original_version) = Self::load_migrated(path)?;
+        if original_version < CURRENT_CONFIG_VERSION {
+            config.save_to_file(path)?;
+        }
+        Ok(original_version)
+    }
+
+    /// Parse the file into a generic document, run migrations, then deserialize.
+    fn load_migrated(config_file: &Path) -> Result<(Self, i32)> {
         let mut file = File::open(config_file)?;
         let mut data = Vec::new();
         file.read_to_end(&mut data)?;
+        Self::parse_migrated(&data, ConfigFormat::from_path(config_file))
+    }
 
-        let config: Config = toml::from_slice(&data)?;
-        Ok(config)
+    /// Read a TOML config from stdin (`--config -`), migrating it in memory.
+    pub fn load_stdin() -> Result<Self> {
+        let mut data = Vec::new();
+        std::io::stdin().read_to_end(&mut data)?;
+        Ok(Self::parse_migrated(&data, ConfigFormat::Toml)?.0)
     }
 
-    pub fn save_to_file(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    fn parse_migrated(data: &[u8], format: ConfigFormat) -> Result<(Self, i32)> {
+        let mut value: serde_json::Value = match format {
+            ConfigFormat::Toml => serde_json::to_value(toml::from_slice::<toml::Table>(data)?)?,
+            ConfigFormat::Json => serde_json::from_slice(data)?,
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => {
+                serde_json::to_value(serde_yaml::from_slice::<serde_yaml::Value>(data)?)?
+            }
+            #[cfg(not(feature = "yaml"))]
+            ConfigFormat::Yaml => return Err(yaml_disabled()),
+        };
+
+        let original_version = crate::migration::migrate(&mut value)?;
+        let config: Config = serde_json::from_value(value)?;
+        if let Some(range) = &config.day_range {
+            parse_day_range(range)?;
+        }
+        if let Some(level) = &config.log_level {
+            crate::logging::filter(&crate::logging::LogOptions::default(), Some(level))?;
+        }
+        for weather in config.weather.iter().flat_map(|m| m.values()) {
+            if let Some(range) = &weather.quiet_hours {
+                parse_time_range(range)?;
+            }
+        }
+        if config.outputs.as_ref().is_some_and(|outputs| {
+            outputs.is_empty() || outputs.iter().any(|o| o.trim().is_empty())
+        }) {
+            return Err(WallmanError::Config(
+                "outputs must list at least one output name, or be left out to detect them"
+                    .to_string(),
+            ));
+        }
+        for image in config.referenced_images() {
+            if crate::wallpaper::generated::is_procedural(image) {
+                crate::wallpaper::generated::validate(image).map_err(|e| {
+                    WallmanError::Config(format!("invalid image '{}': {}", image, e))
+                })?;
+            }
+        }
+        if let Some(template) = &config.apply_command {
+            crate::wallpaper::command::CommandTemplate::parse(template)?;
+        }
+        for backend in config
+            .backend
+            .iter()
+            .chain(config.backend_fallbacks.iter().flatten())
+        {
+            crate::wallpaper::backend::Backend::parse(backend)?;
+        }
+        Ok((config, original_version))
+    }
+
+    pub fn save_to_file(&self, path: &PathBuf) -> Result<()> {
         use std::fs;
 
+        if path.as_os_str() == STDIN_CONFIG {
+            return Err(WallmanError::Config(
+                "the config was read from stdin (--config -) and cannot be written back"
+                    .to_string(),
+            ));
+        }
+
         // Create parent directory if it doesn't exist
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        let toml_string = toml::to_string_pretty(self)?;
-        fs::write(path, toml_string)?;
+        let contents = match ConfigFormat::from_path(path) {
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(self)?,
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml::to_string(self)?,
+            #[cfg(not(feature = "yaml"))]
+            ConfigFormat::Yaml => return Err(yaml_disabled()),
+        };
+        fs::write(path, contents)?;
         Ok(())
     }
 
+    /// Return the first existing config file among `candidates`.
+    ///
+    /// Each candidate (see `config_vec`) is tried as-is, then with every
+    /// extension in `CONFIG_EXTENSIONS` appended, before moving on — so
+    /// `/etc/wallman.conf` and `config` → `config.toml` are both found.
+    /// Directories never match.
+    pub fn discover(candidates: &[PathBuf]) -> Option<PathBuf> {
+        candidates.iter().find_map(|base| {
+            std::iter::once(base.clone())
+                .chain(CONFIG_EXTENSIONS.iter().map(|ext| {
+                    let mut name = base.clone().into_os_string();
+                    name.push(".");
+                    name.push(ext);
+                    PathBuf::from(name)
+                }))
+                .find(|p| p.is_file())
+        })
+    }
+
+    /// States that `state_images` can look up: weather keys, then `day`/`night`.
+    pub fn preview_states(&self) -> Vec<String> {
+        let mut weather: Vec<String> = self
+            .weather
+            .iter()
+            .flat_map(|m| m.values())
+            .flat_map(|w| w.weather.keys().cloned())
+            .collect();
+        weather.sort_unstable();
+        weather.dedup();
+        if self.time_config.is_some() {
+            weather.extend(["day".to_string(), "night".to_string()]);
+        }
+        weather
+    }
+
+    /// The image each `[weather]`/`[timeConfig]` entry shows in `state`
+    /// (e.g. `rainy` or `night`), keyed like the section (`*`, `HDMI-1`, …).
+    pub fn state_images(&self, state: &str) -> HashMap<String, String> {
+        let weather = self
+            .weather
+            .iter()
+            .flat_map(|m| m.iter())
+            .filter_map(|(output, w)| {
+                w.weather
+                    .get(state)
+                    .map(|image| (output.clone(), image.clone()))
+            });
+        let time = self
+            .time_config
+            .iter()
+            .flat_map(|m| m.iter())
+            .filter_map(|(output, tc)| match state {
+                "day" => Some((output.clone(), tc.day.clone())),
+                "night" => Some((output.clone(), tc.night.clone())),
+                _ => None,
+            });
+        weather.chain(time).collect()
+    }
+
+    /// Every image path referenced by the background, timeConfig, weather and
+    /// network and workspace sections, sorted and de-duplicated.
+    pub fn referenced_images(&self) -> Vec<&str> {
+        let mut images: Vec<&str> = Vec::new();
+        for bg in self.background.iter().flat_map(|m| m.values()) {
+            images.extend(bg.image.as_deref());
+        }
+        for tc in self.time_config.iter().flat_map(|m| m.values()) {
+            images.push(&tc.day);
+            images.push(&tc.night);
+            for rule in &tc.rules {
+                images.push(&rule.day);
+                images.push(&rule.night);
+            }
+        }
+        for w in self.weather.iter().flat_map(|m| m.values()) {
+            images.extend(w.weather.values().map(String::as_str));
+        }
+        for n in self.network.iter().flat_map(|m| m.values()) {
+            images.extend(n.ssid.values().map(String::as_str));
+            images.extend(n.default.as_deref());
+        }
+        for w in self.workspace.iter().flat_map(|m| m.values()) {
+            images.extend(w.name.values().map(String::as_str));
+            images.extend(w.app.values().map(String::as_str));
+            images.extend(w.default.as_deref());
+        }
+        images.sort_unstable();
+        images.dedup();
+        images
+    }
+
+    /// Trigger sections present in this config, in priority order.
+    pub fn configured_triggers(&self) -> Vec<TriggerKind> {
+        TriggerKind::PRIORITY
+            .into_iter()
+            .filter(|kind| self.section_outputs(*kind).is_some())
+            .collect()
+    }
+
+    /// Sorted output keys of `kind`'s section, or `None` when it is absent.
+    pub fn section_outputs(&self, kind: TriggerKind) -> Option<Vec<String>> {
+        fn keys<T>(section: &Option<HashMap<String, T>>) -> Option<Vec<String>> {
+            section.as_ref().map(|map| {
+                let mut names: Vec<String> = map.keys().cloned().collect();
+                names.sort();
+                names
+            })
+        }
+        match kind {
+            TriggerKind::Workspace => keys(&self.workspace),
+            TriggerKind::Weather => keys(&self.weather),
+            TriggerKind::Network => keys(&self.network),
+            TriggerKind::DayTime => keys(&self.time_config),
+            TriggerKind::Static => keys(&self.background),
+        }
+    }
+
+    /// The trigger the daemon runs.
+    /// Priority: workspace > weather > network > timeConfig > background.
+    pub fn trigger_kind(&self) -> TriggerKind {
+        self.configured_triggers()
+            .first()
+            .copied()
+            .unwrap_or(TriggerKind::Static)
+    }
+
+    /// Describe configured trigger sections that `trigger_kind` ignores, if any.
+    pub fn trigger_conflict(&self) -> Option<String> {
+        let configured = self.configured_triggers();
+        let (used, ignored) = configured.split_first()?;
+        if ignored.is_empty() {
+            return None;
+        }
+        let ignored: Vec<String> = ignored
+            .iter()
+            .map(|k| format!("[{}]", k.section()))
+            .collect();
+        Some(format!(
+            "multiple trigger sections configured; using [{}] and ignoring {}",
+            used.section(),
+            ignored.join(", ")
+        ))
+    }
+
+    /// Fill mode for `background`: its own `fill_mode`, else `defaultFillMode`,
+    /// else `fill`.
+    pub fn fill_mode_for(&self, background: &BackgroundConfig) -> FillMode {
+        background
+            .fill_mode
+            .clone()
+            .unwrap_or_else(|| self.base_fill_mode())
+    }
+
+    /// Fill mode for an image put on `output` by hand (D-Bus `SetWallpaper`):
+    /// the output's `[background]` entry (exact name, else `"*"`), else
+    /// `defaultFillMode`, else `fill`.
+    pub fn fill_mode_for_output(&self, output: &str) -> FillMode {
+        self.background
+            .as_ref()
+            .and_then(|map| map.get(output).or_else(|| map.get("*")))
+            .map(|background| self.fill_mode_for(background))
+            .unwrap_or_else(|| self.base_fill_mode())
+    }
+
+    /// Fill mode for images from sections without a `fill_mode` of their own
+    /// (`[timeConfig]`, `[weather]`, …): `defaultFillMode`, else `fill`.
+    pub fn base_fill_mode(&self) -> FillMode {
+        self.default_fill_mode.clone().unwrap_or_default()
+    }
+
+    /// `fallback_color` of the active trigger's section for `output`
+    /// (exact key, else `"*"`), if it is a valid `#RRGGBB` color.
+    pub fn fallback_color_for(&self, output: &str) -> Option<&str> {
+        fn lookup<'a, T>(
+            map: &'a Option<HashMap<String, T>>,
+            output: &str,
+            color: impl Fn(&'a T) -> Option<&'a String>,
+        ) -> Option<&'a str> {
+            let map = map.as_ref()?;
+            let section = map.get(output).or_else(|| map.get("*"))?;
+            color(section).map(String::as_str)
+        }
+
+        let color = match self.trigger_kind() {
+            TriggerKind::Workspace => {
+                lookup(&self.workspace, output, |c| c.fallback_color.as_ref())
+            }
+            TriggerKind::Weather => lookup(&self.weather, output, |c| c.fallback_color.as_ref()),
+            TriggerKind::Network => lookup(&self.network, output, |c| c.fallback_color.as_ref()),
+            TriggerKind::DayTime => {
+                lookup(&self.time_config, output, |c| c.fallback_color.as_ref())
+            }
+            TriggerKind::Static => lookup(&self.background, output, |c| c.fallback_color.as_ref()),
+        }?;
+        if crate::wallpaper::apply::is_solid_color(color) {
+            Some(color)
+        } else {
+            tracing::warn!(
+                "Ignoring fallback_color '{}' for output '{}': expected #RRGGBB",
+                color,
+                output
+            );
+            None
+        }
+    }
+
+    /// The theme pool used outside a specific output (see `PoolConfig::primary`).
+    pub fn primary_pool(&self) -> Option<String> {
+        self.pool.as_ref().and_then(PoolConfig::primary)
+    }
+
+    /// Fade duration for `output` from `[transition]`, 0 when unset.
+    pub fn fade_ms_for(&self, output: &str) -> u64 {
+        self.transition
+            .as_ref()
+            .and_then(|map| map.get(crate::OutputResolver::matching_key(map, output)?))
+            .and_then(|transition| transition.fade_ms)
+            .unwrap_or(0)
+    }
+
+    /// Whether `output` is listed in `lockedOutputs`: it gets its first
+    /// wallpaper, then no further changes.
+    pub fn is_locked(&self, output: &str) -> bool {
+        self.locked_outputs
+            .as_ref()
+            .is_some_and(|locked| locked.iter().any(|o| o == output))
+    }
+
+    /// Whether `hour` (0–23) falls in the day window from `dayRange` ("HH-HH",
+    /// default 8-19). The window may wrap midnight, e.g. `"22-08"`; equal
+    /// bounds mean always day or always night, per `equalDayRange`.
+    pub fn is_daytime(&self, hour: u32) -> bool {
+        let default = (crate::constants::day_start(), crate::constants::day_end());
+        let (day_start, night_start) = self
+            .day_range
+            .as_deref()
+            .and_then(|range| parse_day_range(range).ok())
+            .unwrap_or(default);
+
+        if day_start == night_start {
+            self.equal_day_range.unwrap_or_default() == EqualDayRange::Day
+        } else if day_start < night_start {
+            // Normal case: daytime window e.g. 06:00 – 18:00
+            hour >= day_start && hour < night_start
+        } else {
+            // Overnight case: daytime window wraps midnight e.g. 22:00 – 08:00
+            hour >= day_start || hour < night_start
+        }
+    }
+
+    /// Top-level keys, spelled as in config files, whose value differs in `other`
+    /// (e.g. what `merge_theme` changed).
+    pub fn changed_keys(&self, other: &Config) -> Result<Vec<String>> {
+        let (serde_json::Value::Object(before), serde_json::Value::Object(after)) =
+            (serde_json::to_value(self)?, serde_json::to_value(other)?)
+        else {
+            return Ok(Vec::new());
+        };
+        Ok(after
+            .iter()
+            .filter(|(key, value)| before.get(*key) != Some(value))
+            .map(|(key, _)| key.clone())
+            .collect())
+    }
+
+    /// Read a single setting by dotted key, e.g. `weather.*.weather.sunny`.
+    ///
+    /// Key segments match field names in either camelCase or snake_case.
+    pub fn get_key(&self, key: &str) -> Result<serde_json::Value> {
+        let root = serde_json::to_value(self)?;
+        let mut current = &root;
+        for segment in key.split('.') {
+            current = current
+                .as_object()
+                .and_then(|map| map.get(&resolve_segment(map, segment)))
+                .filter(|v| !v.is_null())
+                .ok_or_else(|| {
+                    WallmanError::Config(format!("key '{}' not found in config", key))
+                })?;
+        }
+        Ok(current.clone())
+    }
+
+    /// Set a single setting by dotted key, creating intermediate tables.
+    ///
+    /// `raw` is parsed as a JSON scalar (number, bool, `null`) when the field
+    /// accepts one, otherwise it is stored as a string. Unknown keys and values
+    /// of the wrong type are rejected and leave `self` untouched.
+    pub fn set_key(&mut self, key: &str, raw: &str) -> Result<()> {
+        let parsed: serde_json::Value = serde_json::from_str(raw).unwrap_or_else(|_| raw.into());
+        // New tables have no existing field names to match against, so also
+        // try the snake_case spelling used by some nested structs.
+        let snake = to_snake_case(key);
+        let attempts = [
+            (key, parsed.clone()),
+            (key, raw.into()),
+            (snake.as_str(), parsed),
+            (snake.as_str(), raw.into()),
+        ];
+
+        let mut first_error = None;
+        for (key, value) in attempts {
+            match self.with_key(key, value) {
+                Ok(updated) => {
+                    *self = updated;
+                    return Ok(());
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_error.unwrap())
+    }
+
+    fn with_key(&self, key: &str, value: serde_json::Value) -> Result<Self> {
+        let segments: Vec<&str> = key.split('.').collect();
+        let (last, parents) = segments
+            .split_last()
+            .filter(|(last, _)| !last.is_empty())
+            .ok_or_else(|| WallmanError::Config("empty config key".to_string()))?;
+
+        let mut root = serde_json::to_value(self)?;
+        let mut current = &mut root;
+        for segment in parents {
+            if current.is_null() {
+                *current = serde_json::Value::Object(Default::default());
+            }
+            let map = current
+                .as_object_mut()
+                .ok_or_else(|| WallmanError::Config(format!("'{}' is not a table", key)))?;
+            let name = resolve_segment(map, segment);
+            current = map.entry(name).or_insert(serde_json::Value::Null);
+        }
+        if current.is_null() {
+            *current = serde_json::Value::Object(Default::default());
+        }
+        let map = current
+            .as_object_mut()
+            .ok_or_else(|| WallmanError::Config(format!("'{}' is not a table", key)))?;
+        let name = resolve_segment(map, last);
+        map.insert(name, value.clone());
+
+        let updated: Config = serde_json::from_value(root)
+            .map_err(|e| WallmanError::Config(format!("invalid value for '{}': {}", key, e)))?;
+
+        // Serde ignores unknown fields, so check the value actually landed.
+        if value.is_null() || updated.get_key(key).is_ok() {
+            Ok(updated)
+        } else {
+            Err(WallmanError::Config(format!(
+                "unknown config key '{}'",
+                key
+            )))
+        }
+    }
+
     /// Merge settings from a theme manifest into this config.
     /// Only fills in fields that are currently None, except for lat, lon, and day_range
     /// which are preserved from the user config.
-    pub fn merge_theme(&mut self, theme_path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    /// Theme folder that image references are resolved against first
+    /// (`imagesDir` in the manifest, `images` by default).
+    pub fn images_dir(&self) -> &str {
+        self.images_dir.as_deref().unwrap_or(DEFAULT_IMAGES_DIR)
+    }
+
+    pub fn merge_theme(&mut self, theme_path: PathBuf) -> Result<()> {
         let manifest_path = theme_path.join("manifest.toml");
         if !manifest_path.exists() {
             return Ok(());
@@ -94,21 +1130,25 @@ impl Config {
         let theme_config = Config::load(manifest_path)?;
 
         // Preserve user's lat, lon, and day_range settings
-        let user_lat = self.lat.clone();
-        let user_lon = self.lon.clone();
+        let user_lat = self.lat;
+        let user_lon = self.lon;
         let user_day_range = self.day_range.clone();
 
-        // Priority: Theme Manifest > User Config for trigger logic
-        // But preserve user's main config settings for lat, lon, day_range
-        if theme_config.background.is_some() {
-            self.background = theme_config.background;
-        }
-        if theme_config.time_config.is_some() {
-            self.time_config = theme_config.time_config;
-        }
-        if theme_config.weather.is_some() {
-            self.weather = theme_config.weather;
+        // Trigger sections combine per the user's `mergeStrategy` (theme wins by default),
+        // but the user's main config settings for lat, lon, day_range are always kept.
+        let strategy = self.merge_strategy.unwrap_or_default();
+        strategy.merge(&mut self.background, theme_config.background);
+        strategy.merge(&mut self.time_config, theme_config.time_config);
+        strategy.merge(&mut self.weather, theme_config.weather);
+        strategy.merge(&mut self.network, theme_config.network);
+        strategy.merge(&mut self.workspace, theme_config.workspace);
+        strategy.merge(&mut self.transition, theme_config.transition);
+        // A per-output table is the user's choice of themes; keep it.
+        if theme_config.pool.is_some() && !matches!(self.pool, Some(PoolConfig::PerOutput(_))) {
+            self.pool = theme_config.pool;
         }
+        self.images_dir = theme_config.images_dir;
+        self.requires = theme_config.requires;
 
         // Preserve user's main config fields
         self.lat = user_lat;
@@ -120,10 +1160,10 @@ impl Config {
         if self.name.is_none() || self.name == Some("wallman".to_string()) {
             self.name = theme_config.name;
         }
-        if self.description.is_none() {
+        if theme_config.description.is_some() {
             self.description = theme_config.description;
         }
-        if self.theme.is_none() {
+        if theme_config.theme.is_some() {
             self.theme = theme_config.theme;
         }
 
@@ -131,20 +1171,68 @@ impl Config {
     }
 }
 
+/// Map a key segment onto an existing field name, ignoring `_` and case,
+/// so `fillMode`, `fill_mode` and `timeConfig`/`time_config` all match.
+fn resolve_segment(map: &serde_json::Map<String, serde_json::Value>, segment: &str) -> String {
+    let normalize = |s: &str| s.replace('_', "").to_lowercase();
+    let wanted = normalize(segment);
+    map.keys()
+        .find(|k| normalize(k) == wanted)
+        .cloned()
+        .unwrap_or_else(|| segment.to_string())
+}
+
+fn to_snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             pool: None,
-            version: Some(1),
+            version: Some(CURRENT_CONFIG_VERSION),
             name: Some("wallman".to_string()),
             description: Some("Dynamic wallpaper manager for Sway".to_string()),
+            author: None,
+            license: None,
+            images_dir: None,
+            requires: None,
             theme: None,
             background: None,
             time_config: None,
             weather: None,
+            network: None,
+            workspace: None,
             lat: None,
             lon: None,
             day_range: None,
+            equal_day_range: None,
+            on_change: None,
+            colors: None,
+            default_fill_mode: None,
+            merge_strategy: None,
+            output_cache: None,
+            ca_bundle: None,
+            swap_grace_ms: None,
+            heartbeat_secs: None,
+            locked_outputs: None,
+            apply_command: None,
+            backend: None,
+            backend_fallbacks: None,
+            seasons: None,
+            transition: None,
+            outputs: None,
+            preload: None,
+            log_level: None,
         }
     }
 }
@@ -154,24 +1242,401 @@ mod tests {
     use super::*;
     use std::fs;
 
+    fn sample_config() -> Config {
+        Config {
+            lat: Some(48.85),
+            lon: Some(2.35),
+            time_config: Some(HashMap::from([(
+                "*".to_string(),
+                DayTimeConfig {
+                    day: "day.jpg".to_string(),
+                    night: "night.jpg".to_string(),
+                    rules: Vec::new(),
+                    fallback_color: None,
+                },
+            )])),
+            ..Config::default()
+        }
+    }
+
+    fn assert_round_trip(file_name: &str) {
+        let dir = std::env::temp_dir().join(format!("wallman_test_format_{}", file_name));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join(file_name);
+
+        let config = sample_config();
+        config.save_to_file(&path).unwrap();
+        assert_eq!(Config::load(path).unwrap(), config);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip_toml() {
+        assert_round_trip("config.toml");
+    }
+
+    #[test]
+    fn test_round_trip_json() {
+        assert_round_trip("config.json");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_round_trip_yaml() {
+        assert_round_trip("config.yaml");
+    }
+
+    #[test]
+    fn test_seasons_flip_in_the_southern_hemisphere() {
+        assert_eq!(Season::for_month(1, false), Season::Winter);
+        assert_eq!(Season::for_month(4, false), Season::Spring);
+        assert_eq!(Season::for_month(7, false), Season::Summer);
+        assert_eq!(Season::for_month(11, false), Season::Autumn);
+        assert_eq!(Season::for_month(1, true), Season::Summer);
+        assert_eq!(Season::for_month(4, true), Season::Autumn);
+        assert_eq!(Season::for_month(7, true), Season::Winter);
+        assert_eq!(Season::for_month(12, true), Season::Summer);
+
+        let seasons: SeasonsConfig = toml::from_str("winter = \"snow\"").unwrap();
+        assert_eq!(seasons.theme_for(Season::Winter), Some("snow"));
+        assert_eq!(seasons.theme_for(Season::Summer), None);
+    }
+
+    #[test]
+    fn test_discover_tries_every_extension() {
+        let dir = std::env::temp_dir().join("wallman_test_discover");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("config.json"), "{}").unwrap();
+
+        let candidates = vec![dir.join("missing"), dir.join("config")];
+        assert_eq!(Config::discover(&candidates), Some(dir.join("config.json")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_discover_checks_candidates_as_is_first() {
+        let dir = std::env::temp_dir().join("wallman_test_discover_layouts");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("config")).unwrap();
+        fs::write(dir.join("config.toml"), "").unwrap();
+        fs::write(dir.join("wallman.conf"), "").unwrap();
+        fs::write(dir.join("wallman.toml"), "").unwrap();
+        fs::write(dir.join("plain"), "").unwrap();
+        fs::write(dir.join("plain.toml"), "").unwrap();
+
+        let find = |name: &str| Config::discover(&[dir.join(name)]);
+        // An existing directory is skipped in favour of `config.toml`.
+        assert_eq!(find("config"), Some(dir.join("config.toml")));
+        // Extensions are appended, never substituted for `.conf`.
+        assert_eq!(find("wallman.conf"), Some(dir.join("wallman.conf")));
+        assert_eq!(find("plain"), Some(dir.join("plain")));
+        assert_eq!(find("nothing.conf"), None);
+        // Earlier candidates win.
+        assert_eq!(
+            Config::discover(&[
+                dir.join("missing"),
+                dir.join("wallman.conf"),
+                dir.join("config")
+            ]),
+            Some(dir.join("wallman.conf"))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_and_set_nested_keys() {
+        let mut config = sample_config();
+
+        config.set_key("background.*.fillMode", "crop").unwrap();
+        assert_eq!(
+            config.background.as_ref().unwrap()["*"].fill_mode,
+            Some(FillMode::Crop)
+        );
+        assert_eq!(
+            config.get_key("background.*.fill_mode").unwrap(),
+            serde_json::json!("crop")
+        );
+
+        config.set_key("lat", "40.7").unwrap();
+        assert_eq!(config.lat, Some(40.7));
+
+        // Numeric-looking strings stay strings where a string is expected.
+        config.set_key("name", "2024").unwrap();
+        assert_eq!(config.name.as_deref(), Some("2024"));
+    }
+
+    #[test]
+    fn test_set_rejects_unknown_keys_and_bad_types() {
+        let mut config = sample_config();
+        let before = config.clone();
+
+        assert!(config.set_key("nope", "1").is_err());
+        assert!(config.set_key("lat", "north").is_err());
+        assert!(config.set_key("background.*.fillMode", "stretch").is_err());
+        assert!(config.get_key("weather.missing").is_err());
+        assert_eq!(config, before);
+    }
+
+    #[test]
+    fn test_trigger_conflict_names_used_and_ignored_sections() {
+        let mut config = sample_config();
+        assert_eq!(config.trigger_kind(), TriggerKind::DayTime);
+        assert!(config.trigger_conflict().is_none());
+
+        config.weather = Some(HashMap::new());
+        assert_eq!(config.trigger_kind(), TriggerKind::Weather);
+        assert_eq!(
+            config.trigger_conflict().unwrap(),
+            "multiple trigger sections configured; using [weather] and ignoring [timeConfig]"
+        );
+    }
+
+    #[test]
+    fn test_fill_modes_map_to_swaybg_and_round_trip() {
+        let expected = [
+            (FillMode::Fill, "fill", "fill"),
+            (FillMode::Crop, "crop", "center"),
+            (FillMode::Scale, "scale", "stretch"),
+        ];
+        assert_eq!(FillMode::ALL.len(), expected.len());
+        for (mode, (variant, name, swaybg)) in FillMode::ALL.iter().zip(expected) {
+            assert_eq!(*mode, variant);
+            assert_eq!(mode.name(), name);
+            assert_eq!(mode.as_swaybg_mode(), swaybg);
+            // The config name parses back to the same variant.
+            assert_eq!(
+                serde_json::from_value::<FillMode>(serde_json::json!(name)).unwrap(),
+                variant
+            );
+        }
+        // swaybg modes are distinct, so no two variants draw the same way.
+        let mut swaybg: Vec<_> = FillMode::ALL.iter().map(FillMode::as_swaybg_mode).collect();
+        swaybg.sort_unstable();
+        swaybg.dedup();
+        assert_eq!(swaybg.len(), FillMode::ALL.len());
+    }
+
+    #[test]
+    fn test_default_fill_mode_applies_unless_overridden() {
+        let config: Config = toml::from_str(
+            r#"
+            defaultFillMode = "scale"
+
+            [background."*"]
+            image = "a.png"
+
+            [background.HDMI-1]
+            image = "b.png"
+            fill_mode = "crop"
+            "#,
+        )
+        .unwrap();
+        let background = config.background.as_ref().unwrap();
+        assert_eq!(background["*"].fill_mode, None);
+        assert_eq!(config.fill_mode_for(&background["*"]), FillMode::Scale);
+        assert_eq!(config.fill_mode_for(&background["HDMI-1"]), FillMode::Crop);
+        assert_eq!(config.fill_mode_for_output("HDMI-1"), FillMode::Crop);
+        assert_eq!(config.fill_mode_for_output("DP-1"), FillMode::Scale);
+        assert_eq!(
+            Config {
+                background: None,
+                ..config.clone()
+            }
+            .fill_mode_for_output("HDMI-1"),
+            FillMode::Scale
+        );
+
+        let without_default = Config {
+            default_fill_mode: None,
+            ..config.clone()
+        };
+        assert_eq!(
+            without_default.fill_mode_for(&background["*"]),
+            FillMode::Fill
+        );
+    }
+
+    #[test]
+    fn test_is_daytime_uses_day_range() {
+        let mut config = Config::default();
+        assert!(config.is_daytime(8) && !config.is_daytime(19) && !config.is_daytime(7));
+
+        config.day_range = Some("22-08".to_string());
+        assert!(config.is_daytime(23) && config.is_daytime(3) && !config.is_daytime(12));
+    }
+
+    #[test]
+    fn test_day_range_bounds() {
+        let mut config = Config {
+            day_range: Some("08-08".to_string()),
+            ..Config::default()
+        };
+        assert!((0..24).all(|hour| config.is_daytime(hour)));
+        config.equal_day_range = Some(EqualDayRange::Night);
+        assert!((0..24).all(|hour| !config.is_daytime(hour)));
+
+        config.day_range = Some("0-23".to_string());
+        assert!(config.is_daytime(0) && config.is_daytime(22) && !config.is_daytime(23));
+        config.day_range = Some("23-0".to_string());
+        assert!(config.is_daytime(23) && !config.is_daytime(0) && !config.is_daytime(12));
+
+        assert_eq!(parse_day_range(" 6 - 18 ").unwrap(), (6, 18));
+        assert_eq!(parse_time_range("23:00-07:30").unwrap(), (1380, 450));
+        assert!(in_time_range((1380, 450), 0) && !in_time_range((1380, 450), 450));
+        assert!(in_time_range((60, 120), 60) && !in_time_range((60, 120), 30));
+        assert!(parse_time_range("23-07").is_err());
+        assert!(parse_time_range("24:00-07:00").is_err());
+        assert!(parse_time_range("23:60-07:00").is_err());
+        assert!(parse_day_range("0-24").is_err());
+        assert!(parse_day_range("24-08").is_err());
+        assert!(parse_day_range("8").is_err());
+        assert!(parse_day_range("day-night").is_err());
+
+        let dir = std::env::temp_dir().join("wallman_test_day_range_validation");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, "dayRange = \"08-25\"\n").unwrap();
+        assert!(matches!(
+            Config::load(path.clone()),
+            Err(WallmanError::Config(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_changed_keys_lists_differing_top_level_keys() {
+        let before = sample_config();
+        let mut after = before.clone();
+        assert!(before.changed_keys(&after).unwrap().is_empty());
+
+        after.lat = Some(1.0);
+        after.time_config = None;
+        assert_eq!(
+            before.changed_keys(&after).unwrap(),
+            vec!["lat".to_string(), "timeConfig".to_string()]
+        );
+    }
+
+    fn background(entries: &[(&str, &str)]) -> Option<HashMap<String, BackgroundConfig>> {
+        Some(
+            entries
+                .iter()
+                .map(|(output, image)| {
+                    (
+                        output.to_string(),
+                        BackgroundConfig {
+                            image: Some(image.to_string()),
+                            fill_mode: Some(FillMode::Fill),
+                            mode: None,
+                            blur_sigma: None,
+                            brightness: None,
+                            fallback_color: None,
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Merge a theme with `*` and `HDMI-1` backgrounds into a user config with
+    /// `HDMI-1` and `DP-1` ones, returning output → image.
+    fn merged_backgrounds(strategy: Option<MergeStrategy>, dir: &str) -> Vec<(String, String)> {
+        let theme_dir = std::env::temp_dir().join(dir);
+        let _ = fs::remove_dir_all(&theme_dir);
+        fs::create_dir_all(&theme_dir).unwrap();
+        let theme = Config {
+            background: background(&[("*", "theme-all.jpg"), ("HDMI-1", "theme-hdmi.jpg")]),
+            ..Config::default()
+        };
+        theme
+            .save_to_file(&theme_dir.join("manifest.toml"))
+            .unwrap();
+
+        let mut user = Config {
+            background: background(&[("HDMI-1", "my-hdmi.jpg"), ("DP-1", "my-dp.jpg")]),
+            merge_strategy: strategy,
+            ..Config::default()
+        };
+        user.merge_theme(theme_dir.clone()).unwrap();
+        fs::remove_dir_all(&theme_dir).unwrap();
+
+        let mut images: Vec<(String, String)> = user
+            .background
+            .unwrap()
+            .into_iter()
+            .map(|(output, bg)| (output, bg.image.unwrap()))
+            .collect();
+        images.sort();
+        images
+    }
+
+    fn pairs(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_strategy_theme_wins_by_default() {
+        let expected = pairs(&[("*", "theme-all.jpg"), ("HDMI-1", "theme-hdmi.jpg")]);
+        assert_eq!(
+            merged_backgrounds(None, "wallman_test_merge_default"),
+            expected
+        );
+        assert_eq!(
+            merged_backgrounds(Some(MergeStrategy::ThemeWins), "wallman_test_merge_theme"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_merge_strategy_user_wins() {
+        assert_eq!(
+            merged_backgrounds(Some(MergeStrategy::UserWins), "wallman_test_merge_user"),
+            pairs(&[("DP-1", "my-dp.jpg"), ("HDMI-1", "my-hdmi.jpg")])
+        );
+    }
+
+    #[test]
+    fn test_merge_strategy_deep_merges_per_output() {
+        assert_eq!(
+            merged_backgrounds(Some(MergeStrategy::Deep), "wallman_test_merge_deep"),
+            pairs(&[
+                ("*", "theme-all.jpg"),
+                ("DP-1", "my-dp.jpg"),
+                ("HDMI-1", "my-hdmi.jpg")
+            ])
+        );
+        let parsed: Config = toml::from_str("mergeStrategy = \"deep\"").unwrap();
+        assert_eq!(parsed.merge_strategy, Some(MergeStrategy::Deep));
+    }
+
     #[test]
     fn test_merge_theme_preserves_user_settings() {
         // Create a user config with lat, lon, and day_range set
-        let mut user_config = Config::default();
-        user_config.lat = Some(40.7128);  // New York
-        user_config.lon = Some(-74.0060);
-        user_config.day_range = Some("06-18".to_string()); // 6 AM to 6 PM
-        user_config.pool = Some("/old/theme/path".to_string());
-        user_config.name = Some("My Custom Config".to_string());
-        user_config.description = Some("User's custom configuration".to_string());
+        let user_config = Config {
+            lat: Some(40.7128), // New York
+            lon: Some(-74.0060),
+            day_range: Some("06-18".to_string()), // 6 AM to 6 PM
+            pool: Some(PoolConfig::Single("/old/theme/path".to_string())),
+            name: Some("My Custom Config".to_string()),
+            description: Some("User's custom configuration".to_string()),
+            ..Config::default()
+        };
 
         // Create a temporary theme directory and manifest
         let temp_dir = std::env::temp_dir().join("wallman_test_theme");
         let _ = fs::remove_dir_all(&temp_dir); // Clean up if exists
         fs::create_dir_all(&temp_dir).unwrap();
-        
+
         let theme_config = Config {
-            pool: Some("/theme/pool/path".to_string()),
+            pool: Some(PoolConfig::Single("/theme/pool/path".to_string())),
             version: Some(1),
             name: Some("Test Theme".to_string()),
             description: Some("A test theme".to_string()),
@@ -180,7 +1645,11 @@ mod tests {
                 "HDMI-1".to_string(),
                 BackgroundConfig {
                     image: Some("theme-background.jpg".to_string()),
-                    fill_mode: FillMode::Fill,
+                    fill_mode: Some(FillMode::Fill),
+                    mode: None,
+                    blur_sigma: None,
+                    brightness: None,
+                    fallback_color: None,
                 },
             )])),
             time_config: Some(std::collections::HashMap::from([(
@@ -188,6 +1657,8 @@ mod tests {
                 DayTimeConfig {
                     day: "day-image.jpg".to_string(),
                     night: "night-image.jpg".to_string(),
+                    rules: Vec::new(),
+                    fallback_color: None,
                 },
             )])),
             weather: Some(std::collections::HashMap::from([(
@@ -197,11 +1668,14 @@ mod tests {
                         ("sunny".to_string(), "sunny.jpg".to_string()),
                         ("cloudy".to_string(), "cloudy.jpg".to_string()),
                     ]),
+                    fallback_color: None,
+                    quiet_hours: None,
                 },
             )])),
-            lat: Some(51.5074),  // London (different from user)
+            lat: Some(51.5074), // London (different from user)
             lon: Some(-0.1278),
             day_range: Some("07-19".to_string()), // Different from user
+            ..Config::default()
         };
 
         let manifest_path = temp_dir.join("manifest.toml");
@@ -226,9 +1700,95 @@ mod tests {
         assert!(merged_config.weather.is_some());
 
         // Verify that pool is updated from theme
-        assert_eq!(merged_config.pool, Some("/theme/pool/path".to_string()));
+        assert_eq!(
+            merged_config.pool,
+            Some(PoolConfig::Single("/theme/pool/path".to_string()))
+        );
 
         // Cleanup
         fs::remove_dir_all(&temp_dir).unwrap();
     }
+
+    #[test]
+    fn test_weekday_rules_pick_most_specific() {
+        let config: Config = toml::from_str(
+            r#"
+            [timeConfig."*"]
+            day = "day.jpg"
+            night = "night.jpg"
+
+            [[timeConfig."*".rules]]
+            days = ["sat", "sun"]
+            day = "weekend-day.jpg"
+            night = "weekend-night.jpg"
+
+            [[timeConfig."*".rules]]
+            days = ["saturday"]
+            day = "saturday-day.jpg"
+            night = "saturday-night.jpg"
+            "#,
+        )
+        .unwrap();
+        let tc = &config.time_config.unwrap()["*"];
+
+        assert_eq!(
+            tc.images_for(chrono::Weekday::Sat),
+            ("saturday-day.jpg", "saturday-night.jpg")
+        );
+        assert_eq!(
+            tc.images_for(chrono::Weekday::Sun),
+            ("weekend-day.jpg", "weekend-night.jpg")
+        );
+        assert_eq!(
+            tc.images_for(chrono::Weekday::Tue),
+            ("day.jpg", "night.jpg")
+        );
+    }
+
+    #[test]
+    fn test_state_images_and_preview_states() {
+        let config: Config = toml::from_str(
+            r#"
+            [weather."*".weather]
+            rainy = "wet.jpg"
+            clear = "sun.jpg"
+
+            [timeConfig."HDMI-1"]
+            day = "day.jpg"
+            night = "night.jpg"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.preview_states(),
+            ["clear", "rainy", "day", "night"].map(String::from)
+        );
+        assert_eq!(
+            config.state_images("rainy"),
+            HashMap::from([("*".to_string(), "wet.jpg".to_string())])
+        );
+        assert_eq!(
+            config.state_images("night"),
+            HashMap::from([("HDMI-1".to_string(), "night.jpg".to_string())])
+        );
+        assert!(config.state_images("foggy").is_empty());
+    }
+
+    #[test]
+    fn test_image_meta_sidecar() {
+        let meta = ImageMeta::parse("fill_mode = \"scale\"\nrotate = 90\nblur = 4.0\n").unwrap();
+        assert_eq!(meta.fill_mode, Some(FillMode::Scale));
+        assert_eq!(meta.rotate, Some(90));
+        assert!(ImageMeta::parse("rotate = 45").is_err());
+
+        let image = Path::new("/themes/forest/images/pine.jpg");
+        let sidecar = ImageMeta::sidecar_path(image);
+        assert_eq!(
+            sidecar,
+            Path::new("/themes/forest/images/pine.jpg.meta.toml")
+        );
+        assert!(ImageMeta::is_sidecar(&sidecar));
+        assert!(!ImageMeta::is_sidecar(image));
+    }
 }
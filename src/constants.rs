@@ -1,23 +1,58 @@
-use std::path::PathBuf;
+use std::{ffi::OsString, path::PathBuf};
 
+/// Config candidates in search order; `Config::discover` tries each as-is,
+/// then with `.toml`, `.json`, `.yaml` and `.yml` appended.
 pub fn config_vec() -> Vec<PathBuf> {
     vec![
-        dirs::config_dir()
-            .unwrap_or(PathBuf::from("/home/"))
-            .join("wallman/config"),
+        config_folder().join("config"),
         PathBuf::from("/etc/wallman/config"),
         PathBuf::from("/etc/wallman.conf"),
     ]
 }
 
+/// `$WALLMAN_CONFIG_DIR`, else `$XDG_CONFIG_HOME/wallman`, else `~/.config/wallman`.
 pub fn config_folder() -> PathBuf {
-    dirs::config_dir()
-        .unwrap_or(PathBuf::from("/home/"))
-        .join("wallman/")
+    wallman_dir(
+        |var| std::env::var_os(var),
+        "WALLMAN_CONFIG_DIR",
+        "XDG_CONFIG_HOME",
+        ".config",
+    )
 }
 
+/// `$WALLMAN_DATA_DIR`, else `$XDG_DATA_HOME/wallman`, else `~/.local/share/wallman`.
 pub fn data_folder() -> PathBuf {
-    dirs::data_local_dir().unwrap().join("wallman/")
+    wallman_dir(
+        |var| std::env::var_os(var),
+        "WALLMAN_DATA_DIR",
+        "XDG_DATA_HOME",
+        ".local/share",
+    )
+}
+
+/// Resolve a wallman directory from an explicit override, an XDG base
+/// directory or a path under `$HOME`. Relative values are ignored, as the
+/// XDG spec requires; without a home directory the temp dir is used so
+/// minimal environments (stripped-down units, containers) don't panic.
+fn wallman_dir(
+    env: impl Fn(&str) -> Option<OsString>,
+    override_var: &str,
+    xdg_var: &str,
+    home_default: &str,
+) -> PathBuf {
+    let absolute = |var: &str| {
+        env(var)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+    if let Some(dir) = absolute(override_var) {
+        return dir;
+    }
+    absolute(xdg_var)
+        .or_else(|| absolute("HOME").map(|home| home.join(home_default)))
+        .or_else(dirs::home_dir)
+        .map(|base| base.join("wallman/"))
+        .unwrap_or_else(|| std::env::temp_dir().join("wallman/"))
 }
 
 pub fn decompresion_folder() -> PathBuf {
@@ -31,3 +66,39 @@ pub fn day_start() -> u32 {
 pub fn day_end() -> u32 {
     19
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_with(vars: &[(&str, &str)]) -> PathBuf {
+        let env = |var: &str| {
+            vars.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| OsString::from(value))
+        };
+        wallman_dir(env, "WALLMAN_DATA_DIR", "XDG_DATA_HOME", ".local/share")
+    }
+
+    #[test]
+    fn test_wallman_dir_precedence() {
+        let home = [("HOME", "/home/me")];
+        assert_eq!(
+            dir_with(&home),
+            PathBuf::from("/home/me/.local/share/wallman/")
+        );
+        assert_eq!(
+            dir_with(&[("HOME", "/home/me"), ("XDG_DATA_HOME", "/xdg")]),
+            PathBuf::from("/xdg/wallman/")
+        );
+        assert_eq!(
+            dir_with(&[("XDG_DATA_HOME", "/xdg"), ("WALLMAN_DATA_DIR", "/srv/wm")]),
+            PathBuf::from("/srv/wm")
+        );
+        // Relative values are not valid XDG directories.
+        assert_eq!(
+            dir_with(&[("HOME", "/home/me"), ("XDG_DATA_HOME", "rel")]),
+            PathBuf::from("/home/me/.local/share/wallman/")
+        );
+    }
+}
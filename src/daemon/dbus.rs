@@ -0,0 +1,131 @@
+use std::sync::{OnceLock, mpsc::Sender};
+
+use zbus::{blocking::Connection, interface, names::BusName, object_server::SignalEmitter};
+
+use crate::{error::WallmanError, manager::ControlRequest, trigger::OutputChange};
+
+/// Well-known bus name claimed on the session bus.
+pub const BUS_NAME: &str = "org.wallman.Daemon";
+
+/// Object path the control interface is served at.
+pub const OBJECT_PATH: &str = "/org/wallman/Daemon";
+
+/// Session bus connection, kept alive for the daemon's lifetime.
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+
+/// `org.wallman.Daemon` — forwards method calls to the trigger manager.
+struct DaemonInterface {
+    control: Sender<ControlRequest>,
+}
+
+impl DaemonInterface {
+    fn send(&self, request: ControlRequest) -> zbus::fdo::Result<()> {
+        self.control
+            .send(request)
+            .map_err(|_| zbus::fdo::Error::Failed("wallman daemon loop is not running".into()))
+    }
+}
+
+#[interface(name = "org.wallman.Daemon")]
+impl DaemonInterface {
+    /// Show `path` on `output` immediately.
+    fn set_wallpaper(&self, output: String, path: String) -> zbus::fdo::Result<()> {
+        self.send(ControlRequest::SetWallpaper {
+            output,
+            image_path: path,
+        })
+    }
+
+    /// Re-evaluate all triggers now.
+    fn next(&self) -> zbus::fdo::Result<()> {
+        self.send(ControlRequest::Next)
+    }
+
+    /// Reload the config file.
+    fn reload(&self) -> zbus::fdo::Result<()> {
+        self.send(ControlRequest::Reload)
+    }
+
+    /// Switch to an installed theme (by name) or theme folder without
+    /// re-reading the config file.
+    fn set_pool(&self, name: String) -> zbus::fdo::Result<()> {
+        self.send(ControlRequest::SetPool(crate::PoolConfig::dir(&name)))
+    }
+
+    /// Emitted after an output starts showing a new wallpaper.
+    #[zbus(signal)]
+    async fn wallpaper_changed(
+        emitter: &SignalEmitter<'_>,
+        output: &str,
+        path: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// Claim `BUS_NAME` on the session bus and serve the control interface.
+pub fn serve(control: Sender<ControlRequest>) -> crate::Result<()> {
+    let connection = zbus::blocking::connection::Builder::session()
+        .and_then(|builder| builder.name(BUS_NAME))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, DaemonInterface { control }))
+        .and_then(|builder| builder.build())
+        .map_err(|e| WallmanError::Daemon(format!("D-Bus: {}", e)))?;
+
+    CONNECTION
+        .set(connection)
+        .map_err(|_| WallmanError::Daemon("D-Bus service already started".to_string()))?;
+    tracing::info!("D-Bus interface {} available at {}", BUS_NAME, OBJECT_PATH);
+    Ok(())
+}
+
+/// Ask the running daemon to switch to theme `name` (`SetPool`).
+pub fn request_set_pool(name: &str) -> crate::Result<()> {
+    call("SetPool", &(name,))
+}
+
+/// Ask the running daemon to show `path` on `output` (`SetWallpaper`).
+pub fn request_set_wallpaper(output: &str, path: &str) -> crate::Result<()> {
+    call("SetWallpaper", &(output, path))
+}
+
+/// Whether a daemon has claimed `BUS_NAME` on the session bus.
+pub fn is_serving() -> bool {
+    let Ok(connection) = Connection::session() else {
+        return false;
+    };
+    let Ok(proxy) = zbus::blocking::fdo::DBusProxy::new(&connection) else {
+        return false;
+    };
+    BusName::try_from(BUS_NAME)
+        .ok()
+        .and_then(|name| proxy.name_has_owner(name).ok())
+        .unwrap_or(false)
+}
+
+/// Call `method` on the running daemon's control interface.
+fn call<B>(method: &str, body: &B) -> crate::Result<()>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let error = |e: zbus::Error| WallmanError::Daemon(format!("D-Bus: {}", e));
+    let connection = Connection::session().map_err(error)?;
+    connection
+        .call_method(Some(BUS_NAME), OBJECT_PATH, Some(BUS_NAME), method, body)
+        .map_err(error)?;
+    Ok(())
+}
+
+/// Broadcast `WallpaperChanged` for an applied change. No-op if D-Bus isn't up.
+pub fn emit_wallpaper_changed(change: &OutputChange) {
+    let Some(connection) = CONNECTION.get() else {
+        return;
+    };
+
+    if let Err(e) = connection.emit_signal(
+        None::<BusName<'_>>,
+        OBJECT_PATH,
+        BUS_NAME,
+        "WallpaperChanged",
+        &(change.output.as_str(), change.image_path.as_str()),
+    ) {
+        tracing::warn!("Failed to emit WallpaperChanged: {}", e);
+    }
+}
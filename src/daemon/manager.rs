@@ -1,12 +1,20 @@
 use std::{
     fs,
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{self, Command},
+    sync::mpsc::Sender,
 };
 
 use tracing::info;
 
+use crate::{
+    FillMode, OutputTarget,
+    daemon::status,
+    error::{Result, WallmanError},
+    triggers::manager::ControlRequest,
+};
+
 /// Exit codes returned by daemon operations.
 pub enum DaemonExitCode {
     Success = 0,
@@ -17,17 +25,46 @@ pub enum DaemonExitCode {
 
 /// Manages the wallman daemon process lifecycle via a PID file.
 ///
-/// The PID file is stored at `<data_dir>/wallman/daemon.pid`.
+/// The PID file is stored at `<data_dir>/wallman/daemon.pid` unless overridden
+/// by `--pid-file` or `$WALLMAN_PID_FILE`.
 /// The daemon itself is the `wallman daemon start --foreground` process; the
 /// non-foreground path re-invokes the current executable with
-/// `daemon start --foreground` and detaches via double-fork.
+/// `daemon start --foreground` in a new session (`setsid`).
 pub struct DaemonManager {
     pid_file: PathBuf,
 }
 
+impl Default for DaemonManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Environment variable that overrides the default PID file location.
+pub const PID_FILE_ENV: &str = "WALLMAN_PID_FILE";
+
+/// Variables that show the daemon runs inside a Wayland session; at least one
+/// must be set unless the start is forced.
+pub const SESSION_ENV: [&str; 2] = ["WAYLAND_DISPLAY", "SWAYSOCK"];
+
+/// How long post-loop cleanup may take before the daemon exits anyway.
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long `stop` and `restart` wait for the daemon to exit; longer than
+/// `SHUTDOWN_GRACE`, after which the daemon exits on its own.
+const STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(8);
+
 impl DaemonManager {
+    /// Use `$WALLMAN_PID_FILE` if set, otherwise `<data_dir>/wallman/daemon.pid`.
     pub fn new() -> Self {
-        let pid_file = crate::data_folder().join("daemon.pid");
+        let pid_file = std::env::var_os(PID_FILE_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| crate::data_folder().join("daemon.pid"));
+        Self { pid_file }
+    }
+
+    /// Use an explicit PID file (e.g. from `--pid-file`).
+    pub fn with_pid_file(pid_file: PathBuf) -> Self {
         Self { pid_file }
     }
 
@@ -38,60 +75,120 @@ impl DaemonManager {
     /// If `foreground` is true, run the trigger loop directly in this process
     /// (used by the re-invoked child after double-fork).
     /// If false, spawn a detached child process and return immediately.
-    pub fn start(&self, foreground: bool) -> Result<(), Box<dyn std::error::Error>> {
-        let _ = Command::new("killall").arg("swaybg").spawn();
+    /// `force` skips the Wayland session check (see `SESSION_ENV`).
+    pub fn start(&self, foreground: bool, force: bool) -> Result<()> {
         if foreground {
-            self.run_foreground()
+            self.run_foreground(force)
         } else {
-            if let Some(pid) = self.read_pid()? {
-                if self.is_process_running(pid) {
-                    return Err(format!(
-                        "Daemon is already running (PID {pid}). \
-                        Use `wallman daemon restart` to restart it."
-                    )
-                    .into());
-                }
-                // Stale PID file — remove it before re-spawning.
-                let _ = fs::remove_file(&self.pid_file);
+            if let Some(pid) = self.read_pid()?
+                && self.is_process_running(pid)
+            {
+                return Err(WallmanError::AlreadyRunning { pid });
             }
-            self.spawn_detached()
+            // A stale PID file is unlocked, so the child simply takes it over.
+            self.spawn_detached(force)
         }
     }
 
     /// Stop the daemon by sending SIGTERM to the stored PID.
-    pub fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let pid = self
-            .read_pid()?
-            .ok_or("Daemon is not running (no PID file found)")?;
-
-        if !self.is_process_running(pid) {
-            let _ = fs::remove_file(&self.pid_file);
-            return Err(
-                format!("No process found with PID {pid}. Cleaned up stale PID file.").into(),
+    ///
+    /// Without a running daemon, the wallpaper processes `wallman set`
+    /// left behind are stopped instead.
+    pub fn stop(&self) -> Result<()> {
+        let pid = self.read_pid()?.filter(|pid| self.is_process_running(*pid));
+        let Some(pid) = pid else {
+            let leftovers = self.recorded_backends();
+            if leftovers.is_empty() {
+                return Err(WallmanError::Daemon(match self.read_pid()? {
+                    Some(pid) => format!("No process found with PID {pid}; the PID file is stale."),
+                    None => "Daemon is not running (no PID file found)".to_string(),
+                }));
+            }
+            for backend in &leftovers {
+                stop_backend(backend);
+            }
+            let _ = fs::remove_file(status::state_file_for(&self.pid_file));
+            crate::cli::say!(
+                "Stopped {} wallpaper process(es) started by `wallman set`.",
+                leftovers.len()
             );
-        }
+            return Ok(());
+        };
 
-        self.send_sigterm(pid)?;
-        let _ = fs::remove_file(&self.pid_file);
+        self.send_stop(pid, false)?;
+        self.wait_for_exit(pid)?;
         tracing::info!("Daemon (PID {}) stopped.", pid);
         Ok(())
     }
 
     /// Restart = stop (if running) then start.
-    pub fn restart(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(pid) = self.read_pid()? {
-            if self.is_process_running(pid) {
-                self.send_sigterm(pid)?;
-                // Brief wait for the process to exit.
-                std::thread::sleep(std::time::Duration::from_millis(500));
+    ///
+    /// The old daemon leaves its wallpapers up and the new one takes them
+    /// over, so an unchanged wallpaper is not redrawn. With `if_running`, a
+    /// daemon that is not running is left stopped.
+    pub fn restart(&self, force: bool, if_running: bool) -> Result<()> {
+        let running = self
+            .read_pid()?
+            .is_some_and(|pid| self.is_process_running(pid));
+        if if_running && !running {
+            crate::cli::say!("wallman daemon is not running; nothing to restart.");
+            return Ok(());
+        }
+        // Checked before stopping, so a refused restart leaves the daemon up.
+        ensure_config_file()?;
+        if let Some(pid) = self.read_pid()?
+            && self.is_process_running(pid)
+        {
+            self.send_stop(pid, true)?;
+            self.wait_for_exit(pid)?;
+        }
+        self.spawn_detached(force)
+    }
+
+    /// Show `image` on `target`.
+    ///
+    /// A running daemon is asked to draw it, so it keeps managing those
+    /// outputs. Otherwise it is drawn here by processes detached from the
+    /// terminal, which are recorded in the state file; they replace the ones
+    /// an earlier `set` left on the same outputs, the next `daemon start`
+    /// takes them over and `daemon stop` ends them.
+    pub fn set_wallpaper(&self, target: &OutputTarget, image: &Path, fill: FillMode) -> Result<()> {
+        let daemon = self.read_pid()?.filter(|pid| self.is_process_running(*pid));
+        if daemon.is_some() || crate::daemon::is_on_bus() {
+            let (image_path, outputs) = crate::api::resolve(target, image)?;
+            for output in &outputs {
+                crate::daemon::request_set_wallpaper(output, &image_path)?;
             }
-            let _ = fs::remove_file(&self.pid_file);
+            return Ok(());
         }
-        self.spawn_detached()
+
+        crate::wallpaper::apply::set_detach(true);
+        let result = crate::set_wallpaper_on(target, image, fill);
+        if !crate::wallpaper::apply::is_dry_run() {
+            let drawn: Vec<String> = crate::wallpaper::tracked_processes()
+                .into_iter()
+                .flat_map(|(_, changes)| changes)
+                .map(|change| change.output)
+                .collect();
+            let (replaced, kept): (Vec<_>, Vec<_>) =
+                self.recorded_backends().into_iter().partition(|backend| {
+                    backend.outputs.is_empty() || backend.outputs.iter().any(|o| drawn.contains(o))
+                });
+            for backend in &replaced {
+                stop_backend(backend);
+            }
+            status::record_detached(&status::state_file_for(&self.pid_file), kept);
+        }
+        result
     }
 
-    /// Print daemon status to stdout.
-    pub fn status(&self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Print daemon status to stdout, as JSON when `json` is set.
+    pub fn status(&self, json: bool) -> Result<()> {
+        if json {
+            println!("{}", serde_json::to_string_pretty(&self.status_json()?)?);
+            return Ok(());
+        }
+
         match self.read_pid()? {
             None => {
                 println!("wallman daemon: stopped (no PID file)");
@@ -99,6 +196,10 @@ impl DaemonManager {
             Some(pid) => {
                 if self.is_process_running(pid) {
                     println!("wallman daemon: running  (PID {})", pid);
+                    let state = status::read(&status::state_file_for(&self.pid_file));
+                    if let Some(backend) = state.and_then(|s| s.backend) {
+                        println!("  backend: {} (fallback)", backend);
+                    }
                 } else {
                     println!("wallman daemon: stopped  (stale PID file for {})", pid);
                 }
@@ -107,75 +208,319 @@ impl DaemonManager {
         Ok(())
     }
 
+    /// Build the `status --json` document from the PID and state files.
+    fn status_json(&self) -> Result<serde_json::Value> {
+        let pid = self.read_pid()?;
+        let running = pid.is_some_and(|pid| self.is_process_running(pid));
+        if !running {
+            return Ok(serde_json::json!({ "running": false, "pid": pid }));
+        }
+
+        let state = status::read(&status::state_file_for(&self.pid_file)).unwrap_or_default();
+        // Older daemons don't write a state file; the PID file is written at start.
+        let started_at = match state.started_at {
+            0 => fs::metadata(&self.pid_file)?
+                .modified()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            t => t,
+        };
+
+        Ok(serde_json::json!({
+            "running": true,
+            "pid": pid,
+            "startedAt": started_at,
+            "uptimeSecs": status::now_unix().saturating_sub(started_at),
+            "trigger": state.trigger,
+            "configPath": state.config_path,
+            "wallpapers": state.wallpapers,
+            "backend": state.backend,
+        }))
+    }
+
     // ── Internal helpers ──────────────────────────────────────────────────
 
     /// Run the trigger loop in this process (foreground / child mode).
-    fn run_foreground(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // Write our own PID.
-        self.write_pid(process::id())?;
-        // Install SIGTERM handler to clean up the PID file on shutdown.
-        #[cfg(unix)]
-        {
-            let pid_file = self.pid_file.clone();
-            unsafe {
-                nix::sys::signal::signal(
-                    nix::sys::signal::Signal::SIGTERM,
-                    nix::sys::signal::SigHandler::Handler(handle_sigterm),
-                )
-                .ok();
-            }
-            // Store for the signal handler (static).
-            PID_FILE_PATH
-                .set(pid_file)
-                .expect("PID_FILE_PATH set twice");
+    fn run_foreground(&self, force: bool) -> Result<()> {
+        // Refuse to start without a backend rather than failing on every apply.
+        if !force {
+            ensure_wayland_session()?;
+        }
+        crate::wallpaper::apply::ensure_backend_available()?;
+
+        // Claim the PID file before touching any swaybg, so a losing duplicate
+        // start leaves the winner's wallpapers alone.
+        let _pid_lock = self.claim_pid_file(process::id())?;
+        if !crate::wallpaper::apply::is_dry_run() {
+            self.adopt_recorded_backends();
         }
 
         info!("Daemon started in foreground (PID {})", process::id());
 
         // Build and run the trigger manager.
         let mut manager = build_trigger_manager()?;
-        manager.run()?;
+        let control = manager.control_sender();
 
+        // SIGTERM asks the trigger loop to stop; cleanup happens below once
+        // `run` returns.
+        #[cfg(unix)]
+        spawn_signal_thread(control.clone(), self.pid_file.clone())?;
+
+        crate::wallpaper::preload::spawn();
+
+        let config_path = crate::APP_STATE
+            .get()
+            .map(|state| state.lock().unwrap().config_path.clone())
+            .unwrap_or_default();
+        status::begin(
+            status::state_file_for(&self.pid_file),
+            status::RuntimeState {
+                started_at: status::now_unix(),
+                trigger: manager.names().join(","),
+                config_path,
+                ..Default::default()
+            },
+        );
+
+        #[cfg(feature = "dbus")]
+        if let Err(e) = crate::daemon::dbus::serve(control.clone()) {
+            tracing::warn!("D-Bus interface unavailable: {}", e);
+        }
+
+        let has_seasons = crate::APP_STATE
+            .get()
+            .is_some_and(|state| state.lock().unwrap().config.seasons.is_some());
+        if has_seasons {
+            manager.add(Box::new(crate::season_trigger::SeasonTrigger::new(control)));
+        }
+
+        manager.init();
+
+        let heartbeat_secs = crate::APP_STATE
+            .get()
+            .and_then(|state| state.lock().unwrap().config.heartbeat_secs)
+            .filter(|secs| *secs > 0);
+        if let Some(secs) = heartbeat_secs {
+            let path = status::heartbeat_file_for(&self.pid_file);
+            info!("Touching {} every {}s", path.display(), secs);
+            manager.add_heartbeat(std::time::Duration::from_secs(secs), move || {
+                status::touch_heartbeat(&path)
+            });
+        }
+
+        #[cfg(feature = "systemd")]
+        {
+            use crate::daemon::systemd;
+            if let Some(interval) = systemd::watchdog_interval() {
+                tracing::info!("systemd watchdog enabled (ping every {:?})", interval);
+                manager.add_heartbeat(interval, systemd::ping_watchdog);
+            }
+            systemd::notify_ready();
+        }
+
+        let result = manager.run();
+        self.shut_down();
+        result
+    }
+
+    /// Cleanup after the trigger loop returned. The watchdog (armed on SIGTERM
+    /// already, if that is what ended the loop) hard-exits if it takes longer
+    /// than `SHUTDOWN_GRACE`.
+    fn shut_down(&self) {
+        info!("Daemon shutting down");
+        arm_shutdown_watchdog(self.pid_file.clone());
+
+        #[cfg(feature = "systemd")]
+        crate::daemon::systemd::notify_stopping();
+
+        if handing_over() {
+            // The state file keeps them for the next daemon to take over.
+            info!("Leaving the wallpapers up for the next daemon");
+        } else {
+            // Don't leave our swaybg children behind for the next start to duplicate.
+            crate::wallpaper::kill_all();
+        }
+        remove_own_files(&self.pid_file);
+    }
+
+    /// Wait until `pid` exits; the daemon removes its own files on the way out.
+    fn wait_for_exit(&self, pid: u32) -> Result<()> {
+        let deadline = std::time::Instant::now() + STOP_TIMEOUT;
+        while self.is_process_running(pid) {
+            if std::time::Instant::now() >= deadline {
+                return Err(WallmanError::Daemon(format!(
+                    "daemon (PID {pid}) did not exit within {:?} of SIGTERM",
+                    STOP_TIMEOUT
+                )));
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
         Ok(())
     }
 
     /// Spawn a detached child that runs `wallman daemon start --foreground`.
-    fn spawn_detached(&self) -> Result<(), Box<dyn std::error::Error>> {
+    fn spawn_detached(&self, force: bool) -> Result<()> {
+        ensure_config_file()?;
+        // Check here too: the detached child's stderr goes to /dev/null.
+        if !force {
+            ensure_wayland_session()?;
+        }
+        crate::wallpaper::apply::ensure_backend_available()?;
         let exe = std::env::current_exe()?;
         info!("Spawning detached child");
-        let child = std::process::Command::new(&exe)
-            .args(&["daemon", "start", "--foreground"])
+        let mut command = std::process::Command::new(&exe);
+        command.args(["daemon", "start", "--foreground"]);
+
+        // The child must track itself in the same PID file we check.
+        command.arg("--pid-file").arg(&self.pid_file);
+
+        // The child's stdio is discarded, so always give it a log file.
+        let mut log_options = crate::logging::options().cloned().unwrap_or_default();
+        let log_file = log_options
+            .log_file
+            .get_or_insert_with(crate::logging::default_log_file)
+            .clone();
+        command.args(log_options.to_args());
+        if crate::wallpaper::apply::is_dry_run() {
+            command.arg("--dry-run");
+        }
+        if force {
+            command.arg("--force");
+        }
+
+        // Pin the child to the config this process resolved (`--config` or discovery).
+        if let Some(state) = crate::APP_STATE.get() {
+            let config_path = PathBuf::from(&state.lock().unwrap().config_path);
+            if config_path.exists() {
+                command.arg("--config").arg(config_path);
+            }
+        }
+
+        // On Unix, create a new session so the child is not killed
+        // when the terminal closes.
+        #[cfg(unix)]
+        detach_session(&mut command);
+
+        let child = command
             // Detach stdio so the parent can exit cleanly.
             .stdin(std::process::Stdio::null())
             .stdout(std::process::Stdio::null())
             .stderr(std::process::Stdio::null())
-            // On Unix, create a new session so the child is not killed
-            // when the terminal closes.
             .spawn()?;
 
+        // The child writes its own PID in `run_foreground`, so the PID file keeps
+        // pointing at the daemon after this parent process exits.
+
         tracing::info!("Daemon spawned (PID {}).", child.id());
-        println!("wallman daemon started (PID {}).", child.id());
+        crate::cli::say!("wallman daemon started (PID {}).", child.id());
+        crate::cli::say!("Logging to {} (rotated daily).", log_file.display());
         Ok(())
     }
 
+    /// Take over the backend processes the previous daemon on this PID file
+    /// (or `wallman set`) recorded and left running, so their wallpapers stay
+    /// up without a redraw; `apply` skips outputs that already show the
+    /// right image. Records that lack what the process draws are stopped
+    /// instead. Wallpaper processes it did not start (another session's, the
+    /// user's own) are left alone.
+    fn adopt_recorded_backends(&self) {
+        for backend in self.recorded_backends() {
+            if backend.outputs.is_empty() || backend.image_path.is_empty() {
+                stop_backend(&backend);
+                continue;
+            }
+            info!(
+                "Taking over {} (PID {}) showing '{}' on output(s) '{}'",
+                backend.comm,
+                backend.pid,
+                backend.image_path,
+                backend.outputs.join("', '")
+            );
+            crate::wallpaper::register_process(
+                backend.outputs,
+                crate::wallpaper::ProcessHandle::Adopted(backend.pid),
+                backend.image_path,
+                backend.fill_mode,
+            );
+        }
+    }
+
+    /// The backend processes recorded in the state file that still run.
+    fn recorded_backends(&self) -> Vec<status::BackendProcess> {
+        let Some(previous) = status::read(&status::state_file_for(&self.pid_file)) else {
+            return Vec::new();
+        };
+        previous
+            .backend_processes
+            .into_iter()
+            // The PID may have been reused since; only keep the same program.
+            .filter(|backend| {
+                status::process_comm(backend.pid).as_deref() == Some(backend.comm.as_str())
+            })
+            .collect()
+    }
+
     /// Read the PID stored in the PID file; returns None if file doesn't exist.
-    fn read_pid(&self) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+    fn read_pid(&self) -> Result<Option<u32>> {
         if !self.pid_file.exists() {
             return Ok(None);
         }
         let contents = fs::read_to_string(&self.pid_file)?;
-        let pid: u32 = contents.trim().parse()?;
+        let pid: u32 = contents.trim().parse().map_err(|_| {
+            WallmanError::Daemon(format!(
+                "invalid PID file {}: {:?}",
+                self.pid_file.display(),
+                contents.trim()
+            ))
+        })?;
         Ok(Some(pid))
     }
 
-    /// Write a PID to the PID file (creates parent dirs if needed).
-    fn write_pid(&self, pid: u32) -> io::Result<()> {
+    /// Claim the PID file for `pid` (creates parent dirs if needed).
+    ///
+    /// The file is opened and locked with `flock` before the PID is written.
+    /// The kernel holds the lock until the returned `PidLock` is dropped or the
+    /// process dies, so of two racing daemons exactly one wins, and a file
+    /// left behind by a dead daemon is unlocked and simply taken over.
+    /// Its contents never decide anything.
+    fn claim_pid_file(&self, pid: u32) -> Result<PidLock> {
         if let Some(parent) = self.pid_file.parent() {
             fs::create_dir_all(parent)?;
         }
-        let mut f = fs::File::create(&self.pid_file)?;
-        write!(f, "{}", pid)?;
-        Ok(())
+        // One retry: the previous owner may remove the file between our open
+        // and our lock, leaving us holding a lock on an unlinked file.
+        for _ in 0..2 {
+            let mut file = fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&self.pid_file)?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(fs::TryLockError::WouldBlock) => {
+                    return Err(match self.read_pid() {
+                        Ok(Some(owner)) => WallmanError::AlreadyRunning { pid: owner },
+                        _ => WallmanError::Daemon(format!(
+                            "PID file {} is locked by another daemon",
+                            self.pid_file.display()
+                        )),
+                    });
+                }
+                Err(fs::TryLockError::Error(e)) => return Err(e.into()),
+            }
+            if !is_same_file(&file, &self.pid_file)? {
+                continue;
+            }
+
+            file.set_len(0)?;
+            write!(file, "{}", pid)?;
+            return Ok(PidLock { _file: file });
+        }
+        Err(WallmanError::Daemon(format!(
+            "could not claim PID file {}",
+            self.pid_file.display()
+        )))
     }
 
     /// Returns true if a process with the given PID currently exists.
@@ -184,7 +529,8 @@ impl DaemonManager {
         {
             use nix::sys::signal;
             use nix::unistd::Pid;
-            signal::kill(Pid::from_raw(pid as i32), None).is_ok()
+            // A zombie has exited; it only waits for its parent to reap it.
+            signal::kill(Pid::from_raw(pid as i32), None).is_ok() && !is_zombie(pid)
         }
         #[cfg(not(unix))]
         {
@@ -193,66 +539,362 @@ impl DaemonManager {
         }
     }
 
-    /// Send SIGTERM to a process by PID.
-    fn send_sigterm(&self, pid: u32) -> Result<(), Box<dyn std::error::Error>> {
+    /// Ask the daemon `pid` to stop: SIGTERM, or SIGUSR1 to `hand_over` its
+    /// wallpapers to the next daemon (see `spawn_signal_thread`).
+    fn send_stop(&self, pid: u32, hand_over: bool) -> Result<()> {
         #[cfg(unix)]
         {
             use nix::sys::signal::{self, Signal};
             use nix::unistd::Pid;
-            signal::kill(Pid::from_raw(pid as i32), Signal::SIGTERM)?;
+            let signal = if hand_over {
+                Signal::SIGUSR1
+            } else {
+                Signal::SIGTERM
+            };
+            signal::kill(Pid::from_raw(pid as i32), signal)?;
             Ok(())
         }
         #[cfg(not(unix))]
         {
-            Err(format!("Cannot send SIGTERM on this platform (PID {})", pid).into())
+            Err(WallmanError::Daemon(format!(
+                "Cannot send SIGTERM on this platform (PID {})",
+                pid
+            )))
         }
     }
 }
 
-// ── SIGTERM handler (Unix only) ───────────────────────────────────────────────
+/// A claimed PID file. Dropping it (or exiting) releases the lock.
+#[derive(Debug)]
+struct PidLock {
+    _file: fs::File,
+}
 
-/// Static storage for the PID file path so the signal handler can clean up.
-#[cfg(unix)]
-static PID_FILE_PATH: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+/// Whether `path` still names the file `file` has open.
+fn is_same_file(file: &fs::File, path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let open = file.metadata()?;
+    match fs::metadata(path) {
+        Ok(named) => Ok(open.dev() == named.dev() && open.ino() == named.ino()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Send SIGTERM to a recorded backend process.
+fn stop_backend(backend: &status::BackendProcess) {
+    info!(
+        "Stopping {} (PID {}) left on output(s) '{}'",
+        backend.comm,
+        backend.pid,
+        backend.outputs.join("', '")
+    );
+    #[cfg(unix)]
+    let _ = nix::sys::signal::kill(
+        nix::unistd::Pid::from_raw(backend.pid as i32),
+        nix::sys::signal::Signal::SIGTERM,
+    );
+}
+
+// ── Session detach (Unix only) ───────────────────────────────────────────────
 
+/// Make the spawned child call `setsid()` so it leads a new session with no
+/// controlling terminal, and no longer receives the terminal's SIGHUP.
 #[cfg(unix)]
-type LibcSig = libc::c_int;
+pub(crate) fn detach_session(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: `setsid` is async-signal-safe and touches no state of the parent.
+    unsafe {
+        command.pre_exec(|| nix::unistd::setsid().map(|_| ()).map_err(io::Error::from));
+    }
+}
+
+// ── SIGTERM handling (Unix only) ──────────────────────────────────────────────
+
+/// Set once `daemon restart` asked this daemon to leave its wallpapers up.
+static HANDING_OVER: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+fn handing_over() -> bool {
+    HANDING_OVER.load(std::sync::atomic::Ordering::SeqCst)
+}
 
+/// Turn SIGTERM into `ControlRequest::Shutdown` on a dedicated thread, which
+/// also arms the shutdown watchdog. SIGUSR1 (from `daemon restart`) does the
+/// same but leaves the wallpapers up for the next daemon. A second signal
+/// exits immediately in case the loop is stuck (e.g. in a slow weather
+/// request).
 #[cfg(unix)]
-extern "C" fn handle_sigterm(_: LibcSig) {
-    if let Some(path) = PID_FILE_PATH.get() {
-        let _ = fs::remove_file(path);
+fn spawn_signal_thread(control: Sender<ControlRequest>, pid_file: PathBuf) -> Result<()> {
+    use signal_hook::{
+        consts::{SIGTERM, SIGUSR1},
+        iterator::Signals,
+    };
+
+    let mut signals = Signals::new([SIGTERM, SIGUSR1])?;
+    std::thread::spawn(move || {
+        let mut received = signals.forever();
+        match received.next() {
+            None => return,
+            Some(SIGUSR1) => {
+                info!("SIGUSR1 received; stopping for a restart");
+                HANDING_OVER.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+            Some(_) => info!("SIGTERM received; stopping"),
+        }
+        arm_shutdown_watchdog(pid_file.clone());
+        let _ = control.send(ControlRequest::Shutdown);
+
+        if received.next().is_some() {
+            tracing::warn!("Second stop signal; exiting without cleanup");
+            remove_own_files(&pid_file);
+            process::exit(0);
+        }
+    });
+    Ok(())
+}
+
+/// Hard-exit if shutting down takes longer than `SHUTDOWN_GRACE`. Only the
+/// first call starts the timer.
+fn arm_shutdown_watchdog(pid_file: PathBuf) {
+    static ARMED: std::sync::Once = std::sync::Once::new();
+    ARMED.call_once(|| {
+        std::thread::spawn(move || {
+            std::thread::sleep(SHUTDOWN_GRACE);
+            tracing::error!(
+                "Shutdown did not finish within {:?}; exiting",
+                SHUTDOWN_GRACE
+            );
+            remove_own_files(&pid_file);
+            process::exit(1);
+        });
+    });
+}
+
+/// Remove the PID, state and heartbeat files, but only while the PID file
+/// still holds this process's PID: files of a daemon started since stay.
+/// When handing over, the state file stays for the next daemon.
+fn remove_own_files(pid_file: &Path) {
+    let owner = fs::read_to_string(pid_file)
+        .ok()
+        .and_then(|contents| contents.trim().parse::<u32>().ok());
+    if owner != Some(process::id()) {
+        tracing::debug!(
+            "PID file {} no longer names this process; leaving it",
+            pid_file.display()
+        );
+        return;
+    }
+    let _ = fs::remove_file(pid_file);
+    if !handing_over() {
+        let _ = fs::remove_file(status::state_file_for(pid_file));
     }
-    process::exit(0);
+    let _ = fs::remove_file(status::heartbeat_file_for(pid_file));
+}
+
+/// Whether `pid` has exited but not been reaped yet (Linux `/proc` only).
+fn is_zombie(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| Some(stat.rsplit_once(") ")?.1.starts_with('Z')))
+        .unwrap_or(false)
 }
 
 // ── Trigger manager factory ───────────────────────────────────────────────────
 
 /// Build the TriggerManager with all configured triggers, reading from APP_STATE.
-fn build_trigger_manager()
--> Result<crate::triggers::manager::TriggerManager, Box<dyn std::error::Error>> {
-    use crate::triggers::{
-        daytime_trigger::DayTimeTrigger, manager::TriggerManager, static_trigger::StaticTrigger,
-        weather_trigger::WeatherTrigger,
-    };
+fn build_trigger_manager() -> Result<crate::triggers::manager::TriggerManager> {
+    use crate::triggers::manager::TriggerManager;
 
     let state = crate::APP_STATE.get().unwrap().lock().unwrap();
     let config = state.config.clone();
     drop(state);
 
     let mut manager = TriggerManager::new();
+    let control = manager.control_sender();
 
-    // Mutual Exclusive Trigger Selection (§17/Phase 2)
-    // Priority: Weather > Time > Static
-    if config.weather.is_some() {
-        tracing::info!("Using WeatherTrigger (exclusive)");
-        manager.add(Box::new(WeatherTrigger::new()));
-    } else if config.time_config.is_some() {
-        tracing::info!("Using DayTimeTrigger (exclusive)");
-        manager.add(Box::new(DayTimeTrigger::new()));
-    } else {
-        tracing::info!("Using StaticTrigger (exclusive)");
-        manager.add(Box::new(StaticTrigger::new()));
+    if let Some(conflict) = config.trigger_conflict() {
+        tracing::warn!("{}", conflict);
     }
+
+    // Mutual Exclusive Trigger Selection (§17/Phase 2)
+    // Priority: Workspace > Weather > Network > Time > Static (`wallman explain` shows the choice)
+    let kind = config.trigger_kind();
+    tracing::info!("Using {} trigger (exclusive)", kind.name());
+    manager.add(trigger_for(kind, control));
     Ok(manager)
 }
+
+/// Refuse to detach with `--config -`: the child has no stdin to read the
+/// config from, and would silently fall back to the discovered file.
+fn ensure_config_file() -> Result<()> {
+    let from_stdin = crate::APP_STATE
+        .get()
+        .is_some_and(|state| state.lock().unwrap().config_path == crate::STDIN_CONFIG);
+    if from_stdin {
+        return Err(WallmanError::Daemon(
+            "a detached daemon cannot read its config from stdin (--config -); \
+             use `daemon start --foreground` or pass a config file"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Refuse to run outside a Wayland session, where swaybg cannot connect and
+/// every apply would fail. Dry runs draw nothing and are always allowed.
+fn ensure_wayland_session() -> Result<()> {
+    if crate::wallpaper::apply::is_dry_run() {
+        return Ok(());
+    }
+    session_error(|name| std::env::var_os(name).is_some_and(|v| !v.is_empty())).map_or(Ok(()), Err)
+}
+
+/// The error for a session where none of `SESSION_ENV` is set according to `is_set`.
+fn session_error(is_set: impl Fn(&str) -> bool) -> Option<WallmanError> {
+    if SESSION_ENV.iter().any(|name| is_set(name)) {
+        return None;
+    }
+    Some(WallmanError::Daemon(format!(
+        "not running in a Wayland session: neither {} is set. Start the daemon from your \
+         compositor (or import the variables into the systemd user environment), or pass \
+         --force to start anyway",
+        SESSION_ENV.join(" nor ")
+    )))
+}
+
+/// Instantiate the trigger for `kind`; `control` reaches the manager it runs in.
+fn trigger_for(
+    kind: crate::TriggerKind,
+    control: Sender<ControlRequest>,
+) -> Box<dyn crate::trigger::Trigger> {
+    use crate::TriggerKind;
+    use crate::triggers::{
+        daytime_trigger::DayTimeTrigger, network_trigger::NetworkTrigger,
+        static_trigger::StaticTrigger, weather_trigger::WeatherTrigger,
+        workspace_trigger::WorkspaceTrigger,
+    };
+
+    match kind {
+        TriggerKind::Workspace => Box::new(WorkspaceTrigger::new(control)),
+        TriggerKind::Weather => Box::new(WeatherTrigger::new()),
+        TriggerKind::Network => Box::new(NetworkTrigger::new()),
+        TriggerKind::DayTime => Box::new(DayTimeTrigger::new()),
+        TriggerKind::Static => Box::new(StaticTrigger::new()),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use nix::unistd::{Pid, getsid};
+
+    #[test]
+    fn test_separate_pid_files_do_not_interfere() {
+        let dir = std::env::temp_dir().join("wallman_test_pid_files");
+        let _ = fs::remove_dir_all(&dir);
+        let first = DaemonManager::with_pid_file(dir.join("first.pid"));
+        let second = DaemonManager::with_pid_file(dir.join("second.pid"));
+
+        let _lock = first.claim_pid_file(process::id()).unwrap();
+
+        assert_eq!(first.read_pid().unwrap(), Some(process::id()));
+        assert_eq!(second.read_pid().unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restart_if_running_leaves_a_stopped_daemon_alone() {
+        let dir = std::env::temp_dir().join("wallman_test_restart_if_running");
+        let _ = fs::remove_dir_all(&dir);
+        let dm = DaemonManager::with_pid_file(dir.join("daemon.pid"));
+
+        dm.restart(false, true).unwrap();
+        assert_eq!(dm.read_pid().unwrap(), None);
+
+        // A stale PID file does not count as running either.
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("daemon.pid"), i32::MAX.to_string()).unwrap();
+        dm.restart(false, true).unwrap();
+        assert_eq!(dm.read_pid().unwrap(), Some(i32::MAX as u32));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pid_file_claim_has_a_single_winner() {
+        let dir = std::env::temp_dir().join("wallman_test_pid_claim");
+        let _ = fs::remove_dir_all(&dir);
+        let dm = DaemonManager::with_pid_file(dir.join("daemon.pid"));
+
+        // While the lock is held every other claim loses, even one for the
+        // PID already in the file.
+        let lock = dm.claim_pid_file(process::id()).unwrap();
+        for pid in [process::id() + 1, process::id()] {
+            let err = dm.claim_pid_file(pid).unwrap_err();
+            assert!(
+                matches!(err, WallmanError::AlreadyRunning { pid } if pid == process::id()),
+                "{}",
+                err
+            );
+        }
+
+        // Once released the file is taken over, whatever it contains.
+        drop(lock);
+        let lock = dm.claim_pid_file(process::id() + 1).unwrap();
+        assert_eq!(dm.read_pid().unwrap(), Some(process::id() + 1));
+        drop(lock);
+        fs::write(dir.join("daemon.pid"), "garbage").unwrap();
+        let _lock = dm.claim_pid_file(process::id()).unwrap();
+        assert_eq!(dm.read_pid().unwrap(), Some(process::id()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_leaves_a_newer_daemons_files_alone() {
+        let dir = std::env::temp_dir().join("wallman_test_own_files");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let pid_file = dir.join("daemon.pid");
+        let state_file = status::state_file_for(&pid_file);
+
+        fs::write(&pid_file, (process::id() + 1).to_string()).unwrap();
+        fs::write(&state_file, "{}").unwrap();
+        remove_own_files(&pid_file);
+        assert!(pid_file.exists() && state_file.exists());
+
+        fs::write(&pid_file, process::id().to_string()).unwrap();
+        remove_own_files(&pid_file);
+        assert!(!pid_file.exists() && !state_file.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_detached_child_leads_new_session() {
+        let mut command = Command::new("sleep");
+        command.arg("1");
+        detach_session(&mut command);
+        let mut child = command.spawn().unwrap();
+
+        let child_pid = Pid::from_raw(child.id() as i32);
+        let child_sid = getsid(Some(child_pid)).unwrap();
+        let _ = child.kill();
+        let _ = child.wait();
+
+        assert_eq!(child_sid, child_pid);
+        assert_ne!(child_sid, getsid(None).unwrap());
+    }
+
+    #[test]
+    fn test_session_check_requires_a_wayland_variable() {
+        let err = session_error(|_| false).unwrap().to_string();
+        assert!(err.contains("WAYLAND_DISPLAY") && err.contains("SWAYSOCK"));
+        assert!(err.contains("--force"));
+        assert!(session_error(|name| name == "SWAYSOCK").is_none());
+        assert!(session_error(|name| name == "WAYLAND_DISPLAY").is_none());
+    }
+}
@@ -1,3 +1,48 @@
+#[cfg(feature = "dbus")]
+pub mod dbus;
 pub mod manager;
+pub mod service;
+pub mod status;
+#[cfg(feature = "systemd")]
+pub mod systemd;
 
 pub use manager::DaemonManager;
+
+/// Whether `request_set_pool` and `request_set_wallpaper` can reach a daemon
+/// in this build.
+pub const CAN_SET_POOL: bool = cfg!(feature = "dbus");
+
+/// Ask the running daemon to switch to theme `name` without a restart.
+///
+/// Goes over D-Bus, so it needs a build with `--features dbus`
+/// (see `CAN_SET_POOL`).
+pub fn request_set_pool(name: &str) -> crate::Result<()> {
+    #[cfg(feature = "dbus")]
+    return dbus::request_set_pool(name);
+    #[cfg(not(feature = "dbus"))]
+    Err(crate::WallmanError::Daemon(format!(
+        "cannot switch the running daemon to '{}': wallman was built without `--features dbus`",
+        name
+    )))
+}
+
+/// Ask the running daemon to show `image_path` on `output`, like D-Bus
+/// `SetWallpaper`. Needs `--features dbus` (see `CAN_SET_POOL`).
+pub fn request_set_wallpaper(output: &str, image_path: &str) -> crate::Result<()> {
+    #[cfg(feature = "dbus")]
+    return dbus::request_set_wallpaper(output, image_path);
+    #[cfg(not(feature = "dbus"))]
+    Err(crate::WallmanError::Daemon(format!(
+        "cannot ask the running daemon to show '{}' on '{}': wallman was built without \
+         `--features dbus`; stop the daemon to set the wallpaper directly",
+        image_path, output
+    )))
+}
+
+/// Whether a daemon answers on D-Bus. Always false without `--features dbus`.
+pub fn is_on_bus() -> bool {
+    #[cfg(feature = "dbus")]
+    return dbus::is_serving();
+    #[cfg(not(feature = "dbus"))]
+    false
+}
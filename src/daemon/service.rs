@@ -0,0 +1,153 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// File name of the generated systemd user unit.
+pub const UNIT_NAME: &str = "wallman.service";
+
+/// `Type=notify` when built with readiness notifications, plain `simple` otherwise.
+const SERVICE_TYPE: &str = if cfg!(feature = "systemd") {
+    "notify"
+} else {
+    "simple"
+};
+
+/// Directory systemd reads user units from (`~/.config/systemd/user`).
+fn unit_dir() -> io::Result<PathBuf> {
+    dirs::config_dir()
+        .map(|d| d.join("systemd/user"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "cannot locate config directory"))
+}
+
+/// Quote `exe` as a single `ExecStart=` word.
+///
+/// systemd splits the command line on whitespace and expands `%` specifiers
+/// and `$` variables, so the path goes in double quotes with those escaped.
+fn exec_quote(exe: &Path) -> String {
+    let mut quoted = String::from("\"");
+    for c in exe.display().to_string().chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            '$' => quoted.push_str("$$"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Render the unit file that runs `exe daemon start --foreground`.
+fn unit_contents(exe: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Wallman dynamic wallpaper daemon\n\
+         PartOf=graphical-session.target\n\
+         After=graphical-session.target\n\
+         \n\
+         [Service]\n\
+         Type={}\n\
+         ExecStart={} daemon start --foreground\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=graphical-session.target\n",
+        SERVICE_TYPE,
+        exec_quote(exe)
+    )
+}
+
+/// Run `systemctl --user <args>` and fail if it exits non-zero.
+fn systemctl(args: &[&str]) -> io::Result<()> {
+    let status = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "`systemctl --user {}` exited with {}",
+            args.join(" "),
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Write the user unit, optionally enabling and starting it.
+///
+/// An existing unit is kept unless `force` is set; `enable` still applies to it.
+pub fn install_service(force: bool, enable: bool) -> io::Result<()> {
+    let dir = unit_dir()?;
+    fs::create_dir_all(&dir)?;
+
+    let unit_file = dir.join(UNIT_NAME);
+    if unit_file.exists() && !force {
+        crate::cli::say_err!(
+            "Service file already exists: {} (use --force to overwrite)",
+            unit_file.display()
+        );
+    } else {
+        let exe = std::env::current_exe()?;
+        fs::write(&unit_file, unit_contents(&exe))?;
+        crate::cli::say!("Service installed to: {}", unit_file.display());
+    }
+
+    systemctl(&["daemon-reload"])?;
+    if enable {
+        systemctl(&["enable", "--now", UNIT_NAME])?;
+        crate::cli::say!("Service enabled and started.");
+    } else {
+        crate::cli::say!(
+            "Enable it with: systemctl --user enable --now {}",
+            UNIT_NAME
+        );
+    }
+
+    Ok(())
+}
+
+/// Disable and remove the user unit if present.
+pub fn uninstall_service() -> io::Result<()> {
+    let unit_file = unit_dir()?.join(UNIT_NAME);
+
+    if !unit_file.exists() {
+        crate::cli::say!("No service file found at: {}", unit_file.display());
+        return Ok(());
+    }
+
+    // Best effort: the unit may never have been enabled.
+    if let Err(e) = systemctl(&["disable", "--now", UNIT_NAME]) {
+        tracing::debug!("Could not disable {}: {}", UNIT_NAME, e);
+    }
+
+    fs::remove_file(&unit_file)?;
+    systemctl(&["daemon-reload"])?;
+    crate::cli::say!("Service uninstalled from: {}", unit_file.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_runs_foreground_daemon() {
+        let unit = unit_contents(Path::new("/usr/bin/wallman"));
+        assert!(unit.contains("ExecStart=\"/usr/bin/wallman\" daemon start --foreground\n"));
+        assert!(unit.contains("WantedBy=graphical-session.target"));
+    }
+
+    #[test]
+    fn test_exec_path_with_space_is_quoted() {
+        let unit = unit_contents(Path::new("/home/me/My Apps/wall%man"));
+        assert!(
+            unit.contains("ExecStart=\"/home/me/My Apps/wall%%man\" daemon start --foreground\n")
+        );
+        assert_eq!(exec_quote(Path::new("/opt/a\"b")), "\"/opt/a\\\"b\"");
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FillMode, trigger::OutputChange};
+
+/// Runtime info the daemon persists next to its PID file for `daemon status`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeState {
+    /// Unix timestamp (seconds) the daemon started at.
+    pub started_at: u64,
+    /// Trigger type driving the wallpaper (`weather`, `daytime`, `static`).
+    pub trigger: String,
+    pub config_path: String,
+    /// Last wallpaper applied to each output.
+    pub wallpapers: BTreeMap<String, String>,
+    /// Backend in use after the configured one kept failing (see `backendFallbacks`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    /// Backend processes drawing the wallpapers, so the next start can take
+    /// them over after a restart or if this daemon dies without cleaning up.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backend_processes: Vec<BackendProcess>,
+}
+
+/// A backend process the daemon started, with the name `/proc/<pid>/comm`
+/// gave it (to tell it apart from an unrelated process reusing the PID).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendProcess {
+    pub pid: u32,
+    pub comm: String,
+    /// Outputs it draws; empty in files written before this was recorded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+    /// What it draws there, so the next daemon can take it over.
+    #[serde(default)]
+    pub image_path: String,
+    #[serde(default)]
+    pub fill_mode: FillMode,
+}
+
+/// State file of the running daemon plus its in-memory copy.
+static RUNTIME: OnceLock<(PathBuf, Mutex<RuntimeState>)> = OnceLock::new();
+
+/// The state file that belongs to `pid_file` (`daemon.pid` → `daemon.state.json`).
+pub fn state_file_for(pid_file: &Path) -> PathBuf {
+    pid_file.with_extension("state.json")
+}
+
+/// The heartbeat file that belongs to `pid_file`: `daemon.pid` → `heartbeat`,
+/// any other name `<name>.pid` → `<name>.heartbeat`.
+pub fn heartbeat_file_for(pid_file: &Path) -> PathBuf {
+    if pid_file.file_stem().is_some_and(|stem| stem == "daemon") {
+        pid_file.with_file_name("heartbeat")
+    } else {
+        pid_file.with_extension("heartbeat")
+    }
+}
+
+/// Rewrite the heartbeat file with the current Unix time, refreshing its mtime.
+pub fn touch_heartbeat(path: &Path) {
+    if let Err(e) = fs::write(path, format!("{}\n", now_unix())) {
+        tracing::warn!("Failed to write heartbeat {}: {}", path.display(), e);
+    }
+}
+
+pub fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Start recording runtime state to `path`. Called once by the foreground
+/// daemon, after it took over the previous daemon's backend processes, which
+/// are recorded right away.
+pub fn begin(path: PathBuf, mut state: RuntimeState) {
+    state.backend_processes = backend_processes();
+    if let Err(e) = write(&path, &state) {
+        tracing::warn!("Failed to write daemon state file: {}", e);
+    }
+    let _ = RUNTIME.set((path, Mutex::new(state)));
+}
+
+/// Remember the wallpapers that were just applied and the processes drawing
+/// them. No-op outside the daemon.
+pub fn record_applied(changes: &[&OutputChange]) {
+    let Some((path, state)) = RUNTIME.get() else {
+        return;
+    };
+    let mut state = state.lock().unwrap();
+    for change in changes {
+        state
+            .wallpapers
+            .insert(change.output.clone(), change.image_path.clone());
+    }
+    state.backend_processes = backend_processes();
+    if let Err(e) = write(path, &state) {
+        tracing::warn!("Failed to update daemon state file: {}", e);
+    }
+}
+
+/// Record the backend processes this process started without a daemon
+/// (`wallman set`) in the state file at `path`, after the `kept` ones from
+/// earlier runs, for the next `daemon start` to take over or `daemon stop`
+/// to end.
+pub fn record_detached(path: &Path, kept: Vec<BackendProcess>) {
+    let mut state = read(path).unwrap_or_default();
+    let started = backend_processes();
+    for backend in &started {
+        for output in &backend.outputs {
+            state
+                .wallpapers
+                .insert(output.clone(), backend.image_path.clone());
+        }
+    }
+    state.backend_processes = kept;
+    state.backend_processes.extend(started);
+    if let Err(e) = write(path, &state) {
+        tracing::warn!("Failed to update daemon state file: {}", e);
+    }
+}
+
+/// The tracked backend processes that are still running.
+fn backend_processes() -> Vec<BackendProcess> {
+    crate::wallpaper::tracked_processes()
+        .into_iter()
+        .filter_map(|(pid, changes)| {
+            let first = changes.first()?;
+            Some(BackendProcess {
+                pid,
+                comm: process_comm(pid)?,
+                image_path: first.image_path.clone(),
+                fill_mode: first.fill_mode.clone(),
+                outputs: changes.into_iter().map(|change| change.output).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Note that the daemon fell back to `backend`. No-op outside the daemon.
+pub fn record_backend(backend: &str) {
+    let Some((path, state)) = RUNTIME.get() else {
+        return;
+    };
+    let mut state = state.lock().unwrap();
+    state.backend = Some(backend.to_string());
+    if let Err(e) = write(path, &state) {
+        tracing::warn!("Failed to update daemon state file: {}", e);
+    }
+}
+
+/// Whether process `pid` exists and has not exited (a zombie has).
+pub fn is_running(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| Some(!stat.rsplit_once(") ")?.1.starts_with('Z')))
+        .unwrap_or(false)
+}
+
+/// The kernel's name for process `pid`, if it is running.
+pub fn process_comm(pid: u32) -> Option<String> {
+    let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(comm.trim_end().to_string())
+}
+
+/// Read a state file written by `begin`/`record_applied`.
+pub fn read(path: &Path) -> Option<RuntimeState> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write(path: &Path, state: &RuntimeState) -> crate::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(state)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_file_round_trip() {
+        let dir = std::env::temp_dir().join("wallman_test_runtime_state");
+        let _ = fs::remove_dir_all(&dir);
+        let path = state_file_for(&dir.join("daemon.pid"));
+        assert_eq!(path.file_name().unwrap(), "daemon.state.json");
+
+        let mut state = RuntimeState {
+            started_at: 1_700_000_000,
+            trigger: "static".into(),
+            config_path: "/tmp/config.toml".into(),
+            ..Default::default()
+        };
+        state.wallpapers.insert("DP-1".into(), "/tmp/a.jpg".into());
+        state.backend_processes.push(BackendProcess {
+            pid: std::process::id(),
+            comm: process_comm(std::process::id()).unwrap(),
+            outputs: vec!["DP-1".into()],
+            image_path: "/tmp/a.jpg".into(),
+            fill_mode: FillMode::Crop,
+        });
+        write(&path, &state).unwrap();
+
+        assert_eq!(read(&path), Some(state));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_heartbeat_file_for_pid_file() {
+        let dir = Path::new("/run/wallman");
+        assert_eq!(
+            heartbeat_file_for(&dir.join("daemon.pid")),
+            dir.join("heartbeat")
+        );
+        assert_eq!(
+            heartbeat_file_for(&dir.join("laptop.pid")),
+            dir.join("laptop.heartbeat")
+        );
+    }
+}
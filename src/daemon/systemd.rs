@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use sd_notify::NotifyState;
+
+/// Tell systemd the daemon finished starting up (`READY=1`).
+///
+/// Does nothing when not running under a `Type=notify` unit.
+pub fn notify_ready() {
+    if let Err(e) = sd_notify::notify(false, &[NotifyState::Ready]) {
+        tracing::warn!("sd_notify READY failed: {}", e);
+    }
+}
+
+/// Tell systemd the daemon is shutting down (`STOPPING=1`).
+pub fn notify_stopping() {
+    if let Err(e) = sd_notify::notify(false, &[NotifyState::Stopping]) {
+        tracing::warn!("sd_notify STOPPING failed: {}", e);
+    }
+}
+
+/// How often to ping the watchdog: half of `WatchdogSec`, or `None` if disabled.
+pub fn watchdog_interval() -> Option<Duration> {
+    let mut usec = 0;
+    if sd_notify::watchdog_enabled(false, &mut usec) {
+        Some(Duration::from_micros(usec) / 2)
+    } else {
+        None
+    }
+}
+
+/// Send a `WATCHDOG=1` keep-alive.
+pub fn ping_watchdog() {
+    if let Err(e) = sd_notify::notify(false, &[NotifyState::Watchdog]) {
+        tracing::warn!("sd_notify WATCHDOG failed: {}", e);
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    AppState, Config, OutputResolver,
+    constants::{config_folder, data_folder},
+    wallpaper::{apply::BACKEND_BINARY, find_in_path, missing_binaries},
+};
+
+/// Outcome of a single `wallman doctor` check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Run every first-run diagnostic against the config at `config_path`.
+///
+/// Checks never stop at the first failure so the whole checklist is shown.
+pub fn run(config_path: &Path) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let config = check_config(config_path, &mut checks);
+
+    checks.push(match find_in_path(BACKEND_BINARY) {
+        Some(path) => Check::new("backend", CheckStatus::Pass, path.display().to_string()),
+        None => Check::new(
+            "backend",
+            CheckStatus::Fail,
+            format!("{} not found in PATH", BACKEND_BINARY),
+        ),
+    });
+
+    checks.push(match OutputResolver::detect() {
+        Ok(resolver) if resolver.outputs().is_empty() => {
+            Check::new("outputs", CheckStatus::Fail, "no active outputs detected")
+        }
+        Ok(resolver) => Check::new("outputs", CheckStatus::Pass, resolver.outputs().join(", ")),
+        Err(e) => Check::new("outputs", CheckStatus::Fail, e.to_string()),
+    });
+
+    if let Some(config) = config {
+        check_images(config, config_path, &mut checks);
+    }
+
+    let config_dir = config_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(config_folder);
+    for (name, dir) in [("config dir", config_dir), ("data dir", data_folder())] {
+        checks.push(match writable(&dir) {
+            Ok(()) => Check::new(name, CheckStatus::Pass, dir.display().to_string()),
+            Err(e) => Check::new(
+                name,
+                CheckStatus::Fail,
+                format!("{} is not writable: {}", dir.display(), e),
+            ),
+        });
+    }
+
+    checks
+}
+
+/// Load the config (merging its theme) and record whether that worked.
+fn check_config(path: &Path, checks: &mut Vec<Check>) -> Option<Config> {
+    if !path.exists() {
+        checks.push(Check::new(
+            "config",
+            CheckStatus::Fail,
+            format!(
+                "not found at {} — run `wallman config init`",
+                path.display()
+            ),
+        ));
+        return None;
+    }
+
+    let mut config = match Config::load(path.to_path_buf()) {
+        Ok(config) => config,
+        Err(e) => {
+            checks.push(Check::new("config", CheckStatus::Fail, e.to_string()));
+            return None;
+        }
+    };
+    checks.push(Check::new(
+        "config",
+        CheckStatus::Pass,
+        path.display().to_string(),
+    ));
+
+    if let Some(pool) = config.primary_pool() {
+        checks.push(match config.merge_theme(PathBuf::from(&pool)) {
+            Ok(()) => Check::new("theme", CheckStatus::Pass, pool),
+            Err(e) => Check::new("theme", CheckStatus::Warn, format!("{}: {}", pool, e)),
+        });
+    }
+    if let Some(requires) = config.requires.as_ref().filter(|r| !r.is_empty()) {
+        let missing = missing_binaries(requires);
+        checks.push(if missing.is_empty() {
+            Check::new("theme deps", CheckStatus::Pass, requires.join(", "))
+        } else {
+            Check::new(
+                "theme deps",
+                CheckStatus::Warn,
+                format!("not found in PATH: {}", missing.join(", ")),
+            )
+        });
+    }
+    if let Some(conflict) = config.trigger_conflict() {
+        checks.push(Check::new("triggers", CheckStatus::Warn, conflict));
+    }
+    Some(config)
+}
+
+/// Same image checks as `config validate`: referenced files must exist and decode.
+fn check_images(config: Config, config_path: &Path, checks: &mut Vec<Check>) {
+    let count = config.referenced_images().len();
+    let pool = config.primary_pool();
+    let is_pool = pool.is_some();
+    let state = match AppState::new(
+        config,
+        config_path.to_string_lossy().to_string(),
+        pool,
+        is_pool,
+    ) {
+        Ok(state) => state,
+        Err(e) => {
+            checks.push(Check::new("images", CheckStatus::Fail, e.to_string()));
+            return;
+        }
+    };
+
+    let problems = state.image_problems();
+    checks.push(if count == 0 {
+        Check::new("images", CheckStatus::Warn, "no images configured")
+    } else if problems.is_empty() {
+        Check::new(
+            "images",
+            CheckStatus::Pass,
+            format!("{} referenced, all readable", count),
+        )
+    } else {
+        Check::new("images", CheckStatus::Fail, problems.join("; "))
+    });
+}
+
+/// Create `dir` if needed and prove a file can be written inside it.
+fn writable(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(".wallman-doctor");
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_config_fails_but_other_checks_still_run() {
+        let dir = std::env::temp_dir().join("wallman_test_doctor");
+        let _ = fs::remove_dir_all(&dir);
+
+        let checks = run(&dir.join("config.toml"));
+
+        let config = checks.iter().find(|c| c.name == "config").unwrap();
+        assert_eq!(config.status, CheckStatus::Fail);
+        assert!(checks.iter().any(|c| c.name == "backend"));
+        assert!(checks.iter().any(|c| c.name == "outputs"));
+        // The config directory is created and writable.
+        let config_dir = checks.iter().find(|c| c.name == "config dir").unwrap();
+        assert_eq!(config_dir.status, CheckStatus::Pass);
+        assert!(dir.is_dir());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+//! Crate-wide error type.
+
+use std::io;
+
+/// Errors returned by wallman, grouped by the area that failed so callers
+/// (and the CLI's exit codes) can tell them apart.
+#[derive(Debug, thiserror::Error)]
+pub enum WallmanError {
+    /// The config file is missing, malformed or refers to unknown settings.
+    #[error("{0}")]
+    Config(String),
+    /// Filesystem access outside the more specific areas below.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A `.wallman` pack could not be built, read, verified or installed.
+    #[error(transparent)]
+    Pack(io::Error),
+    /// The daemon could not be started, stopped or signalled.
+    #[error("{0}")]
+    Daemon(String),
+    /// Another daemon already owns the PID file.
+    #[error("Daemon is already running (PID {pid}). Use `wallman daemon restart` to restart it.")]
+    AlreadyRunning { pid: u32 },
+    /// A download or API request failed.
+    #[error("{0}")]
+    Network(String),
+    /// Outputs could not be detected or the wallpaper backend failed.
+    #[error("{0}")]
+    Output(String),
+}
+
+/// `Result` with [`WallmanError`].
+pub type Result<T> = std::result::Result<T, WallmanError>;
+
+impl From<toml::de::Error> for WallmanError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Config(e.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for WallmanError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::Config(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for WallmanError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Config(e.to_string())
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl From<serde_yaml::Error> for WallmanError {
+    fn from(e: serde_yaml::Error) -> Self {
+        Self::Config(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for WallmanError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Network(e.to_string())
+    }
+}
+
+impl From<nix::errno::Errno> for WallmanError {
+    fn from(e: nix::errno::Errno) -> Self {
+        Self::Daemon(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors_are_config_errors() {
+        let err: WallmanError = toml::from_str::<toml::Table>("not = [valid")
+            .unwrap_err()
+            .into();
+        assert!(matches!(err, WallmanError::Config(_)));
+
+        let err: WallmanError = io::Error::new(io::ErrorKind::NotFound, "gone").into();
+        assert!(matches!(err, WallmanError::Io(ref e) if e.kind() == io::ErrorKind::NotFound));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use crate::{Config, OutputResolver, TriggerKind};
+
+/// One trigger section as the daemon's trigger selection sees it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionInfo {
+    pub kind: TriggerKind,
+    /// Output keys of the section, or `None` when it is not configured.
+    pub outputs: Option<Vec<String>>,
+}
+
+/// Where a detected output gets its settings from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputPlan {
+    pub output: String,
+    /// Key of the selected section (`"HDMI-1"` or `"*"`); `None` means skipped.
+    pub key: Option<String>,
+}
+
+/// What `wallman daemon start` would run for a config, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// Every trigger section in priority order.
+    pub sections: Vec<SectionInfo>,
+    pub selected: TriggerKind,
+    pub reason: String,
+    pub outputs: Vec<OutputPlan>,
+}
+
+/// Explain which trigger the daemon would select for `config` (with its theme
+/// already merged) and which of `outputs` it would act on.
+///
+/// Uses the same selection as the daemon, without building any trigger.
+pub fn explain(config: &Config, outputs: &[String]) -> Explanation {
+    let sections: Vec<SectionInfo> = TriggerKind::PRIORITY
+        .into_iter()
+        .map(|kind| SectionInfo {
+            kind,
+            outputs: config.section_outputs(kind),
+        })
+        .collect();
+    let selected = config.trigger_kind();
+
+    let configured = config.configured_triggers();
+    let reason = match configured.as_slice() {
+        [] => "no trigger sections configured; the static trigger has nothing to show".to_string(),
+        [only] => format!(
+            "[{}] is the only trigger section configured",
+            only.section()
+        ),
+        [first, ..] => format!(
+            "[{}] has the highest priority of the configured sections",
+            first.section()
+        ),
+    };
+
+    let keys: HashMap<String, ()> = config
+        .section_outputs(selected)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|key| (key, ()))
+        .collect();
+    let outputs = outputs
+        .iter()
+        .map(|output| OutputPlan {
+            output: output.clone(),
+            key: OutputResolver::matching_key(&keys, output).map(str::to_string),
+        })
+        .collect();
+
+    Explanation {
+        sections,
+        selected,
+        reason,
+        outputs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_picks_highest_priority_and_maps_outputs() {
+        let config: Config = toml::from_str(
+            r#"
+                [background.DP-1]
+                image = "a.png"
+
+                [timeConfig."*"]
+                day = "day.png"
+                night = "night.png"
+
+                [timeConfig.HDMI-1]
+                day = "day.png"
+                night = "night.png"
+            "#,
+        )
+        .unwrap();
+
+        let explanation = explain(&config, &["HDMI-1".to_string(), "DP-1".to_string()]);
+
+        assert_eq!(explanation.selected, TriggerKind::DayTime);
+        assert!(explanation.reason.contains("[timeConfig]"));
+        assert_eq!(explanation.sections.len(), 5);
+        assert_eq!(explanation.sections[0].outputs, None);
+        assert_eq!(
+            explanation.sections[4].outputs,
+            Some(vec!["DP-1".to_string()])
+        );
+        assert_eq!(explanation.outputs[0].key.as_deref(), Some("HDMI-1"));
+        assert_eq!(explanation.outputs[1].key.as_deref(), Some("*"));
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, WallmanError};
+
+/// Name used when the URL has no usable file name.
+const FALLBACK_FILE_NAME: &str = "theme.wallman";
+
+/// Returns true if `source` should be downloaded rather than opened locally.
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/// A pack downloaded into its own temp directory (mode 0700), removed on drop.
+pub struct DownloadedPack {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl DownloadedPack {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DownloadedPack {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Download a `.wallman` pack, following redirects and printing progress.
+///
+/// If a `<url>.sha256` sidecar exists the download is verified against it.
+/// The file keeps its name from the URL so the installer's default theme name
+/// matches a local install of the same file.
+pub fn download_pack(url: &str) -> Result<DownloadedPack> {
+    let client = crate::http::configured_client()?;
+    let mut response = client.get(url).send()?.error_for_status()?;
+
+    let dir = create_download_dir()?;
+    let pack = DownloadedPack {
+        path: dir.join(file_name_from_url(response.url().path())),
+        dir,
+    };
+
+    let total = response.content_length();
+    let mut file = File::create_new(&pack.path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut received: u64 = 0;
+    loop {
+        let n = response
+            .read(&mut buf)
+            .map_err(|e| WallmanError::Network(e.to_string()))?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n])?;
+        hasher.update(&buf[..n]);
+        received += n as u64;
+        if !crate::cli::is_quiet() {
+            print_progress(received, total);
+        }
+    }
+    crate::cli::say_err!();
+    file.flush()?;
+
+    let digest = hex(&hasher.finalize());
+    match client.get(format!("{}.sha256", url)).send() {
+        Ok(sidecar) if sidecar.status() == StatusCode::NOT_FOUND => {
+            tracing::info!("No checksum published for {}", url);
+        }
+        Ok(sidecar) => {
+            let text = sidecar.error_for_status()?.text()?;
+            verify_checksum(&text, &digest).map_err(WallmanError::Pack)?;
+            crate::cli::say!("Checksum verified (sha256 {})", digest);
+        }
+        Err(e) => tracing::warn!("Could not fetch checksum for {}: {}", url, e),
+    }
+
+    Ok(pack)
+}
+
+/// Create a fresh `wallman-download-<pid>-<random>` directory only we can open.
+///
+/// The directory must not exist yet: an existing path (possibly planted by
+/// another user in the shared temp dir) is refused rather than reused.
+fn create_download_dir() -> io::Result<PathBuf> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    let dir = std::env::temp_dir().join(format!(
+        "wallman-download-{}-{:016x}",
+        std::process::id(),
+        rand::random::<u64>()
+    ));
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    Ok(dir)
+}
+
+/// Last path segment of a URL path, or a generic name.
+fn file_name_from_url(path: &str) -> String {
+    path.rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(FALLBACK_FILE_NAME)
+        .to_string()
+}
+
+/// Compare against a `sha256sum`-style sidecar (`<hex>  <file>` or just `<hex>`).
+fn verify_checksum(sidecar: &str, digest: &str) -> io::Result<()> {
+    let expected = sidecar.split_whitespace().next().unwrap_or_default();
+    if expected.eq_ignore_ascii_case(digest) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "checksum mismatch: expected {}, downloaded {}",
+                expected, digest
+            ),
+        ))
+    }
+}
+
+fn print_progress(received: u64, total: Option<u64>) {
+    const MIB: f64 = 1024.0 * 1024.0;
+    match total {
+        Some(total) if total > 0 => eprint!(
+            "\rDownloading… {:>3}% ({:.1}/{:.1} MiB)",
+            received * 100 / total,
+            received as f64 / MIB,
+            total as f64 / MIB
+        ),
+        _ => eprint!("\rDownloading… {:.1} MiB", received as f64 / MIB),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name_from_url() {
+        assert_eq!(
+            file_name_from_url("/packs/forest.wallman"),
+            "forest.wallman"
+        );
+        assert_eq!(file_name_from_url("/"), FALLBACK_FILE_NAME);
+    }
+
+    #[test]
+    fn test_download_dir_is_new_and_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let first = create_download_dir().unwrap();
+        let second = create_download_dir().unwrap();
+        assert_ne!(first, second);
+        let mode = fs::metadata(&first).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        fs::remove_dir(first).unwrap();
+        fs::remove_dir(second).unwrap();
+    }
+
+    #[test]
+    fn test_verify_checksum_accepts_sha256sum_format() {
+        let digest = hex(&Sha256::digest(b"wallman"));
+        assert!(verify_checksum(&format!("{}  forest.wallman\n", digest), &digest).is_ok());
+        assert!(verify_checksum(&digest.to_uppercase(), &digest).is_ok());
+        assert!(verify_checksum("deadbeef", &digest).is_err());
+    }
+}
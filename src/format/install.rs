@@ -1,13 +1,16 @@
 use std::{
     fs::{self, File},
     io::{self, Read},
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 use tar::Archive;
 use zstd::Decoder;
 
-use crate::{Config, decompresion_folder};
+use crate::{
+    Config, decompresion_folder,
+    error::{Result, WallmanError},
+};
 
 pub struct PackInstaller {
     file_path: PathBuf,
@@ -24,18 +27,22 @@ impl PackInstaller {
         }
     }
 
-    pub fn install(&mut self) -> io::Result<()> {
-        self.read_manifest()?;
-        self.create_dest_dir()?;
-        self.unpack_archive()?;
-        Ok(())
+    /// Install into `dir` instead of the default themes folder.
+    pub fn with_dest_dir<T: AsRef<Path>>(mut self, dir: T) -> Self {
+        self.dest_dir = dir.as_ref().to_path_buf();
+        self
     }
 
-    fn read_manifest(&mut self) -> io::Result<()> {
-        let bin_file = File::open(&self.file_path)?;
-        let decoder = Decoder::new(bin_file)?;
-        let mut archive = Archive::new(decoder);
+    /// Extract the pack in a single streaming pass.
+    ///
+    /// Entries are validated as they are unpacked into a staging directory,
+    /// which is renamed to the theme name from `manifest.toml` once the whole
+    /// archive was read. A failed install leaves nothing behind.
+    pub fn install(&mut self) -> Result<()> {
+        self.install_staged().map_err(WallmanError::Pack)
+    }
 
+    fn install_staged(&mut self) -> io::Result<()> {
         // Default name from filename
         self.pack_name = self
             .file_path
@@ -44,71 +51,99 @@ impl PackInstaller {
             .unwrap_or("unknown")
             .to_string();
 
-        for entry in archive.entries()? {
-            let mut entry = entry?;
-            let path = entry.path()?;
-
-            if path
-                .file_name()
-                .map(|n| n == "manifest.toml")
-                .unwrap_or(false)
-            {
-                let mut contents = String::new();
-                entry.read_to_string(&mut contents)?;
-
-                match toml::from_str::<Config>(&contents) {
-                    Ok(config) => {
-                        if let Some(name) = config.name {
-                            self.pack_name = sanitize_name(&name);
-                        }
-                    }
-                    Err(e) => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            format!("Failed to parse manifest.toml: {}", e),
-                        ));
-                    }
-                }
-                break;
-            }
+        fs::create_dir_all(&self.dest_dir)?;
+        let staging = self
+            .dest_dir
+            .join(format!(".installing-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&staging);
+        fs::create_dir_all(&staging)?;
+
+        let result = self
+            .unpack_archive(&staging)
+            .and_then(|()| self.move_into_place(&staging));
+        if result.is_err() {
+            let _ = fs::remove_dir_all(&staging);
         }
-
-        Ok(())
+        result
     }
 
-    fn create_dest_dir(&mut self) -> io::Result<()> {
-        self.dest_dir = self.dest_dir.join(&self.pack_name);
-        fs::create_dir_all(&self.dest_dir)?;
-        Ok(())
+    /// Directory the theme ends up in (valid after `install`).
+    pub fn installed_dir(&self) -> PathBuf {
+        self.dest_dir.join(&self.pack_name)
     }
 
-    fn unpack_archive(&self) -> io::Result<()> {
+    fn unpack_archive(&mut self, staging: &Path) -> io::Result<()> {
         let bin_file = File::open(&self.file_path)?;
         let decoder = Decoder::new(bin_file)?;
         let mut archive = Archive::new(decoder);
 
-        // Validate paths to prevent directory traversal
         for entry in archive.entries()? {
-            let entry = entry?;
-            let path = entry.path()?;
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
 
-            // Check for unsafe paths
-            if path.components().any(|c| c.as_os_str() == "..") {
+            // Check for unsafe paths before anything is written.
+            if !is_safe_entry_path(&path) {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
                     format!("Unsafe path detected: {}", path.display()),
                 ));
             }
-        }
 
-        // Reset archive and unpack
-        let bin_file = File::open(&self.file_path)?;
-        let decoder = Decoder::new(bin_file)?;
-        let mut archive = Archive::new(decoder);
-        archive.unpack(&self.dest_dir)?;
+            if path.file_name().is_some_and(|n| n == "manifest.toml") {
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents)?;
+                self.read_manifest(&contents)?;
+                let target = staging.join(&path);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(target, contents)?;
+                continue;
+            }
+
+            entry.unpack_in(staging)?;
+        }
 
         Ok(())
     }
+
+    fn read_manifest(&mut self, contents: &str) -> io::Result<()> {
+        match toml::from_str::<Config>(contents) {
+            Ok(config) => {
+                if let Some(name) = config.name {
+                    self.pack_name = sanitize_name(&name);
+                }
+                Ok(())
+            }
+            Err(e) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to parse manifest.toml: {}", e),
+            )),
+        }
+    }
+
+    /// Replace any previous install of this theme with the staged files.
+    fn move_into_place(&self, staging: &Path) -> io::Result<()> {
+        // An empty name would make the target the themes folder itself.
+        if self.pack_name.is_empty() || self.pack_name.starts_with('.') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid theme name '{}'", self.pack_name),
+            ));
+        }
+        let target = self.installed_dir();
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(staging, &target)
+    }
+}
+
+/// True if an archive entry path stays inside the extraction directory
+/// (no `..`, root or drive-prefix components).
+pub(crate) fn is_safe_entry_path(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
 }
 
 // Helper function to sanitize pack names
@@ -119,7 +154,80 @@ fn sanitize_name(name: &str) -> String {
 }
 
 // Legacy function for backward compatibility
-pub fn install_pack<T: AsRef<Path>>(file: T) -> io::Result<()> {
+pub fn install_pack<T: AsRef<Path>>(file: T) -> Result<()> {
     let mut installer = PackInstaller::new(file);
     installer.install()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tar::{Builder, Header};
+
+    /// Write a zstd tarball containing `entries` (raw names are not validated).
+    fn write_pack(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut tar_data = Vec::new();
+        {
+            let mut builder = Builder::new(&mut tar_data);
+            for (name, data) in entries {
+                let mut header = Header::new_gnu();
+                header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append(&header, *data).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+        fs::write(path, zstd::encode_all(&tar_data[..], 3).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_install_uses_manifest_name() {
+        let dir = std::env::temp_dir().join("wallman_test_install_name");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let pack = dir.join("file-name.wallman");
+        write_pack(
+            &pack,
+            &[
+                ("manifest.toml", b"name = \"Deep Forest\"\n"),
+                ("images/a.jpg", b"jpg"),
+            ],
+        );
+
+        let mut installer = PackInstaller::new(&pack).with_dest_dir(dir.join("themes"));
+        installer.install().unwrap();
+
+        let installed = dir.join("themes/deep-forest");
+        assert_eq!(installer.installed_dir(), installed);
+        assert!(installed.join("manifest.toml").exists());
+        assert_eq!(fs::read(installed.join("images/a.jpg")).unwrap(), b"jpg");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_rejects_traversal_and_cleans_up() {
+        let dir = std::env::temp_dir().join("wallman_test_install_traversal");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let pack = dir.join("evil.wallman");
+        write_pack(
+            &pack,
+            &[("images/a.jpg", b"jpg"), ("../escape.txt", b"nope")],
+        );
+
+        let themes = dir.join("themes");
+        let err = PackInstaller::new(&pack)
+            .with_dest_dir(&themes)
+            .install()
+            .unwrap_err();
+
+        assert!(
+            matches!(err, WallmanError::Pack(ref e) if e.kind() == io::ErrorKind::InvalidInput)
+        );
+        assert!(!dir.join("escape.txt").exists());
+        assert_eq!(fs::read_dir(&themes).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,2 +1,4 @@
+pub mod download;
 pub mod install;
 pub mod pack;
+pub mod verify;
@@ -1,12 +1,18 @@
-use image::ImageReader;
+use image::{ImageFormat, ImageReader};
 use std::fs;
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, BufRead, Seek, Write};
 use std::path::{Path, PathBuf};
-use tar::Builder;
-use zstd::stream::write::Encoder;
+use tar::{Archive, Builder};
+use zstd::{Decoder, stream::write::Encoder};
 
-use crate::Config;
+use crate::{
+    Config, ImageMeta,
+    error::{Result, WallmanError},
+};
+
+/// zstd level `wallman pack build` compresses at.
+pub const DEFAULT_LEVEL: i32 = 3;
 
 pub struct Packager {
     config: Config,
@@ -21,9 +27,18 @@ impl Packager {
         }
     }
 
-    pub fn pack<T: AsRef<Path>>(&self, out: T) -> io::Result<()> {
-        let out_path = out.as_ref();
+    /// Write the theme directory and manifest as a `.wallman` pack to `out`.
+    pub fn pack<T: AsRef<Path>>(&self, out: T) -> Result<()> {
+        let file = File::create(out.as_ref()).map_err(WallmanError::Pack)?;
+        self.pack_to(file)
+    }
+
+    /// Like `pack`, but stream the pack into any writer (stdout, a buffer, …).
+    pub fn pack_to<W: Write>(&self, sink: W) -> Result<()> {
+        self.write_pack(sink).map_err(WallmanError::Pack)
+    }
 
+    fn write_pack<W: Write>(&self, sink: W) -> io::Result<()> {
         // Paso 1: Validar que self.path existe y es un directorio
         if !self.path.exists() {
             return Err(io::Error::new(
@@ -38,8 +53,8 @@ impl Packager {
             ));
         }
 
-        // Validar que self.path/images existe
-        let images_dir = self.path.join("images");
+        // Validar que el directorio de imágenes existe
+        let images_dir = self.path.join(self.config.images_dir());
         if !images_dir.exists() {
             return Err(io::Error::new(
                 io::ErrorKind::NotFound,
@@ -79,19 +94,30 @@ impl Packager {
             for entry in fs::read_dir(&images_dir)? {
                 let entry = entry?;
                 let path = entry.path();
-                if path.is_file() {
-                    if is_image(&path)? {
-                        let file_name = path.file_name().unwrap().to_string_lossy();
-                        let entry_path = format!("images/{}", file_name);
-                        tar_builder.append_path_with_name(&path, entry_path)?;
+                if !path.is_file() {
+                    continue;
+                }
+                if ImageMeta::is_sidecar(&path) {
+                    let parsed = fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|c| ImageMeta::parse(&c).map_err(|e| e.to_string()));
+                    if let Err(reason) = parsed {
+                        tracing::warn!("Skipping {}: {}", path.display(), reason);
+                        continue;
                     }
+                // Mislabeled or truncated files would only fail later in swaybg.
+                } else if let Err(reason) = check_image_file(&path) {
+                    tracing::warn!("Skipping {}: {}", path.display(), reason);
+                    continue;
                 }
+                let file_name = path.file_name().unwrap().to_string_lossy();
+                let entry_path = format!("{}/{}", self.config.images_dir(), file_name);
+                tar_builder.append_path_with_name(&path, entry_path)?;
             }
         }
 
         // Paso 5: Comprimir tar con zstd
-        let out_file = File::create(out_path)?;
-        let mut encoder = Encoder::new(out_file, 3)?; // nivel de compresión 3
+        let mut encoder = encoder(sink, DEFAULT_LEVEL)?;
         encoder.write_all(&tar_data)?;
         encoder.finish()?;
 
@@ -99,10 +125,277 @@ impl Packager {
     }
 }
 
-// Paso 5: Función auxiliar para validar imágenes
-fn is_image(path: &Path) -> io::Result<bool> {
-    match ImageReader::open(path) {
-        Ok(_) => Ok(true),
-        Err(_) => Ok(false),
+/// The zstd writer every pack is written through, at `level`.
+fn encoder<W: Write>(sink: W, level: i32) -> io::Result<Encoder<'static, W>> {
+    let range = zstd::compression_level_range();
+    if !range.contains(&level) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "compression level {} is out of range ({}..={})",
+                level,
+                range.start(),
+                range.end()
+            ),
+        ));
+    }
+    Encoder::new(sink, level)
+}
+
+/// The lightest zstd level, for `pack repack --store`: packs stay zstd so
+/// every reader can open them, but barely any time goes into compression.
+pub fn store_level() -> i32 {
+    *zstd::compression_level_range().start()
+}
+
+/// Recompress the pack `input` into `output` at zstd `level`.
+///
+/// The input is verified first. Entries are streamed across with their
+/// original headers, so the manifest and images stay byte-for-byte the same.
+/// `output` is only replaced once the new pack is complete, so it may be
+/// `input` itself.
+pub fn repack<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q, level: i32) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let report = crate::format::verify::verify_pack(input)?;
+    if !report.is_ok() {
+        return Err(WallmanError::Pack(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} is not a valid pack: {}",
+                input.display(),
+                report.problems.join("; ")
+            ),
+        )));
+    }
+
+    let mut partial = output.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let written = write_repack(input, &partial, level).and_then(|()| fs::rename(&partial, output));
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written.map_err(WallmanError::Pack)
+}
+
+fn write_repack(input: &Path, output: &Path, level: i32) -> io::Result<()> {
+    let mut archive = Archive::new(Decoder::new(File::open(input)?)?);
+    let mut builder = Builder::new(encoder(File::create(output)?, level)?);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let header = entry.header().clone();
+        builder.append(&header, entry)?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Image formats swaybg can display.
+const DISPLAYABLE_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::WebP,
+    ImageFormat::Bmp,
+];
+
+/// Detect an image's format from its contents and decode it fully.
+///
+/// The error explains why swaybg could not show it: unknown or unsupported
+/// format, or data that does not decode.
+pub(crate) fn displayable_format<R: BufRead + Seek>(
+    reader: ImageReader<R>,
+) -> std::result::Result<ImageFormat, String> {
+    let reader = reader.with_guessed_format().map_err(|e| e.to_string())?;
+    let format = reader
+        .format()
+        .ok_or_else(|| "not a recognised image format".to_string())?;
+    if !DISPLAYABLE_FORMATS.contains(&format) {
+        return Err(format!(
+            "{:?} is not supported by swaybg (use png, jpg, webp or bmp)",
+            format
+        ));
+    }
+    reader
+        .decode()
+        .map_err(|e| format!("{:?} data does not decode: {}", format, e))?;
+    Ok(format)
+}
+
+/// `displayable_format` for a file on disk.
+pub(crate) fn check_image_file(path: &Path) -> std::result::Result<ImageFormat, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    displayable_format(ImageReader::new(io::BufReader::new(file)))
+}
+
+/// Detect the image format of `path` from its contents (not its extension).
+///
+/// `None` means the file is not an image. This only reads the header; use
+/// `check_image_file` to confirm the data decodes.
+pub(crate) fn is_image(path: &Path) -> io::Result<Option<ImageFormat>> {
+    // `ImageReader::open` would seed the format from the extension.
+    let reader = ImageReader::new(io::BufReader::new(File::open(path)?));
+    Ok(reader.with_guessed_format()?.format())
+}
+
+/// Whether the entry path `name` matches `pattern`, where `*` matches any run
+/// of characters (`/` included) and `?` exactly one.
+///
+/// Used by `pack inspect --filter`, so `*.png` matches `images/day.png`.
+pub(crate) fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Last `*` seen, and the name position it is currently matched up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the `*` swallow one more character and retry.
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matches_entry_paths() {
+        assert!(glob_matches("*.png", "images/day.png"));
+        assert!(glob_matches("images/*", "images/night.jpg"));
+        assert!(glob_matches("images/d?y.*", "images/day.png"));
+        assert!(glob_matches("*", "manifest.toml"));
+        assert!(glob_matches("manifest.toml", "manifest.toml"));
+        assert!(!glob_matches("*.png", "images/day.png.meta.toml"));
+        assert!(!glob_matches("images/?", "images/ab"));
+        assert!(!glob_matches("*.jpg", "manifest.toml"));
+    }
+    use crate::format::verify::verify_pack;
+
+    #[test]
+    fn test_pack_skips_fake_images() {
+        let dir = std::env::temp_dir().join("wallman_test_pack_fake");
+        let _ = fs::remove_dir_all(&dir);
+        let images = dir.join("theme/images");
+        fs::create_dir_all(&images).unwrap();
+        image::RgbImage::new(4, 4)
+            .save(images.join("real.png"))
+            .unwrap();
+        fs::write(images.join("fake.png"), b"just text").unwrap();
+        // Valid PNG header, truncated body: detected but does not decode.
+        let real = fs::read(images.join("real.png")).unwrap();
+        fs::write(images.join("cut.png"), &real[..16]).unwrap();
+
+        assert_eq!(
+            is_image(&images.join("real.png")).unwrap(),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(is_image(&images.join("fake.png")).unwrap(), None);
+
+        let out = dir.join("theme.wallman");
+        Packager::new(Config::default(), dir.join("theme"))
+            .pack(&out)
+            .unwrap();
+        let report = verify_pack(&out).unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.entries, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pack_bundles_valid_sidecars() {
+        let dir = std::env::temp_dir().join("wallman_test_pack_sidecar");
+        let _ = fs::remove_dir_all(&dir);
+        let images = dir.join("theme/images");
+        fs::create_dir_all(&images).unwrap();
+        image::RgbImage::new(4, 4)
+            .save(images.join("pine.png"))
+            .unwrap();
+        fs::write(images.join("pine.png.meta.toml"), "rotate = 90\n").unwrap();
+        fs::write(images.join("bad.png.meta.toml"), "rotate = 45\n").unwrap();
+
+        let out = dir.join("theme.wallman");
+        Packager::new(Config::default(), dir.join("theme"))
+            .pack(&out)
+            .unwrap();
+        let report = verify_pack(&out).unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        // manifest.toml, pine.png and its sidecar; the invalid sidecar is left out.
+        assert_eq!(report.entries, 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pack_to_writes_into_a_buffer() {
+        let dir = std::env::temp_dir().join("wallman_test_pack_to");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("images")).unwrap();
+        image::RgbImage::new(2, 2)
+            .save(dir.join("images/a.png"))
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        Packager::new(Config::default(), &dir)
+            .pack_to(&mut buffer)
+            .unwrap();
+
+        let decoder = zstd::Decoder::new(&buffer[..]).unwrap();
+        let mut names: Vec<String> = tar::Archive::new(decoder)
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["images/a.png", "manifest.toml"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_repack_keeps_entries_byte_for_byte() {
+        let dir = std::env::temp_dir().join("wallman_test_repack");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("theme/images")).unwrap();
+        image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([x as u8, y as u8, 7]))
+            .save(dir.join("theme/images/a.png"))
+            .unwrap();
+        let original = dir.join("theme.wallman");
+        Packager::new(Config::default(), dir.join("theme"))
+            .pack(&original)
+            .unwrap();
+
+        let tar_of = |path: &Path| zstd::decode_all(File::open(path).unwrap()).unwrap();
+        let max = dir.join("max.wallman");
+        repack(&original, &max, *zstd::compression_level_range().end()).unwrap();
+        assert_eq!(tar_of(&max), tar_of(&original));
+        assert!(verify_pack(&max).unwrap().is_ok());
+
+        // In place, at the store level.
+        let stored = dir.join("stored.wallman");
+        fs::copy(&original, &stored).unwrap();
+        repack(&stored, &stored, store_level()).unwrap();
+        assert_eq!(tar_of(&stored), tar_of(&original));
+        assert!(!dir.join("stored.wallman.partial").exists());
+
+        assert!(repack(&original, dir.join("bad.wallman"), 99).is_err());
+        assert!(!dir.join("bad.wallman").exists());
+        fs::write(dir.join("junk.wallman"), b"not a pack").unwrap();
+        assert!(repack(dir.join("junk.wallman"), dir.join("out.wallman"), 3).is_err());
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,244 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use tar::Archive;
+use zstd::Decoder;
+
+use crate::{
+    Config, ImageMeta,
+    error::{Result, WallmanError},
+    format::{install::is_safe_entry_path, pack::displayable_format},
+    outputs::variant,
+};
+
+/// Outcome of checking a pack without installing it.
+#[derive(Debug, Default)]
+pub struct PackReport {
+    /// Number of archive entries read.
+    pub entries: usize,
+    /// Parsed `manifest.toml`, if present and valid.
+    pub manifest: Option<Config>,
+    /// Human-readable description of every problem found.
+    pub problems: Vec<String>,
+}
+
+impl PackReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check that a pack decodes, has a valid manifest, contains every image the
+/// manifest references, only ships images swaybg can display and has no
+/// entries escaping the install directory.
+///
+/// Only a file that cannot be opened at all is an `Err`; everything else is
+/// collected into the report so all problems are shown at once.
+pub fn verify_pack<T: AsRef<Path>>(file: T) -> Result<PackReport> {
+    read_report(file.as_ref()).map_err(WallmanError::Pack)
+}
+
+fn read_report(file: &Path) -> io::Result<PackReport> {
+    let mut report = PackReport::default();
+    let decoder = Decoder::new(File::open(file)?)?;
+    let mut archive = Archive::new(decoder);
+
+    let mut paths: HashSet<PathBuf> = HashSet::new();
+    let entries = match archive.entries() {
+        Ok(entries) => entries,
+        Err(e) => {
+            report.problems.push(format!("not a valid pack: {}", e));
+            return Ok(report);
+        }
+    };
+
+    for entry in entries {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.problems.push(format!("corrupt archive: {}", e));
+                return Ok(report);
+            }
+        };
+        report.entries += 1;
+
+        let path = entry.path()?.into_owned();
+        if !is_safe_entry_path(&path) {
+            report
+                .problems
+                .push(format!("unsafe entry path: {}", path.display()));
+        }
+
+        if path == Path::new("manifest.toml") {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            match toml::from_str::<Config>(&contents) {
+                Ok(config) => report.manifest = Some(config),
+                Err(e) => report
+                    .problems
+                    .push(format!("manifest.toml is invalid: {}", e)),
+            }
+        } else if entry.header().entry_type().is_file() && ImageMeta::is_sidecar(&path) {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            if let Err(e) = ImageMeta::parse(&contents) {
+                report
+                    .problems
+                    .push(format!("{}: invalid sidecar: {}", path.display(), e));
+            }
+        } else if entry.header().entry_type().is_file() {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let reader = image::ImageReader::new(Cursor::new(data));
+            if let Err(reason) = displayable_format(reader) {
+                report
+                    .problems
+                    .push(format!("{}: {}", path.display(), reason));
+            }
+        }
+        paths.insert(path);
+    }
+
+    let Some(manifest) = &report.manifest else {
+        if !paths.contains(Path::new("manifest.toml")) {
+            report.problems.push("manifest.toml is missing".to_string());
+        }
+        return Ok(report);
+    };
+
+    // Installed themes resolve images against their images folder (see `resolve_image_path`).
+    let images_dir = Path::new(manifest.images_dir());
+    let present =
+        |image: &str| paths.contains(&images_dir.join(image)) || paths.contains(Path::new(image));
+    let missing: Vec<String> = manifest
+        .referenced_images()
+        .into_iter()
+        .filter(|image| {
+            if crate::wallpaper::generated::is_generated(image) {
+                // Generated on the user's machine, never shipped.
+                false
+            } else if variant::is_template(image) {
+                // Any resolution variant (or the generic file) is enough.
+                !paths
+                    .iter()
+                    .filter_map(|p| p.strip_prefix(images_dir).unwrap_or(p).to_str())
+                    .any(|p| variant::template_matches(image, p))
+            } else {
+                !present(image)
+            }
+        })
+        .map(|image| format!("missing image: {}", image))
+        .collect();
+    report.problems.extend(missing);
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackgroundConfig, FillMode, format::pack::Packager};
+    use std::{collections::HashMap, fs};
+
+    fn build_pack(dir: &Path, images: &[&str]) -> PathBuf {
+        let theme = dir.join("theme");
+        fs::create_dir_all(theme.join("images")).unwrap();
+        image::RgbImage::new(4, 4)
+            .save(theme.join("images/day.jpg"))
+            .unwrap();
+
+        let background = images
+            .iter()
+            .map(|image| {
+                (
+                    image.to_string(),
+                    BackgroundConfig {
+                        image: Some(image.to_string()),
+                        fill_mode: Some(FillMode::Fill),
+                        mode: None,
+                        blur_sigma: None,
+                        brightness: None,
+                        fallback_color: None,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let manifest = Config {
+            background: Some(background),
+            ..Config::default()
+        };
+
+        let out = dir.join("theme.wallman");
+        Packager::new(manifest, &theme).pack(&out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_verify_accepts_complete_pack() {
+        let dir = std::env::temp_dir().join("wallman_test_verify_ok");
+        let _ = fs::remove_dir_all(&dir);
+        let pack = build_pack(&dir, &["day.jpg"]);
+
+        let report = verify_pack(&pack).unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!(report.entries, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_reports_missing_images() {
+        let dir = std::env::temp_dir().join("wallman_test_verify_missing");
+        let _ = fs::remove_dir_all(&dir);
+        let pack = build_pack(&dir, &["day.jpg", "night.jpg"]);
+
+        let report = verify_pack(&pack).unwrap();
+        assert_eq!(report.problems, vec!["missing image: night.jpg"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_rejects_garbage() {
+        let dir = std::env::temp_dir().join("wallman_test_verify_garbage");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let pack = dir.join("bad.wallman");
+        fs::write(&pack, b"definitely not zstd").unwrap();
+
+        assert!(!verify_pack(&pack).map(|r| r.is_ok()).unwrap_or(false));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_reports_undisplayable_images() {
+        let dir = std::env::temp_dir().join("wallman_test_verify_format");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // The packer refuses fake images, so build the archive by hand.
+        let mut tar_data = Vec::new();
+        {
+            let mut builder = tar::Builder::new(&mut tar_data);
+            for (name, data) in [
+                ("manifest.toml", &b""[..]),
+                ("images/fake.png", b"just text"),
+            ] {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append_data(&mut header, name, data).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+        let pack = dir.join("fake.wallman");
+        fs::write(&pack, zstd::encode_all(&tar_data[..], 3).unwrap()).unwrap();
+
+        let report = verify_pack(&pack).unwrap();
+        assert_eq!(report.problems.len(), 1, "{:?}", report.problems);
+        assert!(report.problems[0].starts_with("images/fake.png: "));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
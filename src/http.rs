@@ -0,0 +1,82 @@
+//! Shared HTTP client construction for the weather trigger and pack downloads.
+
+use std::{fs, path::Path};
+
+use reqwest::{Certificate, blocking::Client};
+
+use crate::error::{Result, WallmanError};
+
+/// Build a blocking client that also trusts the PEM certificates in `ca_bundle`.
+///
+/// Unlike `Client::new`, a TLS backend that cannot initialise (e.g. no system
+/// CA certificates in a minimal container) is reported as an error instead of
+/// panicking.
+pub fn client(ca_bundle: Option<&Path>) -> Result<Client> {
+    let mut builder = Client::builder();
+    if let Some(path) = ca_bundle {
+        builder = builder.tls_certs_merge(ca_certs(path)?);
+    }
+    builder
+        .build()
+        .map_err(|e| WallmanError::Network(format!("could not initialise HTTP client: {}", e)))
+}
+
+/// Async counterpart of `client`, for the `async` feature.
+#[cfg(feature = "async")]
+pub fn async_client(ca_bundle: Option<&Path>) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(path) = ca_bundle {
+        builder = builder.tls_certs_merge(ca_certs(path)?);
+    }
+    builder
+        .build()
+        .map_err(|e| WallmanError::Network(format!("could not initialise HTTP client: {}", e)))
+}
+
+/// The PEM certificates in the `caBundle` at `path`.
+fn ca_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let pem = fs::read(path).map_err(|e| {
+        WallmanError::Config(format!("cannot read caBundle {}: {}", path.display(), e))
+    })?;
+    let certs = Certificate::from_pem_bundle(&pem)
+        .map_err(|e| WallmanError::Config(format!("invalid caBundle {}: {}", path.display(), e)))?;
+    tracing::debug!(
+        "Trusting {} certificate(s) from {}",
+        certs.len(),
+        path.display()
+    );
+    Ok(certs)
+}
+
+/// `client` using the `caBundle` of the loaded config, if any.
+pub fn configured_client() -> Result<Client> {
+    let ca_bundle = crate::APP_STATE
+        .get()
+        .and_then(|state| state.lock().unwrap().config.ca_bundle.clone());
+    client(ca_bundle.as_deref().map(Path::new))
+}
+
+/// `async_client` using the `caBundle` of the loaded config, if any.
+#[cfg(feature = "async")]
+pub fn configured_async_client() -> Result<reqwest::Client> {
+    let ca_bundle = crate::APP_STATE
+        .get()
+        .and_then(|state| state.lock().unwrap().config.ca_bundle.clone());
+    async_client(ca_bundle.as_deref().map(Path::new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreadable_ca_bundle_is_a_config_error() {
+        assert!(client(None).is_ok());
+
+        let missing = std::env::temp_dir().join("wallman_test_no_such_bundle.pem");
+        match client(Some(&missing)) {
+            Err(WallmanError::Config(msg)) => assert!(msg.contains("caBundle"), "{}", msg),
+            other => panic!("expected a config error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::path::Path;
+
+use crate::{AppState, BackgroundMode, OutputResolver, TriggerKind, wallpaper::apply};
+
+/// One image a config section could show on an output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageEntry {
+    pub section: TriggerKind,
+    /// State that selects the image: `day`, `rainy`, `wifi:Home`, …
+    pub state: String,
+    /// The value as written in the config.
+    pub configured: String,
+    /// Path after theme pool, generated-image and resolution-variant resolution.
+    pub resolved: String,
+    pub exists: bool,
+}
+
+/// Every image that could be applied to one output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputImages {
+    pub output: String,
+    pub entries: Vec<ImageEntry>,
+}
+
+/// List the images each of `resolver`'s outputs could show, across every
+/// configured section (not only the one the daemon would run).
+pub fn list(state: &AppState, resolver: &OutputResolver) -> Vec<OutputImages> {
+    resolver
+        .outputs()
+        .iter()
+        .map(|output| {
+            let mut images = Vec::new();
+            for kind in TriggerKind::PRIORITY {
+                for (label, configured) in section_images(state, kind, output) {
+                    let resolved = if apply::is_solid_color(&configured) {
+                        configured.clone()
+                    } else {
+                        state.resolve_image_for_output(
+                            output,
+                            &configured,
+                            resolver.geometry(output),
+                        )
+                    };
+                    images.push(ImageEntry {
+                        section: kind,
+                        state: label,
+                        exists: apply::is_solid_color(&resolved) || Path::new(&resolved).exists(),
+                        configured,
+                        resolved,
+                    });
+                }
+            }
+            OutputImages {
+                output: output.clone(),
+                entries: images,
+            }
+        })
+        .collect()
+}
+
+/// `(state, image)` pairs of the entry `kind`'s section has for `output`.
+fn section_images(state: &AppState, kind: TriggerKind, output: &str) -> Vec<(String, String)> {
+    let config = &state.config;
+    let mut images = Vec::new();
+    match kind {
+        TriggerKind::Weather => {
+            if let Some(entry) = config
+                .weather
+                .as_ref()
+                .and_then(|map| map.get(OutputResolver::matching_key(map, output)?))
+            {
+                let mut states: Vec<_> = entry.weather.iter().collect();
+                states.sort();
+                images.extend(states.into_iter().map(|(s, i)| (s.clone(), i.clone())));
+            }
+        }
+        TriggerKind::Network => {
+            if let Some(entry) = config
+                .network
+                .as_ref()
+                .and_then(|map| map.get(OutputResolver::matching_key(map, output)?))
+            {
+                let mut ssids: Vec<_> = entry.ssid.iter().collect();
+                ssids.sort();
+                images.extend(
+                    ssids
+                        .into_iter()
+                        .map(|(ssid, i)| (format!("wifi:{}", ssid), i.clone())),
+                );
+                images.extend(entry.default.clone().map(|i| ("default".to_string(), i)));
+            }
+        }
+        TriggerKind::Workspace => {
+            if let Some(entry) = config
+                .workspace
+                .as_ref()
+                .and_then(|map| map.get(OutputResolver::matching_key(map, output)?))
+            {
+                let mut apps: Vec<_> = entry.app.iter().collect();
+                apps.sort();
+                images.extend(
+                    apps.into_iter()
+                        .map(|(app, i)| (format!("app:{}", app), i.clone())),
+                );
+                let mut names: Vec<_> = entry.name.iter().collect();
+                names.sort();
+                images.extend(
+                    names
+                        .into_iter()
+                        .map(|(name, i)| (format!("workspace:{}", name), i.clone())),
+                );
+                images.extend(entry.default.clone().map(|i| ("default".to_string(), i)));
+            }
+        }
+        TriggerKind::DayTime => {
+            if let Some(entry) = config
+                .time_config
+                .as_ref()
+                .and_then(|map| map.get(OutputResolver::matching_key(map, output)?))
+            {
+                images.push(("day".to_string(), entry.day.clone()));
+                images.push(("night".to_string(), entry.night.clone()));
+                for rule in &entry.rules {
+                    let days = rule
+                        .days
+                        .iter()
+                        .flatten()
+                        .map(|d| format!("{:?}", d).to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    images.push((format!("day ({})", days), rule.day.clone()));
+                    images.push((format!("night ({})", days), rule.night.clone()));
+                }
+            }
+        }
+        TriggerKind::Static => {
+            if let Some(entry) = config
+                .background
+                .as_ref()
+                .and_then(|map| map.get(OutputResolver::matching_key(map, output)?))
+            {
+                if entry.mode == Some(BackgroundMode::Random)
+                    && let Some(pool) = state.pool_for(output)
+                {
+                    let dir = Path::new(&pool).join(config.images_dir());
+                    images.push(("random".to_string(), dir.to_string_lossy().to_string()));
+                } else {
+                    images.extend(entry.image.clone().map(|i| ("image".to_string(), i)));
+                }
+            }
+        }
+    }
+    images
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn test_list_groups_every_section_by_output() {
+        let dir = std::env::temp_dir().join("wallman_test_images_list");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("day.png"), b"").unwrap();
+
+        let config: Config = toml::from_str(
+            r##"
+                [background.DP-1]
+                image = "#112233"
+
+                [timeConfig."*"]
+                day = "day.png"
+                night = "night.png"
+            "##,
+        )
+        .unwrap();
+        let state = AppState::new(
+            config,
+            dir.join("config.toml").to_string_lossy().to_string(),
+            None,
+            false,
+        )
+        .unwrap();
+        let resolver = OutputResolver::from_outputs(vec!["DP-1".into(), "HDMI-1".into()]);
+
+        let listed = list(&state, &resolver);
+
+        assert_eq!(listed.len(), 2);
+        let dp1: Vec<(&str, bool)> = listed[0]
+            .entries
+            .iter()
+            .map(|e| (e.state.as_str(), e.exists))
+            .collect();
+        assert_eq!(dp1, [("day", true), ("night", false), ("image", true)]);
+        assert_eq!(listed[1].entries.len(), 2);
+        assert_eq!(
+            listed[1].entries[0].resolved,
+            dir.join("day.png").to_string_lossy()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
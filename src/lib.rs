@@ -1,7 +1,15 @@
+mod api;
 mod app_state;
 mod config;
 pub mod constants;
+pub mod doctor;
+mod error;
+pub mod explain;
 pub mod format;
+mod http;
+pub mod images;
+pub mod logging;
+mod migration;
 pub mod outputs;
 mod triggers;
 mod wallpaper;
@@ -9,9 +17,14 @@ mod wallpaper;
 pub mod cli;
 pub mod daemon;
 
+#[cfg(feature = "async")]
+pub use api::set_wallpaper_async;
+pub use api::{set_wallpaper, set_wallpaper_on};
 pub use app_state::*;
 pub use config::*;
 pub use constants::*;
-pub use outputs::OutputResolver;
+pub use error::{Result, WallmanError};
+pub use migration::CURRENT_CONFIG_VERSION;
+pub use outputs::{OutputResolver, OutputTarget};
 pub use triggers::*;
 pub use wallpaper::*;
@@ -0,0 +1,163 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use tracing_subscriber::{
+    EnvFilter, Registry, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
+
+use crate::error::{Result, WallmanError};
+
+/// Logging settings taken from the global CLI flags.
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    pub verbose: bool,
+    pub debug: bool,
+    /// Also write logs to this file, rotated daily.
+    pub log_file: Option<PathBuf>,
+}
+
+impl LogOptions {
+    /// CLI arguments that reproduce these options in a child process.
+    pub fn to_args(&self) -> Vec<std::ffi::OsString> {
+        let mut args = Vec::new();
+        if self.verbose {
+            args.push("--verbose".into());
+        }
+        if self.debug {
+            args.push("--debug".into());
+        }
+        if let Some(path) = &self.log_file {
+            args.push("--log-file".into());
+            args.push(path.into());
+        }
+        args
+    }
+}
+
+/// Options the process was started with, for forwarding to a detached daemon.
+static LOG_OPTIONS: OnceLock<LogOptions> = OnceLock::new();
+
+/// Default log file used by the detached daemon.
+pub fn default_log_file() -> PathBuf {
+    crate::data_folder().join("daemon.log")
+}
+
+/// Handle for swapping the filter once the config's `logLevel` is known.
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Options passed to `init`, if logging has been initialised.
+pub fn options() -> Option<&'static LogOptions> {
+    LOG_OPTIONS.get()
+}
+
+/// Filter directives implied by the `--verbose` / `--debug` flags.
+fn base_directives(options: &LogOptions) -> &'static str {
+    if options.debug {
+        "wallman=debug,warn"
+    } else if options.verbose {
+        "wallman=info,warn"
+    } else {
+        "warn"
+    }
+}
+
+/// The flag-derived filter extended with `extra` directives (the config's
+/// `logLevel`, e.g. `wallman::triggers::daytime_trigger=warn`).
+///
+/// As usual for `EnvFilter`, the most specific target wins, so an extra
+/// per-module directive overrides the flags for that module only.
+pub fn filter(options: &LogOptions, extra: Option<&str>) -> Result<EnvFilter> {
+    let directives = match extra.map(str::trim).filter(|e| !e.is_empty()) {
+        Some(extra) => format!("{},{}", base_directives(options), extra),
+        None => base_directives(options).to_string(),
+    };
+    EnvFilter::try_new(&directives).map_err(|e| {
+        WallmanError::Config(format!(
+            "invalid logLevel '{}': {}",
+            extra.unwrap_or_default(),
+            e
+        ))
+    })
+}
+
+/// Apply the config's `logLevel` on top of the flags `init` was called with.
+pub fn set_config_level(level: Option<&str>) -> Result<()> {
+    let (Some(options), Some(handle)) = (LOG_OPTIONS.get(), FILTER.get()) else {
+        return Ok(());
+    };
+    let filter = filter(options, level)?;
+    handle
+        .reload(filter)
+        .map_err(|e| WallmanError::Config(format!("could not apply logLevel: {}", e)))
+}
+
+/// Initialise tracing-subscriber based on verbosity flags.
+///
+/// Console output (stderr) is always enabled; when `log_file` is set the same events
+/// are also appended to a daily-rotated file (`<name>.YYYY-MM-DD`).
+pub fn init(options: LogOptions) {
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(base_directives(&options)));
+
+    let file_layer = options.log_file.as_ref().and_then(|path| {
+        let dir = path
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let name = path.file_name()?;
+        let appender = tracing_appender::rolling::daily(dir, name);
+        Some(
+            fmt::layer()
+                .with_writer(appender)
+                .with_ansi(false)
+                .with_target(false),
+        )
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            // stderr keeps command output (e.g. `pack build -o -`) clean.
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_target(false)
+                .with_thread_ids(false)
+                .compact(),
+        )
+        .with(file_layer)
+        .init();
+
+    let _ = FILTER.set(handle);
+    let _ = LOG_OPTIONS.set(options);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_level_extends_the_flag_filter() {
+        let verbose = LogOptions {
+            verbose: true,
+            ..LogOptions::default()
+        };
+        let shown = filter(&verbose, Some("wallman::triggers::daytime_trigger=warn"))
+            .unwrap()
+            .to_string();
+        assert!(shown.contains("wallman=info"), "{}", shown);
+        assert!(
+            shown.contains("wallman::triggers::daytime_trigger=warn"),
+            "{}",
+            shown
+        );
+
+        assert_eq!(
+            filter(&LogOptions::default(), Some("  "))
+                .unwrap()
+                .to_string(),
+            "warn"
+        );
+        assert!(filter(&verbose, Some("wallman=loud")).is_err());
+    }
+}
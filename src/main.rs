@@ -1,16 +1,15 @@
 use clap::Parser;
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
     sync::{Arc, Mutex},
 };
 
-use tracing_subscriber::{EnvFilter, fmt};
-
 use wallman::{
-    APP_STATE, AppState, Config,
-    cli::{Cli, dispatch},
+    APP_STATE, AppState, Config, OutputResolver, STDIN_CONFIG,
+    cli::{Cli, app::Command, dispatch},
     constants::{config_folder, config_vec},
+    logging::{self, LogOptions},
 };
 
 fn main() {
@@ -18,12 +17,46 @@ fn main() {
     let cli = Cli::parse();
 
     // ── 2. Initialise tracing / logging ─────────────────────────────────
-    init_logging(cli.verbose, cli.debug);
+    logging::init(LogOptions {
+        // Dry-run output is logged at info level, so make sure it is visible.
+        verbose: cli.verbose || cli.dry_run,
+        debug: cli.debug,
+        log_file: cli.log_file.clone(),
+    });
+
+    wallman::apply::set_dry_run(cli.dry_run);
+    wallman::cli::set_quiet(cli.quiet);
 
     // ── 3. Bootstrap APP_STATE ───────────────────────────────────────────
-    if let Err(e) = init_app_state() {
-        eprintln!("Error: failed to load configuration — {e}");
-        process::exit(2);
+    if let Err(e) = init_app_state(cli.config.clone()) {
+        // `doctor` reports a broken config as one of its checks.
+        if !matches!(cli.command, Command::Doctor) {
+            eprintln!("Error: failed to load configuration — {e}");
+            process::exit(2);
+        }
+        let config_path = cli
+            .config
+            .clone()
+            .or_else(|| Config::discover(&config_vec()))
+            .unwrap_or_else(|| config_folder().join("config.toml"));
+        let _ = APP_STATE.set(Arc::new(Mutex::new(AppState {
+            config_path: config_path.to_string_lossy().to_string(),
+            ..AppState::default()
+        })));
+    }
+
+    // The daemon keeps its own in-memory detection; only one-shot commands use outputs.json.
+    let output_cache = !matches!(cli.command, Command::Daemon { .. })
+        && APP_STATE
+            .get()
+            .is_some_and(|state| state.lock().unwrap().config.output_cache == Some(true));
+    OutputResolver::set_disk_cache(output_cache, cli.refresh_outputs);
+    if let Some(state) = APP_STATE.get() {
+        let state = state.lock().unwrap();
+        OutputResolver::set_fixed_outputs(state.config.outputs.clone());
+        if let Err(e) = logging::set_config_level(state.config.log_level.as_deref()) {
+            eprintln!("Warning: {e}");
+        }
     }
 
     // ── 4. Dispatch command ──────────────────────────────────────────────
@@ -36,42 +69,32 @@ fn main() {
     }
 }
 
-/// Initialise tracing-subscriber based on verbosity flags.
-fn init_logging(verbose: bool, debug: bool) {
-    let filter = if debug {
-        "wallman=debug,warn"
-    } else if verbose {
-        "wallman=info,warn"
-    } else {
-        "warn"
-    };
-
-    fmt()
-        .with_env_filter(EnvFilter::new(filter))
-        .with_target(false)
-        .with_thread_ids(false)
-        .compact()
-        .init();
-}
-
 /// Load config and initialise the global APP_STATE.
 ///
-/// Tries each path returned by `config_vec()` in order.
+/// Uses `config_override` (from `--config`) verbatim when given (`-` reads
+/// stdin); otherwise tries each path returned by `config_vec()` in order.
 /// Falls back to `Config::default()` if none are found.
-fn init_app_state() -> Result<(), Box<dyn std::error::Error>> {
+fn init_app_state(config_override: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
     let config_path_resolved: PathBuf;
     let config: Config;
 
-    // Try user config locations in priority order.
-    let candidates: Vec<PathBuf> = config_vec();
-    let found = candidates
-        .iter()
-        .find(|p| p.with_extension("toml").exists());
-
-    if let Some(path) = found {
-        let toml_path = path.with_extension("toml");
-        config = Config::load(toml_path.clone())?;
-        config_path_resolved = toml_path;
+    if config_override.as_deref() == Some(Path::new(STDIN_CONFIG)) {
+        config = Config::load_stdin()?;
+        config_path_resolved = PathBuf::from(STDIN_CONFIG);
+        tracing::info!("Loaded config from stdin (--config -)");
+    } else if let Some(path) = config_override {
+        if !path.exists() {
+            return Err(format!("config file {} does not exist", path.display()).into());
+        }
+        config = Config::load(path.clone())?;
+        config_path_resolved = path;
+        tracing::info!(
+            "Loaded config from {} (--config)",
+            config_path_resolved.display()
+        );
+    } else if let Some(path) = Config::discover(&config_vec()) {
+        config = Config::load(path.clone())?;
+        config_path_resolved = path;
         tracing::info!("Loaded config from {}", config_path_resolved.display());
     } else {
         tracing::info!("No config found — using defaults");
@@ -80,23 +103,25 @@ fn init_app_state() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // If a theme pool is active, merge its manifest settings.
+    let user_config = config.clone();
     let mut config = config;
-    if let Some(pool) = &config.pool {
+    if let Some(pool) = config.primary_pool() {
         let pool_path = PathBuf::from(pool);
         if let Err(e) = config.merge_theme(pool_path) {
             tracing::warn!("Failed to merge theme manifest: {}", e);
         }
     }
 
-    let images_pool = config.pool.clone();
+    let images_pool = config.primary_pool();
     let is_pool = images_pool.is_some();
 
-    let state = AppState::new(
+    let mut state = AppState::new(
         config,
         config_path_resolved.to_string_lossy().to_string(),
         images_pool,
         is_pool,
     )?;
+    state.user_config = Some(user_config);
 
     APP_STATE
         .set(Arc::new(Mutex::new(state)))
@@ -0,0 +1,126 @@
+use serde_json::{Map, Value};
+
+use crate::error::{Result, WallmanError};
+
+/// Config schema version written by this build.
+pub const CURRENT_CONFIG_VERSION: i32 = 2;
+
+/// Version assumed for configs that predate the `version` key.
+const UNVERSIONED: i32 = 1;
+
+/// A migration upgrades the raw config from `from` to `from + 1`.
+struct Migration {
+    from: i32,
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+/// Registered migrations, in ascending `from` order.
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "move per-output lat/lon/day_range to the top level",
+    apply: hoist_location_fields,
+}];
+
+/// Upgrade a raw config document in place to `CURRENT_CONFIG_VERSION`.
+///
+/// Returns the version the document had before migrating. Documents newer
+/// than this build understands are rejected instead of losing fields.
+pub fn migrate(value: &mut Value) -> Result<i32> {
+    let root = value
+        .as_object_mut()
+        .ok_or_else(|| WallmanError::Config("config root must be a table/object".to_string()))?;
+
+    let original = match root.get("version") {
+        None | Some(Value::Null) => UNVERSIONED,
+        Some(v) => v
+            .as_i64()
+            .and_then(|v| i32::try_from(v).ok())
+            .ok_or_else(|| WallmanError::Config(format!("invalid config version: {}", v)))?,
+    };
+
+    if original > CURRENT_CONFIG_VERSION {
+        return Err(WallmanError::Config(format!(
+            "config version {} is newer than this wallman supports ({}); please upgrade wallman",
+            original, CURRENT_CONFIG_VERSION
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= original) {
+        tracing::info!(
+            "Migrating config v{} → v{}: {}",
+            migration.from,
+            migration.from + 1,
+            migration.description
+        );
+        (migration.apply)(root);
+    }
+
+    root.insert("version".to_string(), Value::from(CURRENT_CONFIG_VERSION));
+    Ok(original)
+}
+
+/// v1 → v2: `lat`/`lon` used to live in `[weather.<output>]` and `day_range`
+/// in `[timeConfig.<output>]`. Both are global now; keep the first value found
+/// unless the top level already sets one.
+fn hoist_location_fields(root: &mut Map<String, Value>) {
+    hoist(root, "weather", &["lat"], "lat");
+    hoist(root, "weather", &["lon"], "lon");
+    hoist(root, "timeConfig", &["day_range", "dayRange"], "dayRange");
+}
+
+fn hoist(root: &mut Map<String, Value>, section: &str, keys: &[&str], target: &str) {
+    let mut found = None;
+    if let Some(Value::Object(outputs)) = root.get_mut(section) {
+        for entry in outputs.values_mut().filter_map(Value::as_object_mut) {
+            for key in keys {
+                if let Some(v) = entry.remove(*key) {
+                    found.get_or_insert(v);
+                }
+            }
+        }
+    }
+
+    if let Some(v) = found {
+        root.entry(target.to_string()).or_insert(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_hoists_legacy_fields() {
+        let mut value = json!({
+            "weather": { "*": { "lat": 40.7, "lon": -74.0, "weather": { "sunny": "sun.jpg" } } },
+            "timeConfig": { "*": { "day": "d.jpg", "night": "n.jpg", "day_range": "8-19" } },
+        });
+
+        assert_eq!(migrate(&mut value).unwrap(), 1);
+        assert_eq!(value["version"], json!(CURRENT_CONFIG_VERSION));
+        assert_eq!(value["lat"], json!(40.7));
+        assert_eq!(value["lon"], json!(-74.0));
+        assert_eq!(value["dayRange"], json!("8-19"));
+        assert!(value["weather"]["*"].get("lat").is_none());
+        assert!(value["timeConfig"]["*"].get("day_range").is_none());
+    }
+
+    #[test]
+    fn test_migrate_keeps_top_level_values() {
+        let mut value = json!({
+            "lat": 1.0,
+            "weather": { "*": { "lat": 40.7, "weather": {} } },
+        });
+
+        migrate(&mut value).unwrap();
+        assert_eq!(value["lat"], json!(1.0));
+    }
+
+    #[test]
+    fn test_migrate_rejects_future_version() {
+        let mut value = json!({ "version": CURRENT_CONFIG_VERSION + 1 });
+        assert!(migrate(&mut value).is_err());
+    }
+}
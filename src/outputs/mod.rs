@@ -1,2 +1,3 @@
 pub mod resolver;
-pub use resolver::OutputResolver;
+pub mod variant;
+pub use resolver::{OutputGeometry, OutputInfo, OutputResolver, OutputTarget};
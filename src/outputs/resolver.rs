@@ -1,21 +1,223 @@
+use lazy_static::lazy_static;
 use std::collections::HashMap;
-use std::result::Result as StdResult;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Result, WallmanError};
+
+/// How long a detection result is reused by `OutputResolver::detect_cached`.
+const DETECT_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Last successful output detection and when it happened.
+struct DetectCache {
+    detected_at: Instant,
+    outputs: Vec<OutputInfo>,
+}
+
+impl DetectCache {
+    fn is_fresh(&self, now: Instant) -> bool {
+        now.duration_since(self.detected_at) < DETECT_CACHE_TTL
+    }
+}
+
+lazy_static! {
+    /// Shared across triggers so consecutive evaluations reuse one `swaymsg` call.
+    static ref DETECT_CACHE: Mutex<Option<DetectCache>> = Mutex::new(None);
+    /// `outputs = [...]` from the config, see `OutputResolver::set_fixed_outputs`.
+    static ref FIXED_OUTPUTS: Mutex<Option<Vec<String>>> = Mutex::new(None);
+}
+
+/// How long `outputs.json` is trusted by one-shot CLI commands.
+const DISK_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Set from the CLI, see `OutputResolver::set_disk_cache`.
+static DISK_CACHE_ENABLED: AtomicBool = AtomicBool::new(false);
+static DISK_CACHE_REFRESH: AtomicBool = AtomicBool::new(false);
+
+/// On-disk detection shared between CLI invocations.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct DiskCache {
+    /// Unix timestamp (seconds) of the detection.
+    detected_at: u64,
+    outputs: Vec<OutputInfo>,
+}
+
+impl DiskCache {
+    fn is_fresh(&self, now: u64) -> bool {
+        self.detected_at <= now && now - self.detected_at < DISK_CACHE_TTL.as_secs()
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        let body = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&body).ok()
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let body = serde_json::to_string(self)
+            .map_err(|e| WallmanError::Output(format!("could not encode outputs: {}", e)))?;
+        // Write then rename so a concurrent reader never sees half a file.
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, body)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Location of the CLI output cache: `<data_dir>/wallman/outputs.json`.
+pub fn disk_cache_path() -> PathBuf {
+    crate::data_folder().join("outputs.json")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Pixel size and scale factor of an output.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OutputGeometry {
+    /// Width of the current mode in physical pixels.
+    pub width: u32,
+    /// Height of the current mode in physical pixels.
+    pub height: u32,
+    /// Compositor scale factor (e.g. 2.0 on a HiDPI panel).
+    pub scale: f64,
+}
+
+/// One active output as reported by the compositor.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OutputInfo {
+    /// Connector name used as the config key, e.g. `HDMI-A-1`.
+    pub name: String,
+    pub geometry: Option<OutputGeometry>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// Sway's primary output, or the focused one when none is marked primary.
+    #[serde(default)]
+    pub primary: bool,
+}
 
 /// Single source of truth for monitor/output mapping.
 pub struct OutputResolver {
     outputs: Vec<String>,
+    geometry: HashMap<String, OutputGeometry>,
+    primary: Option<String>,
 }
 
 impl OutputResolver {
     /// Detect connected outputs via `swaymsg -t get_outputs` and build the resolver.
-    pub fn detect() -> StdResult<Self, Box<dyn std::error::Error>> {
+    ///
+    /// Goes through the CLI disk cache when `set_disk_cache` enabled it.
+    pub fn detect() -> Result<Self> {
+        Ok(Self::from_detected(detect_outputs_cli()?))
+    }
+
+    /// Like `detect`, but runs `swaymsg` through tokio so an async caller's
+    /// runtime is not blocked. Always asks the compositor (no disk cache).
+    #[cfg(feature = "async")]
+    pub async fn detect_async() -> Result<Self> {
+        Ok(Self::from_detected(detect_outputs_async().await?))
+    }
+
+    fn from_detected(detected: Vec<OutputInfo>) -> Self {
+        let geometry = detected
+            .iter()
+            .filter_map(|o| Some((o.name.clone(), o.geometry?)))
+            .collect();
+        let primary = detected.iter().find(|o| o.primary).map(|o| o.name.clone());
+        let outputs = detected.into_iter().map(|o| o.name).collect();
+        Self {
+            outputs,
+            geometry,
+            primary,
+        }
+    }
+
+    /// Detect connected outputs with everything the compositor reports about them.
+    pub fn detect_info() -> Result<Vec<OutputInfo>> {
+        detect_outputs_cli()
+    }
+
+    /// Let `detect`/`detect_info` reuse `outputs.json` for `DISK_CACHE_TTL`.
+    ///
+    /// Opt-in for one-shot CLI commands (`outputCache = true`); `refresh`
+    /// (`--refresh-outputs`) ignores the cached copy but still rewrites it.
+    /// The daemon's `detect_cached` never reads this file.
+    pub fn set_disk_cache(enabled: bool, refresh: bool) {
+        DISK_CACHE_ENABLED.store(enabled, Ordering::Relaxed);
+        DISK_CACHE_REFRESH.store(refresh, Ordering::Relaxed);
+    }
+
+    /// Use `outputs` instead of asking the compositor (`outputs = [...]`).
+    ///
+    /// While set, every detection returns this list without running
+    /// `swaymsg`; outputs then have no geometry. `None` restores detection.
+    pub fn set_fixed_outputs(outputs: Option<Vec<String>>) {
+        *FIXED_OUTPUTS.lock().unwrap() = outputs;
+    }
+
+    /// Like `detect`, but reuses a result younger than `DETECT_CACHE_TTL`.
+    ///
+    /// Intended for the daemon loop, where triggers evaluate repeatedly.
+    /// One-shot CLI commands should keep calling `detect` for fresh data.
+    /// Empty detections are not cached so a missing compositor is retried.
+    pub fn detect_cached() -> Result<Self> {
+        let now = Instant::now();
+        let mut cache = DETECT_CACHE.lock().unwrap();
+
+        if let Some(cached) = cache.as_ref().filter(|c| c.is_fresh(now)) {
+            tracing::debug!("Reusing cached outputs: {:?}", cached.outputs);
+            return Ok(Self::from_detected(cached.outputs.clone()));
+        }
+
         let outputs = detect_outputs()?;
-        Ok(Self { outputs })
+        // A different set of outputs means a hotplug: drop the CLI's copy too.
+        // Focus moving to another output is not one.
+        let unfocused = |outputs: &[OutputInfo]| {
+            outputs
+                .iter()
+                .map(|o| OutputInfo {
+                    primary: false,
+                    ..o.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+        if cache
+            .as_ref()
+            .is_some_and(|c| unfocused(&c.outputs) != unfocused(&outputs))
+        {
+            let _ = fs::remove_file(disk_cache_path());
+        }
+        if outputs.is_empty() {
+            *cache = None;
+        } else {
+            *cache = Some(DetectCache {
+                detected_at: now,
+                outputs: outputs.clone(),
+            });
+        }
+        Ok(Self::from_detected(outputs))
+    }
+
+    /// Drop the cached detection so the next `detect_cached` queries sway again.
+    pub fn invalidate_cache() {
+        *DETECT_CACHE.lock().unwrap() = None;
     }
 
     /// Build from an explicit list of output names (useful for testing or non-Sway compositors).
     pub fn from_outputs(outputs: Vec<String>) -> Self {
-        Self { outputs }
+        Self {
+            outputs,
+            geometry: HashMap::new(),
+            primary: None,
+        }
     }
 
     /// Return the list of active outputs detected.
@@ -23,34 +225,169 @@ impl OutputResolver {
         &self.outputs
     }
 
+    /// The primary (else focused) output, when the compositor reported one.
+    pub fn primary(&self) -> Option<&str> {
+        self.primary.as_deref()
+    }
+
+    /// Resolution and scale of `output`, if the compositor reported them.
+    pub fn geometry(&self, output: &str) -> Option<OutputGeometry> {
+        self.geometry.get(output).copied()
+    }
+
     /// Resolve a per-output configuration map against the detected outputs.
     ///
     /// Resolution rules (per output):
     ///   1. If the map has an exact-match key → use it
     ///   2. Else if the map has a `"*"` wildcard key → use it
     ///   3. Else → skip output
-    pub fn resolve_map<T: Clone>(&self, map: &HashMap<String, T>) -> HashMap<String, T> {
-        let mut result = HashMap::new();
+    ///
+    /// Pairs come back in detection order, so apply order and logs are the
+    /// same on every run.
+    pub fn resolve_map<T: Clone>(&self, map: &HashMap<String, T>) -> Vec<(String, T)> {
+        let mut result = Vec::new();
 
         for output in &self.outputs {
-            if let Some(value) = map.get(output) {
-                result.insert(output.clone(), value.clone());
-            } else if let Some(wildcard) = map.get("*") {
-                result.insert(output.clone(), wildcard.clone());
+            // Outputs not covered by this config are skipped silently.
+            if let Some(key) = Self::matching_key(map, output) {
+                result.push((output.clone(), map[key].clone()));
             }
-            // Otherwise: output is not covered by this config — skip silently.
         }
 
         result
     }
+
+    /// The key of `map` that `resolve_map` uses for `output`: the exact
+    /// name, else `"*"`.
+    pub fn matching_key<'a, T>(map: &'a HashMap<String, T>, output: &str) -> Option<&'a str> {
+        map.get_key_value(output)
+            .or_else(|| map.get_key_value("*"))
+            .map(|(key, _)| key.as_str())
+    }
+}
+
+/// Which outputs a one-shot `wallman set` applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputTarget {
+    /// Every detected output.
+    All,
+    /// The primary output, else the focused one (see `OutputResolver::primary`).
+    Primary,
+    /// One output by connector name.
+    Named(String),
+}
+
+impl OutputTarget {
+    /// `"all"`, `"primary"` or an output name.
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "all" => Self::All,
+            "primary" => Self::Primary,
+            name => Self::Named(name.to_string()),
+        }
+    }
+
+    /// The output names this target selects among `resolver`'s outputs.
+    ///
+    /// A named output is taken as given, so it also works where detection
+    /// cannot see it.
+    pub fn select(&self, resolver: &OutputResolver) -> Result<Vec<String>> {
+        let outputs = match self {
+            Self::All => resolver.outputs().to_vec(),
+            Self::Primary => vec![resolver.primary().map(str::to_string).ok_or_else(|| {
+                WallmanError::Output(
+                    "could not determine the primary output: sway reports no primary or focused output"
+                        .to_string(),
+                )
+            })?],
+            Self::Named(name) => vec![name.clone()],
+        };
+        if outputs.is_empty() {
+            return Err(WallmanError::Output(
+                "no active outputs detected".to_string(),
+            ));
+        }
+        Ok(outputs)
+    }
 }
 
-/// Detect active output names by calling `swaymsg -t get_outputs` and parsing the JSON.
-fn detect_outputs() -> StdResult<Vec<String>, Box<dyn std::error::Error>> {
+/// `detect_outputs`, going through `outputs.json` when the CLI enabled it.
+///
+/// Empty detections are never written so a missing compositor is retried.
+fn detect_outputs_cli() -> Result<Vec<OutputInfo>> {
+    if !DISK_CACHE_ENABLED.load(Ordering::Relaxed) || FIXED_OUTPUTS.lock().unwrap().is_some() {
+        return detect_outputs();
+    }
+    let path = disk_cache_path();
+    let now = unix_now();
+
+    if !DISK_CACHE_REFRESH.load(Ordering::Relaxed)
+        && let Some(cached) = DiskCache::read(&path).filter(|c| c.is_fresh(now))
+    {
+        tracing::debug!("Reusing outputs from {}", path.display());
+        return Ok(cached.outputs);
+    }
+
+    let outputs = detect_outputs()?;
+    if !outputs.is_empty() {
+        let cache = DiskCache {
+            detected_at: now,
+            outputs,
+        };
+        if let Err(e) = cache.write(&path) {
+            tracing::debug!("Could not write {}: {}", path.display(), e);
+        }
+        return Ok(cache.outputs);
+    }
+    Ok(outputs)
+}
+
+/// Detect active outputs by calling `swaymsg -t get_outputs` and parsing the JSON.
+///
+/// A fixed output list from the config short-circuits the call.
+fn detect_outputs() -> Result<Vec<OutputInfo>> {
+    if let Some(fixed) = fixed_outputs() {
+        return Ok(fixed);
+    }
+
     let output = std::process::Command::new("swaymsg")
-        .args(&["-t", "get_outputs", "-r"])
+        .args(["-t", "get_outputs", "-r"])
         .output();
+    outputs_from_reply(output)
+}
+
+/// `detect_outputs` without blocking an async runtime on `swaymsg`.
+#[cfg(feature = "async")]
+async fn detect_outputs_async() -> Result<Vec<OutputInfo>> {
+    if let Some(fixed) = fixed_outputs() {
+        return Ok(fixed);
+    }
+
+    let output = tokio::process::Command::new("swaymsg")
+        .args(["-t", "get_outputs", "-r"])
+        .output()
+        .await;
+    outputs_from_reply(output)
+}
+
+/// The `outputs` list from the config, when one is set.
+fn fixed_outputs() -> Option<Vec<OutputInfo>> {
+    FIXED_OUTPUTS.lock().unwrap().as_ref().map(|fixed| {
+        fixed
+            .iter()
+            .map(|name| OutputInfo {
+                name: name.clone(),
+                geometry: None,
+                make: None,
+                model: None,
+                primary: false,
+            })
+            .collect()
+    })
+}
 
+/// Outputs from the result of running `swaymsg -t get_outputs -r`.
+fn outputs_from_reply(output: std::io::Result<std::process::Output>) -> Result<Vec<OutputInfo>> {
     match output {
         Ok(cmd_output) if cmd_output.status.success() => {
             let json_str = String::from_utf8_lossy(&cmd_output.stdout);
@@ -79,19 +416,83 @@ fn detect_outputs() -> StdResult<Vec<String>, Box<dyn std::error::Error>> {
 struct SwayOutput {
     name: String,
     active: bool,
+    #[serde(default)]
+    scale: Option<f64>,
+    #[serde(default)]
+    rect: Option<SwaySize>,
+    #[serde(default)]
+    current_mode: Option<SwaySize>,
+    #[serde(default)]
+    make: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    primary: bool,
+    #[serde(default)]
+    focused: bool,
 }
 
-/// Parse the JSON output of `swaymsg -t get_outputs` and return active output names.
-fn parse_swaymsg_outputs(json_str: &str) -> StdResult<Vec<String>, Box<dyn std::error::Error>> {
-    let outputs: Vec<SwayOutput> = serde_json::from_str(json_str)?;
-    let names = outputs
-        .into_iter()
-        .filter(|o| o.active)
-        .map(|o| o.name)
-        .collect::<Vec<_>>();
+#[derive(serde::Deserialize)]
+struct SwaySize {
+    width: u32,
+    height: u32,
+}
 
-    tracing::info!("Detected outputs: {:?}", names);
-    Ok(names)
+impl SwayOutput {
+    /// Prefer the current mode (physical pixels); otherwise scale the logical rect back up.
+    fn geometry(&self) -> Option<OutputGeometry> {
+        let scale = self.scale.filter(|s| *s > 0.0).unwrap_or(1.0);
+        let (width, height) = match (&self.current_mode, &self.rect) {
+            (Some(mode), _) => (mode.width, mode.height),
+            (None, Some(rect)) => (
+                (rect.width as f64 * scale).round() as u32,
+                (rect.height as f64 * scale).round() as u32,
+            ),
+            (None, None) => return None,
+        };
+        Some(OutputGeometry {
+            width,
+            height,
+            scale,
+        })
+    }
+}
+
+/// Parse the JSON output of `swaymsg -t get_outputs` and return the active outputs.
+fn parse_swaymsg_outputs(json_str: &str) -> Result<Vec<OutputInfo>> {
+    let outputs: Vec<SwayOutput> = serde_json::from_str(json_str)
+        .map_err(|e| WallmanError::Output(format!("unexpected swaymsg output: {}", e)))?;
+    let mut detected: Vec<OutputInfo> = Vec::new();
+    let mut focused = None;
+    for o in outputs.into_iter().filter(|o| o.active) {
+        // Names key the process tracker, so only the first connector of a name is kept.
+        if detected.iter().any(|d| d.name == o.name) {
+            tracing::warn!(
+                "Output '{}' reported more than once; ignoring duplicate",
+                o.name
+            );
+            continue;
+        }
+        detected.push(OutputInfo {
+            geometry: o.geometry(),
+            name: o.name,
+            make: o.make,
+            model: o.model,
+            primary: o.primary,
+        });
+        if o.focused {
+            focused = Some(detected.len() - 1);
+        }
+    }
+    // Sway rarely marks a primary output; the focused one stands in for it.
+    if !detected.iter().any(|o| o.primary)
+        && let Some(index) = focused
+    {
+        detected[index].primary = true;
+    }
+
+    tracing::info!("Detected outputs: {:?}", detected);
+    Ok(detected)
 }
 
 #[cfg(test)]
@@ -108,8 +509,13 @@ mod tests {
 
         let resolved = resolver.resolve_map(&map);
 
-        assert_eq!(resolved.get("HDMI-1"), Some(&"a.png".to_string()));
-        assert_eq!(resolved.get("DP-1"), Some(&"default.png".to_string()));
+        assert_eq!(
+            resolved,
+            [
+                ("HDMI-1".to_string(), "a.png".to_string()),
+                ("DP-1".to_string(), "default.png".to_string()),
+            ]
+        );
     }
 
     #[test]
@@ -121,8 +527,36 @@ mod tests {
 
         let resolved = resolver.resolve_map(&map);
 
-        assert_eq!(resolved.get("HDMI-1"), Some(&"default.png".to_string()));
-        assert_eq!(resolved.get("DP-1"), Some(&"default.png".to_string()));
+        assert_eq!(
+            resolved,
+            [
+                ("HDMI-1".to_string(), "default.png".to_string()),
+                ("DP-1".to_string(), "default.png".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_follows_detection_order() {
+        let detected = ["eDP-1", "HDMI-A-1", "DP-3", "DP-1", "DP-2"];
+        let resolver = OutputResolver::from_outputs(detected.map(String::from).to_vec());
+        let map: HashMap<String, usize> = detected
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), i))
+            .chain([("*".to_string(), 99)])
+            .collect();
+
+        // Same order on every call, whatever the map's iteration order is.
+        for _ in 0..10 {
+            let names: Vec<String> = resolver
+                .resolve_map(&map)
+                .into_iter()
+                .map(|(output, _)| output)
+                .collect();
+            assert_eq!(names, detected);
+        }
     }
 
     #[test]
@@ -135,10 +569,183 @@ mod tests {
         assert!(resolved.is_empty());
     }
 
+    #[test]
+    fn test_detect_cache_freshness() {
+        let now = Instant::now();
+        let cache = DetectCache {
+            detected_at: now,
+            outputs: vec![OutputInfo {
+                name: "HDMI-1".to_string(),
+                geometry: None,
+                make: None,
+                model: None,
+                primary: false,
+            }],
+        };
+
+        assert!(cache.is_fresh(now));
+        assert!(cache.is_fresh(now + DETECT_CACHE_TTL - Duration::from_millis(1)));
+        assert!(!cache.is_fresh(now + DETECT_CACHE_TTL));
+    }
+
+    #[test]
+    fn test_disk_cache_round_trip_and_freshness() {
+        let dir = std::env::temp_dir().join("wallman_test_outputs_cache");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("outputs.json");
+        assert!(DiskCache::read(&path).is_none());
+
+        let cache = DiskCache {
+            detected_at: 1_000,
+            outputs: vec![OutputInfo {
+                name: "DP-1".to_string(),
+                geometry: Some(OutputGeometry {
+                    width: 3840,
+                    height: 2160,
+                    scale: 2.0,
+                }),
+                make: Some("Dell Inc.".to_string()),
+                model: None,
+                primary: false,
+            }],
+        };
+        cache.write(&path).unwrap();
+
+        let read = DiskCache::read(&path).unwrap();
+        assert_eq!(read.outputs, cache.outputs);
+        assert!(read.is_fresh(1_000));
+        assert!(read.is_fresh(1_000 + DISK_CACHE_TTL.as_secs() - 1));
+        assert!(!read.is_fresh(1_000 + DISK_CACHE_TTL.as_secs()));
+        // A timestamp from the future (clock change) is not trusted.
+        assert!(!read.is_fresh(999));
+
+        fs::write(&path, "not json").unwrap();
+        assert!(DiskCache::read(&path).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fixed_outputs_bypass_detection() {
+        let fixed = vec!["HDMI-1".to_string(), "DP-1".to_string()];
+        OutputResolver::set_fixed_outputs(Some(fixed.clone()));
+        OutputResolver::invalidate_cache();
+
+        let detected = OutputResolver::detect().unwrap();
+        let cached = OutputResolver::detect_cached().unwrap();
+        let info = OutputResolver::detect_info().unwrap();
+        OutputResolver::set_fixed_outputs(None);
+        OutputResolver::invalidate_cache();
+
+        assert_eq!(detected.outputs(), fixed);
+        assert_eq!(cached.outputs(), fixed);
+        assert_eq!(detected.geometry("HDMI-1"), None);
+        assert_eq!(info.into_iter().map(|o| o.name).collect::<Vec<_>>(), fixed);
+    }
+
     #[test]
     fn test_parse_swaymsg_outputs() {
         let json = r#"[{"name": "HDMI-A-1","active": true},{"name": "DP-1","active": false}]"#;
         let outputs = parse_swaymsg_outputs(json).unwrap();
-        assert_eq!(outputs, vec!["HDMI-A-1".to_string()]);
+        let names: Vec<String> = outputs.into_iter().map(|o| o.name).collect();
+        assert_eq!(names, vec!["HDMI-A-1".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_swaymsg_drops_duplicate_names() {
+        let json = r#"[
+            {"name": "DP-1", "active": true, "make": "First"},
+            {"name": "HDMI-A-1", "active": true},
+            {"name": "DP-1", "active": true, "make": "Second"}
+        ]"#;
+        let outputs = parse_swaymsg_outputs(json).unwrap();
+        let names: Vec<&str> = outputs.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["DP-1", "HDMI-A-1"]);
+        assert_eq!(outputs[0].make.as_deref(), Some("First"));
+    }
+
+    #[test]
+    fn test_parse_swaymsg_geometry() {
+        // Trimmed from a real `swaymsg -t get_outputs -r` on a 4K@2x + 1080p setup.
+        let json = r#"[
+            {
+                "id": 3, "type": "output", "name": "DP-1", "active": true, "dpms": true,
+                "make": "Dell Inc.", "model": "DELL U2720Q", "serial": "ABC123",
+                "scale": 2.0, "scale_filter": "nearest", "transform": "normal",
+                "rect": { "x": 0, "y": 0, "width": 1920, "height": 1080 },
+                "current_mode": { "width": 3840, "height": 2160, "refresh": 59997 },
+                "modes": [ { "width": 3840, "height": 2160, "refresh": 59997 } ]
+            },
+            {
+                "id": 4, "type": "output", "name": "HDMI-A-1", "active": true,
+                "scale": 1.0, "transform": "normal",
+                "rect": { "x": 1920, "y": 0, "width": 1920, "height": 1080 }
+            },
+            {
+                "id": 5, "type": "output", "name": "eDP-1", "active": false,
+                "scale": -1.0, "rect": { "x": 0, "y": 0, "width": 0, "height": 0 }
+            }
+        ]"#;
+
+        let resolver = OutputResolver::from_detected(parse_swaymsg_outputs(json).unwrap());
+
+        assert_eq!(resolver.outputs(), ["DP-1", "HDMI-A-1"]);
+        assert_eq!(
+            resolver.geometry("DP-1"),
+            Some(OutputGeometry {
+                width: 3840,
+                height: 2160,
+                scale: 2.0
+            })
+        );
+        // No current_mode: fall back to the logical rect times scale.
+        assert_eq!(
+            resolver.geometry("HDMI-A-1"),
+            Some(OutputGeometry {
+                width: 1920,
+                height: 1080,
+                scale: 1.0
+            })
+        );
+        assert_eq!(resolver.geometry("eDP-1"), None);
+
+        let info = parse_swaymsg_outputs(json).unwrap();
+        assert_eq!(info[0].make.as_deref(), Some("Dell Inc."));
+        assert_eq!(info[0].model.as_deref(), Some("DELL U2720Q"));
+        assert_eq!(info[1].make, None);
+    }
+
+    #[test]
+    fn test_output_target_selects_primary_or_focused() {
+        let focused = r#"[
+            {"name": "DP-1", "active": true, "focused": false},
+            {"name": "HDMI-A-1", "active": true, "focused": true}
+        ]"#;
+        let resolver = OutputResolver::from_detected(parse_swaymsg_outputs(focused).unwrap());
+        assert_eq!(resolver.primary(), Some("HDMI-A-1"));
+        assert_eq!(
+            OutputTarget::parse("primary").select(&resolver).unwrap(),
+            ["HDMI-A-1"]
+        );
+        assert_eq!(
+            OutputTarget::parse("all").select(&resolver).unwrap(),
+            ["DP-1", "HDMI-A-1"]
+        );
+        assert_eq!(
+            OutputTarget::parse("eDP-1").select(&resolver).unwrap(),
+            ["eDP-1"]
+        );
+
+        // A primary output wins over focus.
+        let primary = r#"[
+            {"name": "DP-1", "active": true, "primary": true},
+            {"name": "HDMI-A-1", "active": true, "focused": true}
+        ]"#;
+        let resolver = OutputResolver::from_detected(parse_swaymsg_outputs(primary).unwrap());
+        assert_eq!(resolver.primary(), Some("DP-1"));
+
+        let unknown = OutputResolver::from_outputs(vec!["DP-1".into()]);
+        assert!(OutputTarget::Primary.select(&unknown).is_err());
+        let none = OutputResolver::from_outputs(Vec::new());
+        assert!(OutputTarget::All.select(&none).is_err());
     }
 }
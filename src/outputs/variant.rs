@@ -0,0 +1,198 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::OutputGeometry;
+
+const WIDTH: &str = "{width}";
+const HEIGHT: &str = "{height}";
+
+/// True if `path` contains a `{width}` or `{height}` placeholder.
+pub fn is_template(path: &str) -> bool {
+    path.contains(WIDTH) || path.contains(HEIGHT)
+}
+
+/// True if `name` is a resolution variant of `template` or its generic file.
+pub fn template_matches(template: &str, name: &str) -> bool {
+    match_template(template, name).is_some() || name == generic_name(template)
+}
+
+/// Pick the file matching an output's resolution for a template such as
+/// `/themes/forest/images/bg-{width}x{height}.png`.
+///
+/// In order of preference:
+///   1. the exact variant for the output's pixel size,
+///   2. the smallest variant covering the output, else the largest one,
+///   3. the generic file with the placeholders removed (`bg.png`).
+///
+/// Non-template paths are returned unchanged.
+pub fn select_variant(template: &str, geometry: Option<OutputGeometry>) -> String {
+    if !is_template(template) {
+        return template.to_string();
+    }
+
+    let path = Path::new(template);
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return template.to_string();
+    };
+
+    if let Some(geometry) = geometry {
+        let exact = dir.join(
+            name.replace(WIDTH, &geometry.width.to_string())
+                .replace(HEIGHT, &geometry.height.to_string()),
+        );
+        if exact.exists() {
+            return exact.to_string_lossy().to_string();
+        }
+
+        if let Some(nearest) = nearest_variant(dir, name, geometry) {
+            return nearest.to_string_lossy().to_string();
+        }
+    }
+
+    dir.join(generic_name(name)).to_string_lossy().to_string()
+}
+
+/// Scan `dir` for files matching the template and choose the best fit.
+fn nearest_variant(dir: &Path, template: &str, geometry: OutputGeometry) -> Option<PathBuf> {
+    let variants: Vec<(u64, bool, PathBuf)> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let (w, h) = match_template(template, name.to_str()?)?;
+            let (w, h) = (w.unwrap_or(geometry.width), h.unwrap_or(geometry.height));
+            let covers = w >= geometry.width && h >= geometry.height;
+            Some((w as u64 * h as u64, covers, entry.path()))
+        })
+        .collect();
+
+    let covering = variants
+        .iter()
+        .filter(|(_, covers, _)| *covers)
+        .min_by_key(|(area, _, _)| *area);
+    covering
+        .or_else(|| variants.iter().max_by_key(|(area, _, _)| *area))
+        .map(|(_, _, path)| path.clone())
+}
+
+/// Match a file name against the template, returning the numbers found in
+/// place of `{width}` and `{height}`.
+fn match_template(template: &str, name: &str) -> Option<(Option<u32>, Option<u32>)> {
+    let mut width = None;
+    let mut height = None;
+    let mut rest_template = template;
+    let mut rest_name = name;
+
+    loop {
+        let next = [WIDTH, HEIGHT]
+            .iter()
+            .filter_map(|p| rest_template.find(p).map(|i| (i, *p)))
+            .min();
+        let Some((index, placeholder)) = next else {
+            return (rest_template == rest_name).then_some((width, height));
+        };
+
+        rest_name = rest_name.strip_prefix(&rest_template[..index])?;
+        let digits = rest_name
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest_name.len());
+        let value: u32 = rest_name[..digits].parse().ok()?;
+        if placeholder == WIDTH {
+            width = Some(value);
+        } else {
+            height = Some(value);
+        }
+        rest_name = &rest_name[digits..];
+        rest_template = &rest_template[index + placeholder.len()..];
+    }
+}
+
+/// `bg-{width}x{height}.png` → `bg.png`: drop everything from the first to
+/// the last placeholder plus the separator in front of it.
+fn generic_name(template: &str) -> String {
+    let start = [template.find(WIDTH), template.find(HEIGHT)]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(0);
+    let end = [
+        template.rfind(WIDTH).map(|i| i + WIDTH.len()),
+        template.rfind(HEIGHT).map(|i| i + HEIGHT.len()),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+    .unwrap_or(start);
+
+    let prefix = template[..start].trim_end_matches(['-', '_', '@', '.']);
+    format!("{}{}", prefix, &template[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry(width: u32, height: u32) -> Option<OutputGeometry> {
+        Some(OutputGeometry {
+            width,
+            height,
+            scale: 1.0,
+        })
+    }
+
+    fn variants_dir(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for file in files {
+            fs::write(dir.join(file), b"img").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_select_exact_then_nearest_then_generic() {
+        let dir = variants_dir(
+            "wallman_test_variants",
+            &["bg.png", "bg-1920x1080.png", "bg-3840x2160.png"],
+        );
+        let template = dir.join("bg-{width}x{height}.png");
+        let template = template.to_str().unwrap();
+        let pick = |g| {
+            PathBuf::from(select_variant(template, g))
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        };
+
+        assert_eq!(pick(geometry(1920, 1080)), "bg-1920x1080.png");
+        // 2560x1440 has no exact file: smallest covering variant wins.
+        assert_eq!(pick(geometry(2560, 1440)), "bg-3840x2160.png");
+        // Nothing covers 5K: fall back to the largest.
+        assert_eq!(pick(geometry(5120, 2880)), "bg-3840x2160.png");
+        assert_eq!(pick(None), "bg.png");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_plain_paths_untouched() {
+        assert_eq!(select_variant("/a/b.png", geometry(1, 1)), "/a/b.png");
+    }
+
+    #[test]
+    fn test_match_and_generic_name() {
+        let template = "bg-{width}x{height}.png";
+        assert_eq!(
+            match_template(template, "bg-1920x1080.png"),
+            Some((Some(1920), Some(1080)))
+        );
+        assert_eq!(match_template(template, "bg.png"), None);
+        assert_eq!(match_template(template, "bg-axb.png"), None);
+        assert_eq!(generic_name(template), "bg.png");
+        assert_eq!(generic_name("sky_{width}.jpg"), "sky.jpg");
+    }
+}
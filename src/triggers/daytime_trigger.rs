@@ -1,76 +1,61 @@
 use crate::{
     config::DayTimeConfig,
+    error::Result,
     outputs::OutputResolver,
     trigger::{OutputChange, Trigger, TriggerResult},
 };
-use chrono::{Local, Timelike};
-use std::{collections::HashMap, result::Result as StdResult};
-use tracing::info;
+use chrono::{Datelike, Local, Timelike};
+use std::collections::{HashMap, HashSet};
+use tracing::debug;
 
 /// Day/Night trigger — switches wallpapers based on the time of day.
 ///
-/// Internal state tracks the last day/night flag *per output* so a change on
-/// one monitor does not suppress an update for another.
+/// Internal state tracks the last day/night flag and image *per output* so a
+/// change on one monitor does not suppress an update for another, and a
+/// weekday rule taking over at midnight is picked up.
 pub struct DayTimeTrigger {
-    /// Keyed by output name. `true` = currently showing day wallpaper.
-    last_state: HashMap<String, bool>,
+    /// Keyed by output name: (`true` = day, image shown).
+    last_state: HashMap<String, (bool, String)>,
+    /// Outputs whose `last_state` entry was loaded from disk and not yet confirmed.
+    restored: HashSet<String>,
+}
+
+impl Default for DayTimeTrigger {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DayTimeTrigger {
     pub fn new() -> Self {
         Self {
             last_state: HashMap::new(),
+            restored: HashSet::new(),
         }
     }
 
     #[allow(dead_code, unused_variables)]
     /// Determine whether it is currently daytime for a given output's time config.
     fn is_daytime_for(&self, time_cfg: &DayTimeConfig) -> bool {
-        let hour = Local::now().hour();
-        
-        // Try to get day_range from main config first, then use default
-        let day_range = {
-            let state = crate::APP_STATE.get().unwrap().lock().unwrap();
-            let config = state.config.clone();
-            drop(state);
-            
-            match config.day_range.as_ref() {
-                Some(range) => range.clone(),
-                None => {
-                    format!(
-                        "{}-{}",
-                        crate::constants::day_start(),
-                        crate::constants::day_end()
-                    )
-                }
-            }
-        };
-        
-        let day_start = day_range.split('-').next().unwrap().parse::<u32>().unwrap();
-        let night_start = day_range
-            .split('-')
-            .next_back()
-            .unwrap()
-            .parse::<u32>()
-            .unwrap();
-        tracing::debug!(
-            "DayTimeTrigger: day_range={} day_start={} night_start={}",
-            day_range,
-            day_start,
-            night_start
-        );
-        if day_start < night_start {
-            // Normal case: daytime window e.g. 06:00 – 18:00
-            hour >= day_start && hour < night_start
-        } else {
-            // Overnight case: daytime window wraps midnight e.g. 22:00 – 08:00
-            hour >= day_start || hour < night_start
-        }
+        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+        state.config.is_daytime(Local::now().hour())
     }
 }
 
 impl Trigger for DayTimeTrigger {
-    fn init(&mut self) -> StdResult<(), Box<dyn std::error::Error>> {
+    fn name(&self) -> &'static str {
+        crate::TriggerKind::DayTime.name()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        // Pick up where the previous daemon left off.
+        if self.last_state.is_empty()
+            && let Some(saved) = crate::state::load::<HashMap<String, (bool, String)>>(self.name())
+        {
+            self.restored = saved.keys().cloned().collect();
+            self.last_state = saved;
+        }
+
         // ── 1. Clone config ───────────────────────────────────────────────
         let state = crate::APP_STATE.get().unwrap().lock().unwrap();
         let config = state.config.clone();
@@ -85,7 +70,7 @@ impl Trigger for DayTimeTrigger {
         };
 
         // Detect outputs to log status
-        let resolver = OutputResolver::detect()?;
+        let resolver = OutputResolver::detect_cached()?;
         let resolved_time = resolver.resolve_map(time_map);
 
         for (output, time_cfg) in &resolved_time {
@@ -100,8 +85,8 @@ impl Trigger for DayTimeTrigger {
         Ok(())
     }
 
-    fn evaluate(&mut self) -> StdResult<Option<TriggerResult>, Box<dyn std::error::Error>> {
-        info!("DayTimeTrigger evaluate started");
+    fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
+        debug!("DayTimeTrigger evaluate started");
         // ── 1. Clone config ───────────────────────────────────────────────
         let state = crate::APP_STATE.get().unwrap().lock().unwrap();
         let config = state.config.clone();
@@ -115,39 +100,54 @@ impl Trigger for DayTimeTrigger {
         };
 
         // ── 2. Detect outputs ─────────────────────────────────────────────
-        let resolver = OutputResolver::detect()?;
-        info!("DayTimeTrigger resolver detected outputs");
-
-        let resolved_time = resolver.resolve_map(time_map);
-
-        info!("DayTimeTrigger resolved maps for all outputs: {:?}", resolved_time.keys().collect::<Vec<_>>());
+        let resolver = OutputResolver::detect_cached()?;
+        debug!("DayTimeTrigger resolver detected outputs");
+
+        // Collapse weekday rules into the plain day/night pair for today.
+        let weekday = Local::now().weekday();
+        let resolved_time: Vec<(String, DayTimeConfig)> = resolver
+            .resolve_map(time_map)
+            .into_iter()
+            .map(|(output, cfg)| {
+                let (day, night) = cfg.images_for(weekday);
+                let today = DayTimeConfig {
+                    day: day.to_string(),
+                    night: night.to_string(),
+                    rules: Vec::new(),
+                    fallback_color: None,
+                };
+                (output, today)
+            })
+            .collect();
+
+        debug!(
+            "DayTimeTrigger resolved maps for all outputs: {:?}",
+            resolved_time
+                .iter()
+                .map(|(output, _)| output)
+                .collect::<Vec<_>>()
+        );
 
         // ── 4. Determine changes per output ──────────────────────────────
         let mut changes: Vec<OutputChange> = Vec::new();
-        info!("DayTimeTrigger determining changes per output");
-        
+        debug!("DayTimeTrigger determining changes per output");
+
         if resolved_time.is_empty() {
-            info!("DayTimeTrigger: no outputs with time config - cannot determine changes");
+            debug!("DayTimeTrigger: no outputs with time config - cannot determine changes");
             return Ok(None);
         }
         for (output, time_cfg) in &resolved_time {
             let is_day = self.is_daytime_for(time_cfg);
-            info!("Processing output '{}': is_day={}, time_cfg.day='{}', time_cfg.night='{}'", 
-                  output, is_day, time_cfg.day, time_cfg.night);
-
-            // Only emit a change if the state actually flipped for this output.
-            if self.last_state.get(output) == Some(&is_day) {
-                info!("Output '{}': state unchanged (last_state={:?}), skipping", 
-                      output, self.last_state.get(output));
-                continue;
-            }
-
-            info!("Output '{}': state changed, will apply wallpaper", output);
+            debug!(
+                "Processing output '{}': is_day={}, time_cfg.day='{}', time_cfg.night='{}'",
+                output, is_day, time_cfg.day, time_cfg.night
+            );
 
             // Pick the correct image for this output and time-of-day.
             // Fallback: try other outputs' time_config entries if current output has no direct path.
             let fallback_time_cfg = resolved_time
-                .values()
+                .iter()
+                .map(|(_, cfg)| cfg)
                 .find(|cfg| cfg != &time_cfg && (cfg.day.contains('/') || cfg.day.contains('.')));
 
             let image_source: &str;
@@ -159,12 +159,10 @@ impl Trigger for DayTimeTrigger {
                     time_cfg.day.clone()
                 } else {
                     image_source = "time_config fallback (from other output)";
-                    fallback_time_cfg
-                        .map(|c| c.day.clone())
-                        .unwrap_or_else(|| {
-                            tracing::warn!("No day image path found for output '{}'", output);
-                            String::new()
-                        })
+                    fallback_time_cfg.map(|c| c.day.clone()).unwrap_or_else(|| {
+                        tracing::warn!("No day image path found for output '{}'", output);
+                        String::new()
+                    })
                 }
             } else {
                 // night image = time_cfg.night field if it looks like a path,
@@ -184,7 +182,7 @@ impl Trigger for DayTimeTrigger {
             };
 
             tracing::debug!(
-                "DayTimeTrigger DEBUG: output '{}' using source '{}'",
+                "DayTimeTrigger: output '{}' using source '{}'",
                 output,
                 image_source
             );
@@ -193,9 +191,33 @@ impl Trigger for DayTimeTrigger {
                 tracing::warn!("No image path found for output '{}', skipping", output);
                 continue;
             }
+
+            // Restored state only holds while its wallpaper is still up.
+            if self.restored.remove(output) && !crate::wallpaper::is_tracked(output) {
+                self.last_state.remove(output);
+            }
+
+            // Locked outputs keep the first wallpaper they were given.
+            if config.is_locked(output) && self.last_state.contains_key(output) {
+                continue;
+            }
+
+            // Only emit a change if the state actually changed for this output.
+            let current = (is_day, image_path.clone());
+            if self.last_state.get(output) == Some(&current) {
+                debug!(
+                    "Output '{}': state unchanged (last_state={:?}), skipping",
+                    output,
+                    self.last_state.get(output)
+                );
+                continue;
+            }
+
+            debug!("Output '{}': state changed, will apply wallpaper", output);
             let state = crate::APP_STATE.get().unwrap().lock().unwrap();
 
-            let resolved_path = state.resolve_image_path(&image_path);
+            let resolved_path =
+                state.resolve_image_for_output(output, &image_path, resolver.geometry(output));
             tracing::info!(
                 "DayTimeTrigger: output '{}' → {} → '{}'",
                 output,
@@ -204,19 +226,21 @@ impl Trigger for DayTimeTrigger {
             );
 
             drop(state);
-            self.last_state.insert(output.clone(), is_day);
+            self.last_state.insert(output.clone(), current);
             changes.push(OutputChange {
                 output: output.clone(),
                 image_path: resolved_path,
+                fill_mode: config.base_fill_mode(),
             });
         }
 
         if changes.is_empty() {
-            info!("DayTimeTrigger: no changes (either no outputs or state already matches)");
+            debug!("DayTimeTrigger: no changes (either no outputs or state already matches)");
             return Ok(None);
         }
+        crate::state::save(self.name(), &self.last_state);
 
-        info!("DayTimeTrigger: {} changes to apply", changes.len());
+        debug!("DayTimeTrigger: {} changes to apply", changes.len());
 
         Ok(Some(TriggerResult { changes }))
     }
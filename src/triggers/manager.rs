@@ -1,84 +1,495 @@
-use std::time::{Duration, Instant};
+use std::{
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    time::{Duration, Instant},
+};
 
-use crate::trigger::Trigger;
+use crate::{
+    error::Result,
+    outputs::OutputResolver,
+    trigger::{OutputChange, Trigger, TriggerResult},
+};
+
+/// Sleep used when no triggers are registered, so the loop never busy-spins.
+const IDLE_SLEEP: Duration = Duration::from_secs(60);
+
+/// Upper bound on how long the loop sleeps before checking swaybg health.
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Longest a failing trigger is backed off between attempts. Triggers whose
+/// own interval is longer keep their interval.
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+/// Re-apply the last-known wallpaper on outputs whose backend process failed.
+fn respawn_exited() {
+    let changes: Vec<OutputChange> = crate::wallpaper::take_exited();
+
+    if changes.is_empty() {
+        return;
+    }
+
+    tracing::info!("Respawning the wallpaper on {} output(s)", changes.len());
+    match crate::wallpaper::apply::apply(TriggerResult { changes }) {
+        Ok(report) => report.log(),
+        Err(e) => tracing::error!("Failed to respawn wallpaper: {}", e),
+    }
+}
+
+/// Requests delivered to the running manager from outside the loop (e.g. D-Bus).
+#[derive(Debug, Clone)]
+pub enum ControlRequest {
+    /// Show `image_path` on `output` right away.
+    SetWallpaper { output: String, image_path: String },
+    /// Re-evaluate every trigger now instead of waiting for its interval.
+    Next,
+    /// Reload the config file and re-initialise all triggers.
+    Reload,
+    /// Switch to the theme in this folder and re-initialise all triggers,
+    /// without re-reading the config file.
+    SetPool(String),
+    /// Leave the loop: `run` shuts the triggers down and returns. The daemon
+    /// sends it on SIGTERM.
+    Shutdown,
+}
 
 /// Wrapper that tracks when a trigger should run next
 pub struct ScheduledTrigger {
     pub trigger: Box<dyn Trigger>,
     pub next_run: Instant,
+    /// Consecutive `evaluate` errors; reset by the next success.
+    pub failures: u32,
+}
+
+impl ScheduledTrigger {
+    /// Delay before the next run: the trigger's interval, doubled for every
+    /// consecutive failure after the first, up to `MAX_BACKOFF`.
+    fn delay(&self) -> Duration {
+        let interval = Duration::from_secs(self.trigger.interval());
+        let factor = 1u32 << self.failures.saturating_sub(1).min(16);
+        interval.max(interval.saturating_mul(factor).min(MAX_BACKOFF))
+    }
+}
+
+/// Callback run by the loop at a fixed cadence.
+struct Heartbeat {
+    interval: Duration,
+    next_beat: Instant,
+    beat: Box<dyn FnMut() + Send>,
 }
 
 /// Manages all triggers and their execution
 pub struct TriggerManager {
     triggers: Vec<ScheduledTrigger>,
+    heartbeats: Vec<Heartbeat>,
+    initialized: bool,
+    /// Set by `ControlRequest::Shutdown`; checked once per loop iteration.
+    stopping: bool,
+    /// Incoming control requests; also used as an interruptible sleep.
+    control: Option<Receiver<ControlRequest>>,
+}
+
+impl Default for TriggerManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TriggerManager {
     pub fn new() -> Self {
         Self {
             triggers: Vec::new(),
+            heartbeats: Vec::new(),
+            initialized: false,
+            stopping: false,
+            control: None,
         }
     }
 
+    /// Create the control channel and return a sender for it.
+    ///
+    /// Requests sent on it wake the loop immediately. Calling this again
+    /// replaces the previous channel.
+    pub fn control_sender(&mut self) -> Sender<ControlRequest> {
+        let (tx, rx) = mpsc::channel();
+        self.control = Some(rx);
+        tx
+    }
+
     pub fn add(&mut self, trigger: Box<dyn Trigger>) {
         // Set next_run to now so it fires immediately upon start.
         let next_run = Instant::now();
-        let trigger_name = std::any::type_name_of_val(&*trigger);
+        let trigger_name = trigger.name();
         tracing::info!("Adding trigger: {} (will run immediately)", trigger_name);
-        self.triggers.push(ScheduledTrigger { trigger, next_run });
+        self.triggers.push(ScheduledTrigger {
+            trigger,
+            next_run,
+            failures: 0,
+        });
     }
 
-    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        tracing::info!("Trigger manager started");
+    /// Labels of the registered triggers, in registration order.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.triggers.iter().map(|s| s.trigger.name()).collect()
+    }
+
+    /// Register a callback the loop runs every `interval` (e.g. watchdog pings).
+    pub fn add_heartbeat(&mut self, interval: Duration, beat: impl FnMut() + Send + 'static) {
+        self.heartbeats.push(Heartbeat {
+            interval,
+            next_beat: Instant::now(),
+            beat: Box::new(beat),
+        });
+    }
 
-        // Initialize all triggers
+    /// Initialize all triggers. `run` does this itself if it hasn't happened yet;
+    /// call it directly to do work between initialization and the first cycle.
+    pub fn init(&mut self) {
         for scheduled in &mut self.triggers {
             match scheduled.trigger.init() {
-                Ok(()) => tracing::info!(
-                    "Trigger initialized: {:?}",
-                    std::any::type_name_of_val(&*scheduled.trigger)
-                ),
+                Ok(()) => tracing::info!("Trigger initialized: {}", scheduled.trigger.name()),
                 Err(e) => tracing::error!("Failed to initialize trigger: {}", e),
             }
         }
+        self.initialized = true;
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        tracing::info!("Trigger manager started");
+
+        if !self.initialized {
+            self.init();
+        }
 
         // Force run all triggers immediately on startup (at least once)
         let now = Instant::now();
         for scheduled in self.triggers.iter_mut() {
-            tracing::info!("Running trigger on startup: {:?}", std::any::type_name_of_val(&*scheduled.trigger));
+            tracing::info!("Running trigger on startup: {}", scheduled.trigger.name());
             scheduled.next_run = now; // Force run immediately
         }
 
-        loop {
-            let now = Instant::now();
-
-            for scheduled in self.triggers.iter_mut() {
-                if now >= scheduled.next_run {
-                    tracing::info!("Trigger {:?} is ready to evaluate", std::any::type_name_of_val(&*scheduled.trigger));
-                    match scheduled.trigger.evaluate() {
-                        Ok(Some(result)) => {
-                            tracing::info!("Trigger returned {} changes", result.changes.len());
-                            // Apply wallpaper change
-                            if let Err(e) = crate::wallpaper::apply::apply(result) {
-                                tracing::error!("Failed to apply wallpaper: {}", e);
-                            }
-                        }
-                        Ok(None) => {
-                            // No change needed
-                            tracing::debug!("Trigger evaluated, no change needed");
-                        }
-                        Err(e) => {
-                            tracing::error!("Trigger evaluation failed: {}", e);
-                        }
+        while !self.stopping {
+            self.run_due(Instant::now());
+
+            respawn_exited();
+            self.beat_due_heartbeats();
+
+            // Sleep until the earliest trigger is due instead of polling,
+            // but wake periodically to supervise swaybg.
+            let wait = self
+                .next_wakeup()
+                .map(|at| at.saturating_duration_since(Instant::now()))
+                .unwrap_or(IDLE_SLEEP)
+                .min(SUPERVISE_INTERVAL);
+            tracing::debug!("Trigger manager sleeping for {:?}", wait);
+            self.wait_for(wait);
+        }
+
+        self.shutdown();
+        tracing::info!("Trigger manager stopped");
+        Ok(())
+    }
+
+    /// Evaluate every trigger due at `now`, apply its changes and schedule its
+    /// next run, backing off triggers that keep failing.
+    fn run_due(&mut self, now: Instant) {
+        for scheduled in self.triggers.iter_mut() {
+            if now < scheduled.next_run {
+                continue;
+            }
+            tracing::info!("Trigger {} is ready to evaluate", scheduled.trigger.name());
+            match scheduled.trigger.evaluate() {
+                Ok(Some(result)) => {
+                    scheduled.failures = 0;
+                    tracing::info!("Trigger returned {} changes", result.changes.len());
+                    // Apply wallpaper change
+                    match crate::wallpaper::apply::apply(result) {
+                        Ok(report) => report.log(),
+                        Err(e) => tracing::error!("Failed to apply wallpaper: {}", e),
                     }
+                }
+                Ok(None) => {
+                    scheduled.failures = 0;
+                    // No change needed
+                    tracing::debug!("Trigger evaluated, no change needed");
+                }
+                Err(e) => {
+                    scheduled.failures += 1;
+                    tracing::error!(
+                        "Trigger {} evaluation failed ({} in a row, retrying in {:?}): {}",
+                        scheduled.trigger.name(),
+                        scheduled.failures,
+                        scheduled.delay(),
+                        e
+                    );
+                }
+            }
+
+            // Schedule next run
+            scheduled.next_run = now + scheduled.delay();
+        }
+    }
+
+    /// Give every trigger a chance to release its resources.
+    fn shutdown(&mut self) {
+        for scheduled in &mut self.triggers {
+            if let Err(e) = scheduled.trigger.shutdown() {
+                tracing::error!(
+                    "Failed to shut down trigger {}: {}",
+                    scheduled.trigger.name(),
+                    e
+                );
+            }
+        }
+    }
 
-                    // Schedule next run
-                    scheduled.next_run = now + Duration::from_secs(scheduled.trigger.interval());
+    /// Sleep for `timeout`, returning early to handle a control request.
+    fn wait_for(&mut self, timeout: Duration) {
+        let received = match &self.control {
+            Some(rx) => rx.recv_timeout(timeout),
+            None => {
+                std::thread::sleep(timeout);
+                return;
+            }
+        };
+
+        match received {
+            Ok(request) => self.handle_control(request),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                tracing::debug!("Control channel closed");
+                self.control = None;
+            }
+        }
+    }
+
+    fn handle_control(&mut self, request: ControlRequest) {
+        tracing::info!("Handling control request: {:?}", request);
+        match request {
+            ControlRequest::SetWallpaper { output, image_path } => {
+                let fill_mode = crate::APP_STATE
+                    .get()
+                    .map(|state| state.lock().unwrap().config.fill_mode_for_output(&output))
+                    .unwrap_or_default();
+                let result = TriggerResult::single(output, image_path, fill_mode);
+                match crate::wallpaper::apply::apply(result) {
+                    Ok(report) => report.log(),
+                    Err(e) => tracing::error!("Failed to apply requested wallpaper: {}", e),
+                }
+            }
+            ControlRequest::Next => {
+                OutputResolver::invalidate_cache();
+                self.schedule_all_now();
+            }
+            ControlRequest::Reload => {
+                if let Some(state) = crate::APP_STATE.get()
+                    && let Err(e) = state.lock().unwrap().reload_config()
+                {
+                    tracing::error!("Failed to reload config: {}", e);
+                    return;
+                }
+                self.reinit_all();
+                crate::wallpaper::preload::spawn();
+            }
+            ControlRequest::SetPool(pool) => {
+                if let Some(state) = crate::APP_STATE.get()
+                    && let Err(e) = state.lock().unwrap().set_pool(pool)
+                {
+                    tracing::error!("Failed to switch theme: {}", e);
+                    return;
                 }
+                self.reinit_all();
+                crate::wallpaper::preload::spawn();
+            }
+            ControlRequest::Shutdown => self.stopping = true,
+        }
+    }
+
+    /// Re-initialise every trigger against the new config and run them now.
+    fn reinit_all(&mut self) {
+        OutputResolver::invalidate_cache();
+        for scheduled in &mut self.triggers {
+            if let Err(e) = scheduled.trigger.init() {
+                tracing::error!("Failed to re-initialize trigger: {}", e);
             }
+        }
+        self.schedule_all_now();
+    }
+
+    fn schedule_all_now(&mut self) {
+        let now = Instant::now();
+        for scheduled in &mut self.triggers {
+            scheduled.next_run = now;
+        }
+    }
+
+    /// Earliest trigger run or heartbeat, or `None` if nothing is scheduled.
+    fn next_wakeup(&self) -> Option<Instant> {
+        self.triggers
+            .iter()
+            .map(|s| s.next_run)
+            .chain(self.heartbeats.iter().map(|h| h.next_beat))
+            .min()
+    }
+
+    fn beat_due_heartbeats(&mut self) {
+        let now = Instant::now();
+        for heartbeat in self.heartbeats.iter_mut().filter(|h| now >= h.next_beat) {
+            (heartbeat.beat)();
+            heartbeat.next_beat = now + heartbeat.interval;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    struct NoopTrigger;
+
+    /// Counts `shutdown` calls through a shared counter.
+    struct ShutdownProbe(Arc<AtomicUsize>);
+
+    impl Trigger for ShutdownProbe {
+        fn name(&self) -> &'static str {
+            "shutdown-probe"
+        }
+
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
+            Ok(None)
+        }
+
+        fn interval(&self) -> u64 {
+            60
+        }
+
+        fn shutdown(&mut self) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    impl Trigger for NoopTrigger {
+        fn name(&self) -> &'static str {
+            "noop"
+        }
+
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
+            Ok(None)
+        }
+
+        fn interval(&self) -> u64 {
+            60
+        }
+    }
+
+    /// Fails until `ok_after` evaluations have happened.
+    struct FlakyTrigger {
+        calls: u32,
+        ok_after: u32,
+    }
+
+    impl Trigger for FlakyTrigger {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
+            self.calls += 1;
+            if self.calls > self.ok_after {
+                Ok(None)
+            } else {
+                Err(crate::WallmanError::Output("outage".to_string()))
+            }
+        }
+
+        fn interval(&self) -> u64 {
+            60
+        }
+    }
+
+    #[test]
+    fn test_repeated_errors_back_off_until_success() {
+        let mut manager = TriggerManager::new();
+        manager.add(Box::new(FlakyTrigger {
+            calls: 0,
+            ok_after: 7,
+        }));
 
-            // Sleep to prevent busy waiting
-            std::thread::sleep(Duration::from_millis(500));
+        let mut delays = Vec::new();
+        for _ in 0..8 {
+            let now = manager.triggers[0].next_run;
+            manager.run_due(now);
+            delays.push((manager.triggers[0].next_run - now).as_secs());
         }
+
+        // 60s doubling per failure, capped at 30 minutes, then back to 60s.
+        assert_eq!(delays, vec![60, 120, 240, 480, 960, 1800, 1800, 60]);
+        assert_eq!(manager.triggers[0].failures, 0);
+    }
+
+    #[test]
+    fn test_names_lists_trigger_labels() {
+        let mut manager = TriggerManager::new();
+        manager.add(Box::new(NoopTrigger));
+        manager.add(Box::new(FlakyTrigger {
+            calls: 0,
+            ok_after: 0,
+        }));
+        assert_eq!(manager.names(), ["noop", "flaky"]);
+    }
+
+    #[test]
+    fn test_next_wakeup_is_earliest_trigger() {
+        let mut manager = TriggerManager::new();
+        assert!(manager.next_wakeup().is_none());
+
+        let now = Instant::now();
+        manager.add(Box::new(NoopTrigger));
+        manager.add(Box::new(NoopTrigger));
+        manager.triggers[0].next_run = now + Duration::from_secs(900);
+        manager.triggers[1].next_run = now + Duration::from_secs(60);
+
+        assert_eq!(manager.next_wakeup(), Some(now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_next_request_reschedules_triggers() {
+        let mut manager = TriggerManager::new();
+        manager.add(Box::new(NoopTrigger));
+        manager.triggers[0].next_run = Instant::now() + Duration::from_secs(900);
+
+        let tx = manager.control_sender();
+        tx.send(ControlRequest::Next).unwrap();
+        manager.wait_for(Duration::from_secs(5));
+
+        assert!(manager.next_wakeup().unwrap() <= Instant::now());
+    }
+
+    #[test]
+    fn test_shutdown_request_ends_run_and_shuts_triggers_down() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut manager = TriggerManager::new();
+        manager.add(Box::new(ShutdownProbe(calls.clone())));
+
+        let tx = manager.control_sender();
+        tx.send(ControlRequest::Shutdown).unwrap();
+        manager.run().unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
     }
 }
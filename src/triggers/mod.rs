@@ -1,5 +1,9 @@
-pub mod trigger;
+pub mod daytime_trigger;
 pub mod manager;
+pub mod network_trigger;
+pub mod season_trigger;
+pub mod state;
 pub mod static_trigger;
-pub mod daytime_trigger;
+pub mod trigger;
 pub mod weather_trigger;
+pub mod workspace_trigger;
@@ -0,0 +1,185 @@
+use crate::{
+    config::NetworkConfig,
+    error::Result,
+    outputs::OutputResolver,
+    trigger::{OutputChange, Trigger, TriggerResult},
+};
+use std::process::Command;
+
+/// Network trigger — switches wallpapers based on the connected Wi-Fi SSID.
+///
+/// Only emits changes when the SSID changes (including connecting and
+/// disconnecting). Without a connection, or without `iwgetid`/`nmcli`, each
+/// output shows its `default` image.
+pub struct NetworkTrigger {
+    /// SSID seen on the previous evaluation; `None` until the first one.
+    last_ssid: Option<Option<String>>,
+}
+
+impl Default for NetworkTrigger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetworkTrigger {
+    pub fn new() -> Self {
+        Self { last_ssid: None }
+    }
+}
+
+/// The SSID of the active Wi-Fi connection, trying `iwgetid` then `nmcli`.
+fn current_ssid() -> Option<String> {
+    run_tool("iwgetid", &["-r"])
+        .filter(|ssid| !ssid.is_empty())
+        .or_else(|| {
+            run_tool("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"])
+                .and_then(|out| parse_nmcli_active(&out))
+        })
+}
+
+/// Run `program` and return its trimmed stdout, or `None` if it is missing or fails.
+fn run_tool(program: &str, args: &[&str]) -> Option<String> {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        Ok(output) => {
+            tracing::debug!("NetworkTrigger: {} exited with {}", program, output.status);
+            None
+        }
+        Err(e) => {
+            tracing::debug!("NetworkTrigger: could not run {}: {}", program, e);
+            None
+        }
+    }
+}
+
+/// Pick the SSID from `nmcli -t -f active,ssid dev wifi` lines (`yes:Home`).
+fn parse_nmcli_active(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("yes:"))
+        // nmcli escapes ':' in terse output.
+        .map(|ssid| ssid.replace("\\:", ":"))
+        .filter(|ssid| !ssid.is_empty())
+}
+
+/// The image for `ssid` on one output, falling back to `default`.
+fn image_for<'a>(config: &'a NetworkConfig, ssid: Option<&str>) -> Option<&'a str> {
+    ssid.and_then(|ssid| config.ssid.get(ssid))
+        .or(config.default.as_ref())
+        .map(String::as_str)
+}
+
+impl Trigger for NetworkTrigger {
+    fn name(&self) -> &'static str {
+        crate::TriggerKind::Network.name()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.last_ssid = None;
+        match current_ssid() {
+            Some(ssid) => tracing::info!("NetworkTrigger ready: connected to '{}'", ssid),
+            None => tracing::info!(
+                "NetworkTrigger ready: no Wi-Fi connection detected (iwgetid/nmcli) — using defaults"
+            ),
+        }
+        Ok(())
+    }
+
+    fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
+        // ── 1. Clone config ───────────────────────────────────────────────
+        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+        let config = state.config.clone();
+        drop(state);
+
+        let network_map = match config.network.as_ref() {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+
+        // ── 2. Only act on SSID transitions ───────────────────────────────
+        let ssid = current_ssid();
+        if self.last_ssid.as_ref() == Some(&ssid) {
+            return Ok(None);
+        }
+        tracing::info!("NetworkTrigger: SSID changed to {:?}", ssid);
+
+        // ── 3. Resolve per-output network config ──────────────────────────
+        let resolver = OutputResolver::detect_cached()?;
+        let resolved = resolver.resolve_map(network_map);
+
+        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+        let mut changes: Vec<OutputChange> = Vec::new();
+        for (output, nc) in &resolved {
+            let Some(image_path) = image_for(nc, ssid.as_deref()) else {
+                tracing::warn!(
+                    "NetworkTrigger: no image for SSID {:?} and no default on output '{}' — skipping",
+                    ssid,
+                    output
+                );
+                continue;
+            };
+
+            let resolved_path =
+                state.resolve_image_for_output(output, image_path, resolver.geometry(output));
+            tracing::info!(
+                "NetworkTrigger: output '{}' → {:?} → '{}'",
+                output,
+                ssid,
+                resolved_path
+            );
+            changes.push(OutputChange {
+                output: output.clone(),
+                image_path: resolved_path,
+                fill_mode: config.base_fill_mode(),
+            });
+        }
+        drop(state);
+
+        self.last_ssid = Some(ssid);
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(TriggerResult { changes }))
+    }
+
+    fn interval(&self) -> u64 {
+        // Network changes are not urgent; check every 30 seconds.
+        30
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_image_for_ssid_with_default() {
+        let config = NetworkConfig {
+            ssid: HashMap::from([("Home".to_string(), "home.jpg".to_string())]),
+            default: Some("away.jpg".to_string()),
+            fallback_color: None,
+        };
+        assert_eq!(image_for(&config, Some("Home")), Some("home.jpg"));
+        assert_eq!(image_for(&config, Some("Cafe")), Some("away.jpg"));
+        assert_eq!(image_for(&config, None), Some("away.jpg"));
+
+        let no_default = NetworkConfig {
+            default: None,
+            fallback_color: None,
+            ..config
+        };
+        assert_eq!(image_for(&no_default, None), None);
+    }
+
+    #[test]
+    fn test_parse_nmcli_active() {
+        let output = "no:Neighbour\nyes:Office\\:5G\nno:Other\n";
+        assert_eq!(parse_nmcli_active(output), Some("Office:5G".to_string()));
+        assert_eq!(parse_nmcli_active("no:Neighbour\n"), None);
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::Datelike;
+use std::sync::mpsc::Sender;
+
+use crate::{
+    Season,
+    constants::decompresion_folder,
+    error::Result,
+    manager::ControlRequest,
+    trigger::{Trigger, TriggerResult},
+};
+
+/// Switches the active theme when the season changes (`[seasons]`).
+///
+/// Like `wallman theme set --apply` on a schedule: the running manager is
+/// asked to switch pools in memory (`ControlRequest::SetPool`); the config
+/// file is left alone. The trigger never produces wallpaper changes itself.
+pub struct SeasonTrigger {
+    control: Sender<ControlRequest>,
+    last_season: Option<Season>,
+}
+
+impl SeasonTrigger {
+    pub fn new(control: Sender<ControlRequest>) -> Self {
+        Self {
+            control,
+            last_season: None,
+        }
+    }
+
+    /// Record `season` and say whether to switch to its theme.
+    ///
+    /// The first call after a (re)start knows of no transition, so it only
+    /// fills in a missing pool: a theme picked by hand stays until the
+    /// season actually changes.
+    fn should_switch(&mut self, season: Season, has_pool: bool) -> bool {
+        let previous = self.last_season.replace(season);
+        match previous {
+            None => !has_pool,
+            Some(previous) => previous != season,
+        }
+    }
+}
+
+impl Trigger for SeasonTrigger {
+    fn name(&self) -> &'static str {
+        "season"
+    }
+
+    fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
+        let (seasons, pool) = {
+            let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+            let Some(seasons) = state.config.seasons.clone() else {
+                return Ok(None);
+            };
+            (seasons, state.config.primary_pool())
+        };
+
+        let season = Season::for_month(chrono::Local::now().month(), seasons.southern_hemisphere);
+        if !self.should_switch(season, pool.is_some()) {
+            return Ok(None);
+        }
+
+        let Some(theme) = seasons.theme_for(season) else {
+            tracing::debug!("SeasonTrigger: no theme for {}", season.name());
+            return Ok(None);
+        };
+        let theme_dir = decompresion_folder().join(theme);
+        if !theme_dir.is_dir() {
+            tracing::warn!(
+                "SeasonTrigger: theme '{}' for {} is not installed",
+                theme,
+                season.name()
+            );
+            return Ok(None);
+        }
+        let theme_dir = theme_dir.to_string_lossy().to_string();
+        if pool.as_deref() == Some(theme_dir.as_str()) {
+            return Ok(None);
+        }
+
+        tracing::info!("It is {} — switching to theme '{}'", season.name(), theme);
+        let _ = self.control.send(ControlRequest::SetPool(theme_dir));
+        Ok(None)
+    }
+
+    fn interval(&self) -> u64 {
+        60 * 60
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_keeps_a_hand_picked_theme_until_the_season_changes() {
+        let (control, _requests) = std::sync::mpsc::channel();
+
+        let mut trigger = SeasonTrigger::new(control.clone());
+        assert!(!trigger.should_switch(Season::Summer, true));
+        assert!(!trigger.should_switch(Season::Summer, true));
+        assert!(trigger.should_switch(Season::Autumn, true));
+        assert!(!trigger.should_switch(Season::Autumn, true));
+
+        // Without any pool the season's theme is used straight away.
+        let mut trigger = SeasonTrigger::new(control);
+        assert!(trigger.should_switch(Season::Winter, false));
+    }
+}
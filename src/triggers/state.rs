@@ -0,0 +1,76 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+/// File a trigger persists its last decisions to: `<data_dir>/state/<trigger>.json`.
+pub fn state_path(trigger: &str) -> PathBuf {
+    crate::data_folder()
+        .join("state")
+        .join(format!("{}.json", trigger))
+}
+
+/// Load a trigger's persisted state. A missing, stale or corrupt file is
+/// ignored so the trigger simply starts fresh.
+pub fn load<T: DeserializeOwned>(trigger: &str) -> Option<T> {
+    read(&state_path(trigger))
+}
+
+/// Persist a trigger's state; failures are only logged.
+pub fn save<T: Serialize>(trigger: &str, state: &T) {
+    let path = state_path(trigger);
+    if let Err(e) = write(&path, state) {
+        tracing::warn!("Failed to save trigger state {}: {}", path.display(), e);
+    }
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            tracing::warn!(
+                "Ignoring unreadable trigger state {}: {}",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+fn write<T: Serialize>(path: &Path, state: &T) -> crate::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(state)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_state_round_trips_and_ignores_corrupt_files() {
+        let dir = std::env::temp_dir().join("wallman_test_trigger_state");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("daytime.json");
+
+        let state = HashMap::from([("DP-1".to_string(), (true, "/a.png".to_string()))]);
+        write(&path, &state).unwrap();
+        assert_eq!(read::<HashMap<String, (bool, String)>>(&path), Some(state));
+
+        fs::write(&path, "{not json").unwrap();
+        assert_eq!(read::<HashMap<String, (bool, String)>>(&path), None);
+        assert_eq!(
+            read::<HashMap<String, (bool, String)>>(&dir.join("missing.json")),
+            None
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
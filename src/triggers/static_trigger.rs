@@ -1,18 +1,56 @@
+use crate::wallpaper::process::{ProcessOptions, processed_image};
+use crate::{BackgroundMode, ImageMeta};
 use crate::{
+    error::Result,
     outputs::OutputResolver,
     trigger::{OutputChange, Trigger, TriggerResult},
 };
-use std::result::Result as StdResult;
+use rand::seq::IndexedRandom;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Images in a theme pool (`<pool>/<images_dir>/`, or the pool itself), sorted.
+fn pool_images(pool: &str, images_dir: &str) -> Vec<PathBuf> {
+    let pool = Path::new(pool);
+    let images_dir = pool.join(images_dir);
+    let dir = if images_dir.is_dir() {
+        images_dir
+    } else {
+        pool.to_path_buf()
+    };
+
+    let mut images: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && matches!(crate::format::pack::is_image(p), Ok(Some(_))))
+                .collect()
+        })
+        .unwrap_or_default();
+    images.sort();
+    images
+}
 
 /// Applies configured per-output wallpapers once at startup.
 ///
 /// Reads `config.background`, resolves wildcard `"*"` entries against all
 /// detected outputs, and emits a batch `TriggerResult` covering every output.
+/// Outputs with `mode = "random"` get a random image from the theme pool.
 /// After the first successful evaluation it becomes a no-op.
 pub struct StaticTrigger {
     executed: bool,
 }
 
+impl Default for StaticTrigger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StaticTrigger {
     pub fn new() -> Self {
         Self { executed: false }
@@ -20,11 +58,17 @@ impl StaticTrigger {
 }
 
 impl Trigger for StaticTrigger {
-    fn init(&mut self) -> StdResult<(), Box<dyn std::error::Error>> {
+    fn name(&self) -> &'static str {
+        crate::TriggerKind::Static.name()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        // Re-initialised on reload: show the (possibly new) backgrounds again.
+        self.executed = false;
         Ok(())
     }
 
-    fn evaluate(&mut self) -> StdResult<Option<TriggerResult>, Box<dyn std::error::Error>> {
+    fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
         if self.executed {
             return Ok(None);
         }
@@ -42,7 +86,7 @@ impl Trigger for StaticTrigger {
         };
 
         // ── 2. Detect outputs ─────────────────────────────────────────────
-        let resolver = OutputResolver::detect()?;
+        let resolver = OutputResolver::detect_cached()?;
 
         if resolver.outputs().is_empty() {
             tracing::warn!("StaticTrigger: no active outputs detected — skipping");
@@ -55,13 +99,46 @@ impl Trigger for StaticTrigger {
         // ── 4. Produce OutputChange per output ───────────────────────────
         let mut changes: Vec<OutputChange> = Vec::new();
 
+        // Enumerate each pool once; random outputs sharing a pool draw from it.
+        let mut pools: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
         for (output, bg_cfg) in &resolved {
+            if bg_cfg.mode == Some(BackgroundMode::Random) {
+                let pool_images = match state.pool_for(output) {
+                    Some(pool) => pools
+                        .entry(pool)
+                        .or_insert_with_key(|pool| pool_images(pool, state.config.images_dir()))
+                        .as_slice(),
+                    None => &[],
+                };
+                if let Some(image) = pool_images.choose(&mut rand::rng()) {
+                    let image_path = image.to_string_lossy().to_string();
+                    tracing::info!(
+                        "StaticTrigger: output '{}' → '{}' (random)",
+                        output,
+                        image_path
+                    );
+                    changes.push(OutputChange {
+                        output: output.clone(),
+                        image_path,
+                        fill_mode: state.config.fill_mode_for(bg_cfg),
+                    });
+                    continue;
+                }
+                tracing::warn!(
+                    "StaticTrigger: output '{}' uses mode = \"random\" but the pool has no images",
+                    output
+                );
+            }
+
             if let Some(image_path) = &bg_cfg.image {
-                let resolved_path = state.resolve_image_path(image_path);
+                let resolved_path =
+                    state.resolve_image_for_output(output, image_path, resolver.geometry(output));
                 tracing::info!("StaticTrigger: output '{}' → '{}'", output, resolved_path);
                 changes.push(OutputChange {
                     output: output.clone(),
                     image_path: resolved_path,
+                    fill_mode: state.config.fill_mode_for(bg_cfg),
                 });
             } else {
                 tracing::warn!(
@@ -77,6 +154,43 @@ impl Trigger for StaticTrigger {
 
         self.executed = true;
         drop(state);
+
+        // ── 5. Blur/brightness post-processing (outside the state lock) ────
+        for change in &mut changes {
+            let Some(options) = resolved
+                .iter()
+                .find(|(output, _)| *output == change.output)
+                .and_then(|(_, bg_cfg)| ProcessOptions::from_background(bg_cfg))
+            else {
+                continue;
+            };
+            // The image's sidecar wins over the per-output settings.
+            let meta = ImageMeta::for_image(Path::new(&change.image_path)).unwrap_or_default();
+            let Some(options) = ProcessOptions::with_meta(Some(options), &meta) else {
+                continue;
+            };
+            match processed_image(Path::new(&change.image_path), &options) {
+                Ok(path) => {
+                    // Rotation and blur are baked in; only the fill mode is left for apply.
+                    let remaining = ImageMeta {
+                        fill_mode: meta.fill_mode,
+                        ..ImageMeta::default()
+                    };
+                    if remaining != ImageMeta::default()
+                        && let Err(e) = remaining.save_for(&path)
+                    {
+                        tracing::warn!("StaticTrigger: could not write sidecar: {}", e);
+                    }
+                    change.image_path = path.to_string_lossy().to_string();
+                }
+                Err(e) => tracing::warn!(
+                    "StaticTrigger: could not process '{}' — showing it unmodified: {}",
+                    change.image_path,
+                    e
+                ),
+            }
+        }
+
         Ok(Some(TriggerResult { changes }))
     }
 
@@ -86,3 +200,28 @@ impl Trigger for StaticTrigger {
         60
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_images_prefers_images_subdir() {
+        let pool = std::env::temp_dir().join("wallman_test_random_pool");
+        let _ = fs::remove_dir_all(&pool);
+        fs::create_dir_all(pool.join("images")).unwrap();
+        let img = image::RgbImage::new(2, 2);
+        img.save(pool.join("cover.jpg")).unwrap();
+        img.save(pool.join("images/b.jpg")).unwrap();
+        img.save(pool.join("images/a.png")).unwrap();
+        fs::write(pool.join("images/notes.txt"), b"not an image").unwrap();
+
+        let images = pool_images(pool.to_str().unwrap(), "images");
+
+        assert_eq!(
+            images,
+            vec![pool.join("images/a.png"), pool.join("images/b.jpg")]
+        );
+        fs::remove_dir_all(&pool).unwrap();
+    }
+}
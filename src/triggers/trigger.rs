@@ -1,10 +1,12 @@
-use std::result::Result as StdResult;
+use crate::{FillMode, error::Result};
 
 /// A single output → image assignment decided by a trigger.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct OutputChange {
     pub output: String,
     pub image_path: String,
+    /// How the image fills the output; an image's sidecar overrides it.
+    pub fill_mode: FillMode,
 }
 
 /// Result of a trigger evaluation — carries decisions for one or more outputs.
@@ -17,11 +19,16 @@ pub struct TriggerResult {
 
 impl TriggerResult {
     /// Convenience constructor for a single-output result.
-    pub fn single(output: impl Into<String>, image_path: impl Into<String>) -> Self {
+    pub fn single(
+        output: impl Into<String>,
+        image_path: impl Into<String>,
+        fill_mode: FillMode,
+    ) -> Self {
         Self {
             changes: vec![OutputChange {
                 output: output.into(),
                 image_path: image_path.into(),
+                fill_mode,
             }],
         }
     }
@@ -34,15 +41,23 @@ impl TriggerResult {
 
 /// Trait that all triggers must implement.
 pub trait Trigger: Send {
+    /// Short label used in logs and `daemon status` (e.g. `"weather"`).
+    fn name(&self) -> &'static str;
+
     /// Called once when the trigger starts (before the first evaluate loop).
-    fn init(&mut self) -> StdResult<(), Box<dyn std::error::Error>>;
+    fn init(&mut self) -> Result<()>;
 
     /// Called periodically by the manager.
     ///
     /// Returns `Some(TriggerResult)` whose `changes` may cover multiple outputs
     /// if a wallpaper change is needed, or `None` when nothing changed.
-    fn evaluate(&mut self) -> StdResult<Option<TriggerResult>, Box<dyn std::error::Error>>;
+    fn evaluate(&mut self) -> Result<Option<TriggerResult>>;
 
     /// How often (in seconds) the manager should call `evaluate`.
     fn interval(&self) -> u64;
+
+    /// Called once when the manager loop exits, to release resources.
+    fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
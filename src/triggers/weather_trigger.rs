@@ -1,23 +1,26 @@
 use crate::{
+    error::{Result, WallmanError},
     outputs::OutputResolver,
     trigger::{OutputChange, Trigger, TriggerResult},
 };
+use chrono::{Local, Timelike};
 use reqwest::blocking::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    result::Result as StdResult,
+    collections::{HashMap, HashSet},
     time::{Duration, Instant},
 };
 
 /// Weather states that can trigger wallpaper changes.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WeatherState {
     Clear,
     Cloudy,
     Rainy,
     Snowy,
     Stormy,
+    /// A weather code this mapping does not know.
+    Unknown,
 }
 
 impl WeatherState {
@@ -28,17 +31,45 @@ impl WeatherState {
             Self::Rainy => "raining",
             Self::Snowy => "snowing",
             Self::Stormy => "lighting",
+            Self::Unknown => "default",
         }
     }
 
+    /// Keys tried in the `weather` map, the canonical one first, then
+    /// common variations/typos.
+    fn config_keys(&self) -> &'static [&'static str] {
+        match self {
+            Self::Clear => &["sunny", "clear"],
+            Self::Cloudy => &["cloudy"],
+            Self::Rainy => &["raining", "rainy"],
+            Self::Snowy => &["snowing"],
+            Self::Stormy => &["lighting", "stormy", "ligthing"], // User typo fallback
+            Self::Unknown => &["default", "unknown"],
+        }
+    }
+
+    /// The configured image for this state, if any. `<key>_day` / `<key>_night`
+    /// (e.g. `clear_night`) take precedence over the plain key.
+    fn image_in<'a>(
+        &self,
+        images: &'a HashMap<String, String>,
+        is_day: bool,
+    ) -> Option<&'a String> {
+        let suffix = if is_day { "_day" } else { "_night" };
+        let keys = self.config_keys();
+        keys.iter()
+            .find_map(|key| images.get(&format!("{}{}", key, suffix)))
+            .or_else(|| keys.iter().find_map(|key| images.get(*key)))
+    }
+
     fn from_code(code: i32) -> Self {
         match code {
             0 => Self::Clear,
-            1 | 2 | 3 | 51 | 53 | 55 | 56 | 57 => Self::Cloudy,
+            1 | 2 | 3 | 45 | 48 | 51 | 53 | 55 | 56 | 57 => Self::Cloudy,
             61 | 63 | 65 | 66 | 67 | 80 | 81 | 82 => Self::Rainy,
             71 | 73 | 75 | 77 | 85 | 86 => Self::Snowy,
             95 | 96 | 99 => Self::Stormy,
-            _ => Self::Cloudy,
+            _ => Self::Unknown,
         }
     }
 }
@@ -48,42 +79,83 @@ impl WeatherState {
 /// Per-output state is tracked so each monitor can independently detect changes
 /// (even though the weather source is currently global per lat/lon).
 pub struct WeatherTrigger {
-    /// Last known weather per output name.
-    last_weather: HashMap<String, WeatherState>,
-    client: Client,
+    /// Last weather and image applied per output name.
+    last_weather: HashMap<String, (WeatherState, String)>,
+    /// Outputs whose `last_weather` entry was loaded from disk and not yet confirmed.
+    restored: HashSet<String>,
+    /// Built on first use so a broken TLS setup cannot fail construction.
+    client: Option<Client>,
     last_api_call: Option<Instant>,
     /// Cached weather result between API calls.
     cached_weather: Option<WeatherState>,
 }
 
+impl Default for WeatherTrigger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WeatherTrigger {
     pub fn new() -> Self {
         Self {
             last_weather: HashMap::new(),
-            client: Client::new(),
+            restored: HashSet::new(),
+            client: None,
             last_api_call: None,
             cached_weather: None,
         }
     }
 
+    /// The HTTP client, built (honouring `caBundle`) on first use.
+    fn client(&mut self) -> Result<&Client> {
+        if self.client.is_none() {
+            self.client = Some(crate::http::configured_client()?);
+        }
+        Ok(self.client.as_ref().unwrap())
+    }
+
     /// Fetch current weather from Open-Meteo using the lat/lon from the wildcard
     /// (or first available) weather config entry.
-    fn fetch_weather(&mut self) -> StdResult<WeatherState, Box<dyn std::error::Error>> {
+    fn fetch_weather(&mut self) -> Result<WeatherState> {
+        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+        let config = state.config.clone();
+        drop(state);
+
+        let now = Local::now();
+        self.fetch_weather_at(&config, now.hour() * 60 + now.minute())
+    }
+
+    /// `fetch_weather` at `minute` past midnight, local time.
+    fn fetch_weather_at(&mut self, config: &crate::Config, minute: u32) -> Result<WeatherState> {
         // Rate-limit: at most once per 10 minutes.
         let now = Instant::now();
-        if let Some(last) = self.last_api_call {
-            if now.duration_since(last) < Duration::from_secs(600) {
-                // Return cached value.
-                if let Some(cached) = &self.cached_weather {
-                    return Ok(cached.clone());
-                }
+        if let Some(last) = self.last_api_call
+            && now.duration_since(last) < Duration::from_secs(600)
+        {
+            // Return cached value.
+            if let Some(cached) = &self.cached_weather {
+                return Ok(cached.clone());
             }
         }
 
-        // Read config for coordinates.
-        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
-        let config = state.config.clone();
-        drop(state);
+        // Quiet hours: stay off the network and reuse the last known weather,
+        // falling back to what the previous daemon saw.
+        if in_quiet_hours(config, minute) {
+            let known = self
+                .cached_weather
+                .clone()
+                .or_else(|| self.last_weather.values().next().map(|(w, _)| w.clone()));
+            match known {
+                Some(weather) => {
+                    tracing::debug!("WeatherTrigger: quiet hours — using {:?}", weather);
+                    return Ok(weather);
+                }
+                None => tracing::info!(
+                    "WeatherTrigger: quiet hours but no known weather yet — fetching once"
+                ),
+            }
+        }
 
         // Try to get lat/lon from main config first, then fall back to weather config
         let (lat, lon) = match (config.lat, config.lon) {
@@ -91,39 +163,53 @@ impl WeatherTrigger {
             _ => {
                 let weather_map = match config.weather.as_ref() {
                     Some(m) => m,
-                    None => return Err("No [weather.*] configuration found".into()),
+                    None => {
+                        return Err(WallmanError::Config(
+                            "No [weather.*] configuration found".to_string(),
+                        ));
+                    }
                 };
 
                 // Use wildcard config for coordinates (weather is global, not per-output).
                 let _weather_cfg = weather_map
                     .get("*")
                     .or_else(|| weather_map.values().next())
-                    .ok_or_else(|| "Could not find any weather configuration entry")?;
+                    .ok_or_else(|| {
+                        WallmanError::Config(
+                            "Could not find any weather configuration entry".to_string(),
+                        )
+                    })?;
 
                 // Since we removed lat/lon from WeatherConfig, we need to handle this case
                 // For backward compatibility, we'll need to check if there are any legacy configs
                 // But since we removed the fields, this should not happen in new configs
-                return Err("No latitude/longitude found in main config or weather config".into());
+                return Err(WallmanError::Config(
+                    "No latitude/longitude found in main config or weather config".to_string(),
+                ));
             }
         };
 
-        let url = format!(
-            "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current_weather=true",
-            lat, lon
-        );
-
+        let url = forecast_url(lat, lon);
         tracing::debug!("WeatherTrigger: fetching {}", url);
 
-        let response = self
-            .client
+        let client = match self.client() {
+            Ok(client) => client,
+            // Keep showing the last known weather rather than failing outright.
+            Err(e) => match &self.cached_weather {
+                Some(cached) => {
+                    tracing::warn!("WeatherTrigger: {} — using cached weather", e);
+                    return Ok(cached.clone());
+                }
+                None => return Err(e),
+            },
+        };
+        let response = client
             .get(&url)
             .timeout(Duration::from_secs(10))
             .send()?
             .error_for_status()?;
 
-        let data: WeatherApiResponse = response.json()?;
-        let state = WeatherState::from_code(data.current_weather.weathercode);
-
+        let state = state_from_response(response.json()?);
         tracing::info!("WeatherTrigger: current weather = {:?}", state);
 
         self.last_api_call = Some(now);
@@ -133,7 +219,20 @@ impl WeatherTrigger {
 }
 
 impl Trigger for WeatherTrigger {
-    fn init(&mut self) -> StdResult<(), Box<dyn std::error::Error>> {
+    fn name(&self) -> &'static str {
+        crate::TriggerKind::Weather.name()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        // Pick up where the previous daemon left off.
+        if self.last_weather.is_empty()
+            && let Some(saved) =
+                crate::state::load::<HashMap<String, (WeatherState, String)>>(self.name())
+        {
+            self.restored = saved.keys().cloned().collect();
+            self.last_weather = saved;
+        }
+
         let state = crate::APP_STATE.get().unwrap().lock().unwrap();
         let config = state.config.clone();
         drop(state);
@@ -155,7 +254,9 @@ impl Trigger for WeatherTrigger {
                     // Since we removed lat/lon from WeatherConfig, we need to handle this case
                     // For backward compatibility, we'll need to check if there are any legacy configs
                     // But since we removed the fields, this should not happen in new configs
-                    tracing::warn!("WeatherTrigger: weather config found but no lat/lon in main config");
+                    tracing::warn!(
+                        "WeatherTrigger: weather config found but no lat/lon in main config"
+                    );
                     return Ok(());
                 } else {
                     tracing::warn!("WeatherTrigger: no weather configuration entry found");
@@ -177,7 +278,7 @@ impl Trigger for WeatherTrigger {
         Ok(())
     }
 
-    fn evaluate(&mut self) -> StdResult<Option<TriggerResult>, Box<dyn std::error::Error>> {
+    fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
         // ── 1. Clone config ───────────────────────────────────────────────
         let state = crate::APP_STATE.get().unwrap().lock().unwrap();
         let config = state.config.clone();
@@ -191,17 +292,11 @@ impl Trigger for WeatherTrigger {
         };
 
         // ── 2. Fetch weather (rate-limited) ───────────────────────────────
-        let current_weather = match self.fetch_weather() {
-            Ok(w) => w,
-            Err(e) => {
-                tracing::warn!("WeatherTrigger: could not fetch weather: {}", e);
-                drop(state);
-                return Ok(None);
-            }
-        };
+        // Errors go to the manager, which backs off while the API is down.
+        let current_weather = self.fetch_weather()?;
 
         // ── 3. Detect outputs ─────────────────────────────────────────────
-        let resolver = OutputResolver::detect()?;
+        let resolver = OutputResolver::detect_cached()?;
 
         if resolver.outputs().is_empty() {
             drop(state);
@@ -214,33 +309,12 @@ impl Trigger for WeatherTrigger {
         // ── 5. Produce changes for outputs where weather flipped ──────────
         let mut changes: Vec<OutputChange> = Vec::new();
 
+        let is_day = config.is_daytime(Local::now().hour());
         for (output, wc) in &resolved_weather {
-            // Check if the state actually changed for this output.
-            if self.last_weather.get(output) == Some(&current_weather) {
-                continue;
-            }
-
-            // Look up the image for the current weather state.
+            // Look up the image for the current weather state and time of day.
             let key = current_weather.config_key();
-            let mut image_path = wc.weather.get(key).cloned();
-
-            // Fallbacks for common variations/typos
-            if image_path.is_none() {
-                image_path = match current_weather {
-                    WeatherState::Clear => wc.weather.get("clear").cloned(),
-                    WeatherState::Rainy => wc.weather.get("rainy").cloned(),
-                    WeatherState::Stormy => {
-                        wc.weather
-                            .get("stormy")
-                            .or_else(|| wc.weather.get("ligthing")) // User typo fallback
-                            .cloned()
-                    }
-                    _ => None,
-                };
-            }
-
-            let image_path = match image_path {
-                Some(p) => p,
+            let image_path = match current_weather.image_in(&wc.weather, is_day) {
+                Some(p) => p.clone(),
                 None => {
                     tracing::warn!(
                         "WeatherTrigger: no image for weather='{}' (or fallbacks) on output '{}' — skipping",
@@ -251,7 +325,24 @@ impl Trigger for WeatherTrigger {
                 }
             };
 
-            let resolved_path = state.resolve_image_path(&image_path);
+            // Restored state only holds while its wallpaper is still up.
+            if self.restored.remove(output) && !crate::wallpaper::is_tracked(output) {
+                self.last_weather.remove(output);
+            }
+
+            // Locked outputs keep the first wallpaper they were given.
+            if config.is_locked(output) && self.last_weather.contains_key(output) {
+                continue;
+            }
+
+            // Check if the weather or day/night image actually changed for this output.
+            let current = (current_weather.clone(), image_path.clone());
+            if self.last_weather.get(output) == Some(&current) {
+                continue;
+            }
+
+            let resolved_path =
+                state.resolve_image_for_output(output, &image_path, resolver.geometry(output));
             tracing::info!(
                 "WeatherTrigger: output '{}' → {:?} → '{}'",
                 output,
@@ -259,11 +350,11 @@ impl Trigger for WeatherTrigger {
                 resolved_path
             );
 
-            self.last_weather
-                .insert(output.clone(), current_weather.clone());
+            self.last_weather.insert(output.clone(), current);
             changes.push(OutputChange {
                 output: output.clone(),
                 image_path: resolved_path,
+                fill_mode: config.base_fill_mode(),
             });
         }
 
@@ -271,18 +362,80 @@ impl Trigger for WeatherTrigger {
         if changes.is_empty() {
             return Ok(None);
         }
+        crate::state::save(self.name(), &self.last_weather);
 
         Ok(Some(TriggerResult { changes }))
     }
 
     fn interval(&self) -> u64 {
-        // Check every 15 minutes to stay well within API rate limits.
-        36000
+        // Check every 15 minutes to stay well within API rate limits
+        // (and notice day/night changes reasonably quickly).
+        900
+    }
+}
+
+/// Fetches the current weather without blocking an async runtime (the
+/// `async` feature). Uses the same Open-Meteo request and state mapping as
+/// `WeatherTrigger`, without its rate limiting or caching.
+#[cfg(feature = "async")]
+pub struct AsyncWeatherProvider {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "async")]
+impl AsyncWeatherProvider {
+    /// Build the provider, honouring `caBundle` when a config is loaded.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            client: crate::http::configured_async_client()?,
+        })
+    }
+
+    /// Current weather at `lat`/`lon`.
+    pub async fn current(&self, lat: f64, lon: f64) -> Result<WeatherState> {
+        let response = self
+            .client
+            .get(forecast_url(lat, lon))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(state_from_response(response.json().await?))
     }
 }
 
+/// Whether `minute` falls in the `quiet_hours` of any `[weather.*]` section.
+///
+/// Weather is fetched once for all outputs, so one section's quiet hours
+/// cover every output.
+fn in_quiet_hours(config: &crate::Config, minute: u32) -> bool {
+    config
+        .weather
+        .iter()
+        .flat_map(|m| m.values())
+        .filter_map(|w| w.quiet_hours.as_deref())
+        .filter_map(|range| crate::parse_time_range(range).ok())
+        .any(|range| crate::in_time_range(range, minute))
+}
+
 // ── Open-Meteo API response types ────────────────────────────────────────────
 
+fn forecast_url(lat: f64, lon: f64) -> String {
+    format!(
+        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current_weather=true",
+        lat, lon
+    )
+}
+
+fn state_from_response(data: WeatherApiResponse) -> WeatherState {
+    let code = data.current_weather.weathercode;
+    let state = WeatherState::from_code(code);
+    if state == WeatherState::Unknown {
+        tracing::warn!("WeatherTrigger: unmapped weather code {}", code);
+    }
+    state
+}
+
 #[derive(Deserialize, Default)]
 struct WeatherApiResponse {
     current_weather: CurrentWeather,
@@ -300,3 +453,90 @@ struct CurrentWeather {
     #[allow(dead_code)]
     time: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_code_uses_default_image_only() {
+        assert_eq!(WeatherState::from_code(1234), WeatherState::Unknown);
+        assert_eq!(WeatherState::from_code(-1), WeatherState::Unknown);
+        assert_eq!(WeatherState::from_code(45), WeatherState::Cloudy);
+        assert_eq!(WeatherState::from_code(0), WeatherState::Clear);
+
+        let images = HashMap::from([
+            ("default".to_string(), "fallback.jpg".to_string()),
+            ("clear".to_string(), "clear.jpg".to_string()),
+        ]);
+        let image = |state: WeatherState| state.image_in(&images, true).cloned();
+        assert_eq!(
+            image(WeatherState::Unknown).as_deref(),
+            Some("fallback.jpg")
+        );
+        assert_eq!(image(WeatherState::Clear).as_deref(), Some("clear.jpg"));
+        // Known states without an image don't borrow the default.
+        assert_eq!(image(WeatherState::Rainy), None);
+    }
+
+    #[test]
+    fn test_day_night_variants_take_precedence() {
+        let images = HashMap::from([
+            ("sunny".to_string(), "sun.jpg".to_string()),
+            ("clear_night".to_string(), "stars.jpg".to_string()),
+            ("rainy_day".to_string(), "rain-day.jpg".to_string()),
+        ]);
+        let image = |state: WeatherState, is_day| state.image_in(&images, is_day).cloned();
+        assert_eq!(
+            image(WeatherState::Clear, false).as_deref(),
+            Some("stars.jpg")
+        );
+        assert_eq!(image(WeatherState::Clear, true).as_deref(), Some("sun.jpg"));
+        assert_eq!(
+            image(WeatherState::Rainy, true).as_deref(),
+            Some("rain-day.jpg")
+        );
+        // No base key and no night variant.
+        assert_eq!(image(WeatherState::Rainy, false), None);
+    }
+
+    #[test]
+    fn test_fetch_is_skipped_within_quiet_hours() {
+        let config: crate::Config = toml::from_str(
+            r#"
+                lat = 48.85
+                lon = 2.35
+
+                [weather."*"]
+                quiet_hours = "23:00-07:00"
+
+                [weather."*".weather]
+                raining = "rain.jpg"
+            "#,
+        )
+        .unwrap();
+        let mut trigger = WeatherTrigger::new();
+        trigger.cached_weather = Some(WeatherState::Rainy);
+
+        for minute in [23 * 60, 0, 6 * 60 + 59] {
+            assert_eq!(
+                trigger.fetch_weather_at(&config, minute).unwrap(),
+                WeatherState::Rainy
+            );
+        }
+        // Building the HTTP client is the first step of a real fetch.
+        assert!(trigger.client.is_none() && trigger.last_api_call.is_none());
+
+        // After a restart, the persisted state stands in for the cache.
+        let mut restarted = WeatherTrigger::new();
+        restarted.last_weather = HashMap::from([(
+            "DP-1".to_string(),
+            (WeatherState::Snowy, "snow.jpg".to_string()),
+        )]);
+        assert_eq!(
+            restarted.fetch_weather_at(&config, 2 * 60).unwrap(),
+            WeatherState::Snowy
+        );
+        assert!(restarted.client.is_none());
+    }
+}
@@ -0,0 +1,466 @@
+use crate::{
+    config::WorkspaceConfig,
+    error::Result,
+    outputs::OutputResolver,
+    trigger::{OutputChange, Trigger, TriggerResult},
+    triggers::manager::ControlRequest,
+};
+use serde_json::Value;
+use std::{
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    time::Duration,
+};
+
+/// How long the focus must hold before the wallpaper follows it.
+const DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Pause before re-subscribing after `swaymsg -t subscribe` exits.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// What sway currently has focused.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Focus {
+    output: Option<String>,
+    workspace: Option<String>,
+    /// app_id of the focused window (X11 class under Xwayland); `None` on an
+    /// empty workspace.
+    app: Option<String>,
+}
+
+/// Workspace trigger — switches the focused output's wallpaper based on the
+/// focused sway workspace or application.
+///
+/// A background `swaymsg -t subscribe` keeps the focus up to date and asks
+/// the manager to re-evaluate (`ControlRequest::Next`) once the focus has held
+/// for `DEBOUNCE`, so flicking through workspaces does not redraw on every
+/// step. The subscription is restarted if it ends, e.g. when sway restarts.
+/// Without sway (or `swaymsg`) the trigger does nothing.
+pub struct WorkspaceTrigger {
+    control: Sender<ControlRequest>,
+    focus: Arc<Mutex<Focus>>,
+    subscription: Option<Subscription>,
+}
+
+/// The running `swaymsg -t subscribe`, shared with the thread that restarts it.
+struct Subscription {
+    stop: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl Subscription {
+    fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(child) = self.child.lock().unwrap().as_mut() {
+            let _ = child.kill();
+        }
+    }
+}
+
+impl WorkspaceTrigger {
+    pub fn new(control: Sender<ControlRequest>) -> Self {
+        Self {
+            control,
+            focus: Arc::new(Mutex::new(Focus::default())),
+            subscription: None,
+        }
+    }
+
+    fn stop_subscription(&mut self) {
+        if let Some(subscription) = self.subscription.take() {
+            subscription.stop();
+        }
+    }
+}
+
+/// The current focus from `swaymsg -t get_tree`, or `None` outside sway.
+fn current_focus() -> Option<Focus> {
+    let output = Command::new("swaymsg")
+        .args(["-r", "-t", "get_tree"])
+        .output()
+        .inspect_err(|e| tracing::debug!("WorkspaceTrigger: could not run swaymsg: {}", e))
+        .ok()?;
+    if !output.status.success() {
+        tracing::debug!("WorkspaceTrigger: swaymsg exited with {}", output.status);
+        return None;
+    }
+    let tree: Value = serde_json::from_slice(&output.stdout).ok()?;
+    focus_from_tree(&tree)
+}
+
+/// Walk a `get_tree` reply down to the focused node, noting the output and
+/// workspace it sits in.
+fn focus_from_tree(tree: &Value) -> Option<Focus> {
+    fn walk(node: &Value, mut focus: Focus) -> Option<Focus> {
+        let name = node["name"].as_str().map(str::to_string);
+        match node["type"].as_str() {
+            Some("output") => focus.output = name,
+            Some("workspace") => focus.workspace = name,
+            _ => {}
+        }
+        if node["focused"].as_bool() == Some(true) {
+            focus.app = window_app(node);
+            return Some(focus);
+        }
+        ["nodes", "floating_nodes"]
+            .iter()
+            .filter_map(|key| node[*key].as_array())
+            .flatten()
+            .find_map(|child| walk(child, focus.clone()))
+    }
+    walk(tree, Focus::default())
+}
+
+/// app_id of a window node, falling back to the X11 class.
+fn window_app(node: &Value) -> Option<String> {
+    node["app_id"]
+        .as_str()
+        .or_else(|| node["window_properties"]["class"].as_str())
+        .map(str::to_string)
+}
+
+/// Update `focus` from one `workspace` or `window` subscription event.
+fn apply_event(focus: &mut Focus, event: &Value) {
+    let change = event["change"].as_str();
+    if let Some(current) = event.get("current").filter(|c| c.is_object()) {
+        // Workspace event.
+        if change == Some("focus") {
+            focus.output = current["output"].as_str().map(str::to_string);
+            focus.workspace = current["name"].as_str().map(str::to_string);
+            // A window focus event follows when the workspace is not empty.
+            focus.app = None;
+        }
+    } else if let Some(container) = event.get("container") {
+        // Window event.
+        match change {
+            Some("focus") => focus.app = window_app(container),
+            Some("close") if window_app(container) == focus.app => focus.app = None,
+            _ => {}
+        }
+    }
+}
+
+/// The image for `focus` on one output: the app's, else the workspace's,
+/// else `default`.
+fn image_for<'a>(config: &'a WorkspaceConfig, focus: &Focus) -> Option<&'a str> {
+    let app = focus.app.as_ref().and_then(|app| config.app.get(app));
+    let workspace = focus
+        .workspace
+        .as_ref()
+        .and_then(|name| config.name.get(name));
+    app.or(workspace)
+        .or(config.default.as_ref())
+        .map(String::as_str)
+}
+
+/// Follow sway's focus from background threads until the subscription is
+/// stopped: one runs `swaymsg -t subscribe` (again whenever it exits) and
+/// feeds its events into `focus`, the other sends `ControlRequest::Next` once
+/// the focus has settled.
+fn subscribe(focus: Arc<Mutex<Focus>>, control: Sender<ControlRequest>) -> Subscription {
+    let stop = Arc::new(AtomicBool::new(false));
+    let child = Arc::new(Mutex::new(None));
+    let (changed, changes) = mpsc::channel();
+
+    let stopped = Arc::clone(&stop);
+    std::thread::spawn(move || {
+        debounce(changes, DEBOUNCE, || {
+            if !stopped.load(Ordering::SeqCst) {
+                let _ = control.send(ControlRequest::Next);
+            }
+        })
+    });
+
+    let subscription = Subscription {
+        stop: Arc::clone(&stop),
+        child: Arc::clone(&child),
+    };
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            follow_events(&focus, &child, &stop, &changed);
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            tracing::warn!(
+                "WorkspaceTrigger: sway subscription ended; retrying in {}s",
+                RESUBSCRIBE_DELAY.as_secs()
+            );
+            std::thread::sleep(RESUBSCRIBE_DELAY);
+            // Events were missed in between; start again from the tree.
+            if let Some(current) = current_focus() {
+                *focus.lock().unwrap() = current;
+                let _ = changed.send(());
+            }
+        }
+    });
+    subscription
+}
+
+/// Run one `swaymsg -t subscribe` until it exits, applying its events to
+/// `focus` and signalling `changed` after each one.
+fn follow_events(
+    focus: &Mutex<Focus>,
+    slot: &Mutex<Option<Child>>,
+    stop: &AtomicBool,
+    changed: &Sender<()>,
+) {
+    let spawned = Command::new("swaymsg")
+        .args(["-r", "-m", "-t", "subscribe", r#"["workspace","window"]"#])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            tracing::warn!("WorkspaceTrigger: could not subscribe to sway: {}", e);
+            return;
+        }
+    };
+    let stdout = child.stdout.take();
+    *slot.lock().unwrap() = Some(child);
+    // `stop` may have been set before the child was visible to it.
+    if stop.load(Ordering::SeqCst) {
+        if let Some(child) = slot.lock().unwrap().as_mut() {
+            let _ = child.kill();
+        }
+    } else if let Some(stdout) = stdout {
+        for event in serde_json::Deserializer::from_reader(stdout).into_iter::<Value>() {
+            match event {
+                Ok(event) => {
+                    apply_event(&mut focus.lock().unwrap(), &event);
+                    let _ = changed.send(());
+                }
+                Err(e) => {
+                    tracing::debug!("WorkspaceTrigger: subscription ended: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+    if let Some(mut child) = slot.lock().unwrap().take() {
+        let _ = child.wait();
+    }
+}
+
+/// Call `fire` once per burst of `changes`, after `quiet` has passed without
+/// another one. Returns when the sending side is dropped.
+fn debounce(changes: Receiver<()>, quiet: Duration, mut fire: impl FnMut()) {
+    while changes.recv().is_ok() {
+        loop {
+            match changes.recv_timeout(quiet) {
+                Ok(()) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        fire();
+    }
+}
+
+impl Trigger for WorkspaceTrigger {
+    fn name(&self) -> &'static str {
+        crate::TriggerKind::Workspace.name()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.stop_subscription();
+        let Some(focus) = current_focus() else {
+            tracing::info!("WorkspaceTrigger: sway IPC unavailable — trigger is idle");
+            return Ok(());
+        };
+        tracing::info!(
+            "WorkspaceTrigger ready: workspace {:?} on {:?}",
+            focus.workspace,
+            focus.output
+        );
+        *self.focus.lock().unwrap() = focus;
+        self.subscription = Some(subscribe(Arc::clone(&self.focus), self.control.clone()));
+        Ok(())
+    }
+
+    fn evaluate(&mut self) -> Result<Option<TriggerResult>> {
+        if self.subscription.is_none() {
+            return Ok(None);
+        }
+
+        // ── 1. Read the workspace config ──────────────────────────────────
+        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+        let Some(workspace_map) = state.config.workspace.clone() else {
+            return Ok(None);
+        };
+        let fill_mode = state.config.base_fill_mode();
+        drop(state);
+
+        // ── 2. Take the settled focus ─────────────────────────────────────
+        let focus = self.focus.lock().unwrap().clone();
+        let Some(output) = focus.output.as_deref() else {
+            return Ok(None);
+        };
+
+        // ── 3. Resolve the focused output's entry ─────────────────────────
+        let Some(wc) = OutputResolver::matching_key(&workspace_map, output)
+            .and_then(|key| workspace_map.get(key))
+        else {
+            return Ok(None);
+        };
+        let Some(image_path) = image_for(wc, &focus) else {
+            tracing::debug!(
+                "WorkspaceTrigger: no image for {:?} and no default on output '{}'",
+                focus,
+                output
+            );
+            return Ok(None);
+        };
+
+        let resolver = OutputResolver::detect_cached()?;
+        let state = crate::APP_STATE.get().unwrap().lock().unwrap();
+        let resolved_path =
+            state.resolve_image_for_output(output, image_path, resolver.geometry(output));
+        drop(state);
+
+        // An output already showing the image is skipped by `apply`, which
+        // also sees changes made through D-Bus or `wallman set`.
+        tracing::debug!(
+            "WorkspaceTrigger: output '{}' → workspace {:?}, app {:?} → '{}'",
+            output,
+            focus.workspace,
+            focus.app,
+            resolved_path
+        );
+
+        Ok(Some(TriggerResult {
+            changes: vec![OutputChange {
+                output: output.to_string(),
+                image_path: resolved_path,
+                fill_mode,
+            }],
+        }))
+    }
+
+    fn interval(&self) -> u64 {
+        // Focus changes arrive through `ControlRequest::Next`; this only
+        // re-checks now and then.
+        60
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.stop_subscription();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_focus_follows_tree_and_events() {
+        let tree: Value = serde_json::from_str(
+            r#"{"type": "root", "name": "root", "nodes": [
+                {"type": "output", "name": "DP-1", "nodes": [
+                    {"type": "workspace", "name": "1", "nodes": [
+                        {"type": "con", "app_id": "foot", "focused": false}
+                    ]}
+                ]},
+                {"type": "output", "name": "HDMI-1", "nodes": [
+                    {"type": "workspace", "name": "2", "nodes": [], "floating_nodes": [
+                        {"type": "floating_con", "app_id": null,
+                         "window_properties": {"class": "Steam"}, "focused": true}
+                    ]}
+                ]}
+            ]}"#,
+        )
+        .unwrap();
+        let mut focus = focus_from_tree(&tree).unwrap();
+        assert_eq!(
+            focus,
+            Focus {
+                output: Some("HDMI-1".into()),
+                workspace: Some("2".into()),
+                app: Some("Steam".into()),
+            }
+        );
+
+        let event = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+        apply_event(
+            &mut focus,
+            &event(
+                r#"{"change": "focus", "current": {"name": "web", "output": "DP-1"}, "old": null}"#,
+            ),
+        );
+        assert_eq!(focus.output.as_deref(), Some("DP-1"));
+        assert_eq!(focus.workspace.as_deref(), Some("web"));
+        assert_eq!(focus.app, None);
+
+        apply_event(
+            &mut focus,
+            &event(r#"{"change": "focus", "container": {"app_id": "firefox"}}"#),
+        );
+        assert_eq!(focus.app.as_deref(), Some("firefox"));
+        apply_event(
+            &mut focus,
+            &event(r#"{"change": "title", "container": {"app_id": "foot"}}"#),
+        );
+        assert_eq!(focus.app.as_deref(), Some("firefox"));
+        apply_event(
+            &mut focus,
+            &event(r#"{"change": "close", "container": {"app_id": "firefox"}}"#),
+        );
+        assert_eq!(focus.app, None);
+    }
+
+    #[test]
+    fn test_debounce_fires_once_per_burst() {
+        let (changed, changes) = mpsc::channel();
+        let fired = std::thread::spawn(move || {
+            let mut fired = 0;
+            debounce(changes, Duration::from_millis(50), || fired += 1);
+            fired
+        });
+
+        for _ in 0..3 {
+            changed.send(()).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(200));
+        changed.send(()).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        drop(changed);
+
+        assert_eq!(fired.join().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_image_for_prefers_app_over_workspace() {
+        let config = WorkspaceConfig {
+            name: HashMap::from([("web".to_string(), "web.png".to_string())]),
+            app: HashMap::from([("mpv".to_string(), "dark.png".to_string())]),
+            default: Some("plain.png".to_string()),
+            fallback_color: None,
+        };
+        let focus = |workspace: &str, app: Option<&str>| Focus {
+            output: Some("DP-1".into()),
+            workspace: Some(workspace.into()),
+            app: app.map(Into::into),
+        };
+        assert_eq!(
+            image_for(&config, &focus("web", Some("mpv"))),
+            Some("dark.png")
+        );
+        assert_eq!(
+            image_for(&config, &focus("web", Some("foot"))),
+            Some("web.png")
+        );
+        assert_eq!(image_for(&config, &focus("3", None)), Some("plain.png"));
+
+        let no_default = WorkspaceConfig {
+            default: None,
+            ..config
+        };
+        assert_eq!(image_for(&no_default, &focus("3", None)), None);
+    }
+}
@@ -1,55 +1,895 @@
-use crate::trigger::{OutputChange, TriggerResult};
-use std::result::Result as StdResult;
+use crate::{
+    ImageMeta,
+    error::{Result, WallmanError},
+    trigger::{OutputChange, TriggerResult},
+    wallpaper::{
+        backend::{self, Backend},
+        command::CommandTemplate,
+        process::{ProcessOptions, processed_image},
+    },
+};
+use std::{
+    collections::HashMap,
+    path::Path,
+    result::Result as StdResult,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-/// Apply a batch of wallpaper changes produced by a trigger evaluation.
-pub fn apply(result: TriggerResult) -> StdResult<(), Box<dyn std::error::Error>> {
-    if result.is_empty() {
-        tracing::debug!("apply called with empty TriggerResult — nothing to do");
-        return Ok(());
+/// Executable used to draw wallpapers on each output.
+pub const BACKEND_BINARY: &str = "swaybg";
+
+/// When set, `apply` only logs the changes it would make (`--dry-run`).
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable dry-run mode for this process.
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// When set, backend processes start in a session of their own, so they
+/// outlive this process and its terminal (`wallman set` without a daemon).
+static DETACH: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable detached backend processes for this process.
+pub fn set_detach(enabled: bool) {
+    DETACH.store(enabled, Ordering::Relaxed);
+}
+
+/// A `Command` for a backend process, detached when `set_detach` asked for it.
+fn backend_command(program: &str) -> std::process::Command {
+    let mut command = std::process::Command::new(program);
+    #[cfg(unix)]
+    if DETACH.load(Ordering::Relaxed) {
+        crate::daemon::manager::detach_session(&mut command);
     }
+    command
+}
 
-    let mut last_err: Option<Box<dyn std::error::Error>> = None;
+/// Whether `value` is a `#RRGGBB` color rather than an image path.
+///
+/// Changes may carry a color in `image_path` (see `fallback_color`); swaybg
+/// draws those with `-c` instead of `-i`.
+pub fn is_solid_color(value: &str) -> bool {
+    value
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
 
-    for change in result.changes {
-        // Kill existing process for THIS output specifically before starting a new one.
-        crate::wallpaper::kill_for_output(&change.output);
+/// Drop changes to `lockedOutputs` that already show a wallpaper.
+fn drop_locked(
+    changes: Vec<OutputChange>,
+    config: &crate::Config,
+    is_drawn: impl Fn(&str) -> bool,
+) -> Vec<OutputChange> {
+    changes
+        .into_iter()
+        .filter(|change| {
+            let locked = config.is_locked(&change.output) && is_drawn(&change.output);
+            if locked {
+                tracing::debug!(
+                    "Output '{}' is locked — ignoring '{}'",
+                    change.output,
+                    change.image_path
+                );
+            }
+            !locked
+        })
+        .collect()
+}
 
-        if let Err(e) = apply_to_output(&change) {
+/// Swap a missing image for its section's `fallback_color`, when one is set.
+fn with_fallback(change: OutputChange, config: Option<&crate::Config>) -> OutputChange {
+    if is_solid_color(&change.image_path) || Path::new(&change.image_path).exists() {
+        return change;
+    }
+    match config.and_then(|c| c.fallback_color_for(&change.output)) {
+        Some(color) => {
             tracing::warn!(
-                "Failed to apply wallpaper for output '{}': {}",
+                "Image '{}' for output '{}' does not exist — showing fallback color {}",
+                change.image_path,
                 change.output,
+                color
+            );
+            OutputChange {
+                image_path: color.to_string(),
+                ..change
+            }
+        }
+        None => change,
+    }
+}
+
+/// Apply the image's `<image>.meta.toml`, if any: rotate/blur it into a
+/// processed copy and let its fill mode replace the change's.
+fn with_sidecar(mut change: OutputChange) -> OutputChange {
+    if is_solid_color(&change.image_path) {
+        return change;
+    }
+    let Some(meta) = ImageMeta::for_image(Path::new(&change.image_path)) else {
+        return change;
+    };
+    if let Some(options) = ProcessOptions::with_meta(None, &meta) {
+        match processed_image(Path::new(&change.image_path), &options) {
+            Ok(path) => change.image_path = path.to_string_lossy().to_string(),
+            Err(e) => tracing::warn!(
+                "Could not process '{}' — showing it unmodified: {}",
+                change.image_path,
                 e
+            ),
+        }
+    }
+    if let Some(mode) = meta.fill_mode {
+        change.fill_mode = mode;
+    }
+    change
+}
+
+/// Error returned when the wallpaper backend cannot be found.
+fn backend_missing() -> WallmanError {
+    WallmanError::Output(format!(
+        "{} not found in PATH — install it to let wallman display wallpapers",
+        BACKEND_BINARY
+    ))
+}
+
+/// Check that the wallpaper backend is installed before the daemon starts.
+///
+/// With `applyCommand` set, its program is checked instead of swaybg; any
+/// backend in the fallback chain being installed is enough.
+pub fn ensure_backend_available() -> Result<()> {
+    if is_dry_run() {
+        return Ok(());
+    }
+    let chain = crate::APP_STATE
+        .get()
+        .map(|state| backend::chain(&state.lock().unwrap().config));
+    if let Some(chain) = chain.filter(|chain| chain.len() > 1 || chain[0] != backend::SWAYBG) {
+        let installed = |program: &str| {
+            if program.contains('/') {
+                Path::new(program).is_file()
+            } else {
+                crate::wallpaper::find_in_path(program).is_some()
+            }
+        };
+        for entry in &chain {
+            let found = match Backend::parse(entry)? {
+                Backend::Swaybg => installed(BACKEND_BINARY),
+                Backend::Command(command) => installed(&command.program),
+                #[cfg(feature = "portal")]
+                Backend::Portal => crate::wallpaper::portal::is_available(),
+            };
+            if found {
+                return Ok(());
+            }
+            tracing::warn!("Backend '{}' is not available", entry);
+        }
+        return Err(WallmanError::Output(format!(
+            "none of the configured backends are installed ({})",
+            chain.join(", ")
+        )));
+    }
+    match crate::wallpaper::find_in_path(BACKEND_BINARY) {
+        Some(path) => {
+            tracing::debug!("Using wallpaper backend at {}", path.display());
+            Ok(())
+        }
+        None => Err(backend_missing()),
+    }
+}
+
+/// What `apply` did on each output: drawn, or the error that stopped it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ApplyReport {
+    /// Requested outputs in request order, then outputs that had to be
+    /// redrawn because they shared a swaybg with one of them.
+    pub outputs: Vec<(String, StdResult<(), String>)>,
+}
+
+impl ApplyReport {
+    pub fn is_ok(&self) -> bool {
+        self.outputs.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Outputs that show their wallpaper.
+    pub fn succeeded(&self) -> impl Iterator<Item = &str> {
+        self.outputs
+            .iter()
+            .filter(|(_, result)| result.is_ok())
+            .map(|(output, _)| output.as_str())
+    }
+
+    /// Outputs that failed, with why.
+    pub fn failed(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.outputs
+            .iter()
+            .filter_map(|(output, result)| Some((output.as_str(), result.as_ref().err()?.as_str())))
+    }
+
+    /// Collapse into one error listing every failed output, for callers that
+    /// only need pass/fail.
+    pub fn into_result(self) -> Result<()> {
+        if self.is_ok() {
+            return Ok(());
+        }
+        let failures: Vec<String> = self
+            .failed()
+            .map(|(output, error)| format!("{}: {}", output, error))
+            .collect();
+        Err(WallmanError::Output(failures.join("; ")))
+    }
+
+    /// Log the outcome on each output.
+    pub fn log(&self) {
+        for (output, result) in &self.outputs {
+            match result {
+                Ok(()) => tracing::debug!("Output '{}' updated", output),
+                Err(e) => tracing::error!("Failed to apply wallpaper to '{}': {}", output, e),
+            }
+        }
+    }
+}
+
+/// `apply` for async callers: the process management runs on tokio's
+/// blocking pool so the runtime's worker threads are never held up.
+#[cfg(feature = "async")]
+pub async fn apply_async(result: TriggerResult) -> Result<ApplyReport> {
+    tokio::task::spawn_blocking(move || apply(result))
+        .await
+        .map_err(|e| WallmanError::Output(format!("apply task failed: {}", e)))?
+}
+
+/// Apply a batch of wallpaper changes produced by a trigger evaluation.
+///
+/// Outputs showing the same image share one swaybg process; each process is
+/// started on its own thread so all monitors flip together. Every output is
+/// tried; the report says which ones failed. `Err` is only returned when
+/// nothing could be attempted (e.g. an invalid backend).
+pub fn apply(result: TriggerResult) -> Result<ApplyReport> {
+    if result.is_empty() {
+        tracing::debug!("apply called with empty TriggerResult — nothing to do");
+        return Ok(ApplyReport::default());
+    }
+
+    let (result, on_change, colors, grace, backend, fades) = match crate::APP_STATE.get() {
+        Some(state) => {
+            let state = state.lock().unwrap();
+            let changes: Vec<OutputChange> =
+                drop_locked(result.changes, &state.config, crate::wallpaper::is_tracked)
+                    .into_iter()
+                    .map(|change| with_fallback(change, Some(&state.config)))
+                    .collect();
+            let fades: HashMap<String, u64> = changes
+                .iter()
+                .map(|c| (c.output.clone(), state.config.fade_ms_for(&c.output)))
+                .collect();
+            (
+                TriggerResult { changes },
+                state.config.on_change.clone(),
+                state.config.colors.clone(),
+                state.config.swap_grace_ms.unwrap_or(0),
+                backend::active(&state.config)?,
+                fades,
+            )
+        }
+        None => (result, None, None, 0, Backend::Swaybg, HashMap::new()),
+    };
+
+    if is_dry_run() {
+        for change in &result.changes {
+            tracing::info!(
+                "[dry-run] would apply '{}' to output '{}'",
+                change.image_path,
+                change.output
             );
-            last_err = Some(e);
         }
+        return Ok(ApplyReport {
+            outputs: result
+                .changes
+                .into_iter()
+                .map(|change| (change.output, Ok(())))
+                .collect(),
+        });
     }
-    tracing::info!("Prefinalize the wallpaper aplication");
-    if let Some(e) = last_err {
-        return Err(e);
+
+    let changes: Vec<OutputChange> = result.changes.into_iter().map(with_sidecar).collect();
+
+    // Leave swaybg alone where it already shows the image (avoids a black flash).
+    let pending: Vec<&OutputChange> = changes
+        .iter()
+        .filter(|change| {
+            let showing =
+                crate::wallpaper::is_showing(&change.output, &change.image_path, &change.fill_mode);
+            if showing {
+                tracing::debug!(
+                    "Output '{}' already shows '{}' — skipping respawn",
+                    change.output,
+                    change.image_path
+                );
+            }
+            !showing
+        })
+        .collect();
+
+    // Free the outputs first. Outputs that shared a swaybg with one of them
+    // but are not changing lose their wallpaper too, so they are redrawn.
+    // With `swapGraceMs` the old processes keep drawing until the new ones are up.
+    let mut redraw: Vec<OutputChange> = Vec::new();
+    let mut retiring = Vec::new();
+    for change in &pending {
+        let Some(old) = crate::wallpaper::take_for_output(&change.output) else {
+            continue;
+        };
+        for orphan in old.other_outputs(&change.output) {
+            let known = pending.iter().any(|c| c.output == orphan.output)
+                || redraw.iter().any(|c| c.output == orphan.output);
+            if !known {
+                redraw.push(orphan);
+            }
+        }
+        if grace > 0 {
+            retiring.push(old);
+        } else {
+            old.stop();
+        }
     }
 
-    Ok(())
+    // Decided before spawning, which updates the current images.
+    let changed = changed_outputs(&pending, crate::wallpaper::current_image);
+    // Respawned and redrawn outputs already showed their image; fading it in
+    // again would only flash.
+    let fades = fades_for_changed(fades, &changed);
+
+    let mut applied: Vec<&OutputChange> = Vec::new();
+    let mut outcomes: HashMap<&str, StdResult<(), String>> = HashMap::new();
+
+    let groups = group_by_image(pending.iter().copied().chain(&redraw));
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = groups
+            .iter()
+            .map(|group| {
+                let (backend, fades) = (&backend, &fades);
+                scope.spawn(move || match backend {
+                    Backend::Command(command) => spawn_with_command(command, group, fades),
+                    Backend::Swaybg => spawn_for_group(group),
+                    #[cfg(feature = "portal")]
+                    Backend::Portal => set_with_portal(group),
+                })
+            })
+            .collect();
+
+        for (handle, group) in handles.into_iter().zip(&groups) {
+            let outcome = match handle.join() {
+                // Redrawn and respawned outputs keep their wallpaper, so they
+                // are not reported as changed.
+                Ok(Ok(())) => {
+                    applied.extend(group.iter().filter(|c| changed.contains(&c.output)));
+                    Ok(())
+                }
+                Ok(Err(e)) => {
+                    backend::record_failure();
+                    Err(e)
+                }
+                Err(_) => Err("apply thread panicked".to_string()),
+            };
+            for change in group {
+                outcomes.insert(&change.output, outcome.clone());
+            }
+        }
+    });
+
+    if !retiring.is_empty() {
+        std::thread::sleep(std::time::Duration::from_millis(grace));
+        for old in retiring {
+            old.stop();
+        }
+    }
+
+    crate::daemon::status::record_applied(&applied);
+
+    if let Some(script) = &on_change {
+        for change in &applied {
+            crate::wallpaper::hook::run_on_change(script, change);
+        }
+    }
+
+    #[cfg(feature = "dbus")]
+    for change in &applied {
+        crate::daemon::dbus::emit_wallpaper_changed(change);
+    }
+
+    if let Some(colors) = colors.filter(|c| c.enabled) {
+        let primary = crate::OutputResolver::detect_cached()
+            .ok()
+            .and_then(|resolver| resolver.primary().map(str::to_string));
+        if let Some(source) = palette_source(&applied, primary.as_deref()) {
+            crate::wallpaper::colors::spawn_palette_export(colors, source.image_path.clone());
+        }
+    }
+
+    // Outputs that already showed their image were left alone, which counts as done.
+    let outputs = changes
+        .iter()
+        .chain(&redraw)
+        .map(|change| {
+            let outcome = outcomes.remove(change.output.as_str()).unwrap_or(Ok(()));
+            (change.output.clone(), outcome)
+        })
+        .collect();
+    Ok(ApplyReport { outputs })
+}
+
+/// Outputs in `pending` whose image differs from the one `current` says they
+/// last showed. Only these count as changed: hooks, D-Bus signals and
+/// transitions are for them, not for outputs that are merely redrawn.
+fn changed_outputs(
+    pending: &[&OutputChange],
+    current: impl Fn(&str) -> Option<String>,
+) -> Vec<String> {
+    pending
+        .iter()
+        .filter(|change| current(&change.output).as_deref() != Some(change.image_path.as_str()))
+        .map(|change| change.output.clone())
+        .collect()
+}
+
+/// The change the palette is taken from: the primary output's when it got a
+/// new image, else the first applied output that shows an image.
+fn palette_source<'a>(
+    applied: &[&'a OutputChange],
+    primary: Option<&str>,
+) -> Option<&'a OutputChange> {
+    let mut images = applied.iter().filter(|c| !is_solid_color(&c.image_path));
+    let first = images.clone().next();
+    images
+        .find(|c| Some(c.output.as_str()) == primary)
+        .or(first)
+        .copied()
+}
+
+/// Keep the configured fade only for outputs in `changed`; the others are
+/// drawn without one.
+fn fades_for_changed(mut fades: HashMap<String, u64>, changed: &[String]) -> HashMap<String, u64> {
+    fades.retain(|output, _| changed.contains(output));
+    fades
+}
+
+/// Group changes that show the same image in the same fill mode so one
+/// swaybg can draw them all.
+///
+/// Groups keep the order in which their image first appears.
+fn group_by_image<'a>(
+    changes: impl Iterator<Item = &'a OutputChange>,
+) -> Vec<Vec<&'a OutputChange>> {
+    let mut groups: Vec<Vec<&OutputChange>> = Vec::new();
+    for change in changes {
+        match groups.iter_mut().find(|group| {
+            group[0].image_path == change.image_path && group[0].fill_mode == change.fill_mode
+        }) {
+            Some(group) => group.push(change),
+            None => groups.push(vec![change]),
+        }
+    }
+    groups
 }
 
-/// Apply a wallpaper to a single output using swaybg.
+/// swaybg arguments drawing each change: an `-o <output> -i <image> -m <mode>`
+/// group per output (`-o <output> -c <color>` for solid colors), all in one
+/// invocation.
+fn swaybg_args(group: &[&OutputChange], mode: &str) -> Vec<String> {
+    group
+        .iter()
+        .flat_map(|change| {
+            let mut args = vec!["-o".to_string(), change.output.clone()];
+            if is_solid_color(&change.image_path) {
+                args.extend(["-c".to_string(), change.image_path.clone()]);
+            } else {
+                args.extend([
+                    "-i".to_string(),
+                    change.image_path.clone(),
+                    "-m".to_string(),
+                    mode.to_string(),
+                ]);
+            }
+            args
+        })
+        .collect()
+}
+
+/// Start one swaybg for a group of outputs sharing an image and fill mode.
 ///
-/// Spawns `swaybg -o <output> -i <image> -m fill` as a background process.
-fn apply_to_output(change: &OutputChange) -> StdResult<(), Box<dyn std::error::Error>> {
+/// Errors are stringified so the result can cross the thread boundary.
+fn spawn_for_group(group: &[&OutputChange]) -> StdResult<(), String> {
+    let outputs: Vec<String> = group.iter().map(|c| c.output.clone()).collect();
+    let image_path = group[0].image_path.clone();
+    let fill_mode = group[0].fill_mode.clone();
     tracing::info!(
-        "Applying wallpaper '{}' to output '{}'",
-        change.image_path,
-        change.output
+        "Applying wallpaper '{}' to output(s) '{}'",
+        image_path,
+        outputs.join("', '")
     );
 
     // Use spawn() instead of output() so it doesn't block the daemon.
-    let child = std::process::Command::new("swaybg")
-        .args(&["-o", &change.output, "-i", &change.image_path, "-m", "fill"])
+    let child = backend_command(BACKEND_BINARY)
+        .args(swaybg_args(group, fill_mode.as_swaybg_mode()))
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
-        .spawn()?;
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => backend_missing(),
+            _ => e.into(),
+        })
+        .map_err(|e| {
+            tracing::warn!(
+                "Failed to apply wallpaper for output(s) '{}': {}",
+                outputs.join("', '"),
+                e
+            );
+            e.to_string()
+        })?;
 
-    // Register the child so we can kill it later when the wallpaper changes for this output.
-    crate::wallpaper::register_process(change.output.clone(), child);
+    // Register the child so we can kill it later when the wallpaper changes on any of its outputs.
+    crate::wallpaper::register_process(outputs, child, image_path, fill_mode);
+    Ok(())
+}
 
+/// Hand the group's image to the desktop portal.
+///
+/// The portal sets one wallpaper for every monitor, so with several images
+/// the group applied last wins.
+#[cfg(feature = "portal")]
+fn set_with_portal(group: &[&OutputChange]) -> StdResult<(), String> {
+    let image_path = &group[0].image_path;
+    if is_solid_color(image_path) {
+        return Err("the portal backend cannot show solid colors".to_string());
+    }
+    tracing::info!(
+        "Setting wallpaper '{}' through the desktop portal",
+        image_path
+    );
+    crate::wallpaper::portal::set_wallpaper(image_path).map_err(|e| e.to_string())?;
+    let outputs: Vec<String> = group.iter().map(|c| c.output.clone()).collect();
+    crate::wallpaper::record_current_image(&outputs, image_path);
     Ok(())
 }
+
+/// Run `applyCommand` once per output in the group, tracking each child
+/// like a swaybg process. Outputs missing from `fades` (redraws) switch
+/// instantly.
+fn spawn_with_command(
+    command: &CommandTemplate,
+    group: &[&OutputChange],
+    fades: &HashMap<String, u64>,
+) -> StdResult<(), String> {
+    let mut errors = Vec::new();
+    for change in group {
+        tracing::info!(
+            "Applying wallpaper '{}' to output '{}' with '{}'",
+            change.image_path,
+            change.output,
+            command.program
+        );
+        let spawned = backend_command(&command.program)
+            .args(command.expand(
+                &change.output,
+                &change.image_path,
+                change.fill_mode.name(),
+                fades.get(&change.output).copied().unwrap_or(0),
+            ))
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(child) => crate::wallpaper::register_process(
+                vec![change.output.clone()],
+                child,
+                change.image_path.clone(),
+                change.fill_mode.clone(),
+            ),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to run applyCommand '{}' for output '{}': {}",
+                    command.program,
+                    change.output,
+                    e
+                );
+                errors.push(format!("{}: {}", command.program, e));
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FillMode;
+
+    fn change(output: &str, image: &str) -> OutputChange {
+        OutputChange {
+            output: output.to_string(),
+            image_path: image.to_string(),
+            fill_mode: FillMode::Fill,
+        }
+    }
+
+    #[test]
+    fn test_same_image_outputs_share_one_swaybg() {
+        let changes = [
+            change("DP-1", "/a.png"),
+            change("DP-2", "/b.png"),
+            change("HDMI-A-1", "/a.png"),
+        ];
+        let groups = group_by_image(changes.iter());
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            swaybg_args(&groups[0], "fill"),
+            [
+                "-o", "DP-1", "-i", "/a.png", "-m", "fill", "-o", "HDMI-A-1", "-i", "/a.png", "-m",
+                "fill"
+            ]
+        );
+        assert_eq!(
+            swaybg_args(&groups[1], "fill"),
+            ["-o", "DP-2", "-i", "/b.png", "-m", "fill"]
+        );
+    }
+
+    #[test]
+    fn test_report_keeps_per_output_outcomes() {
+        let report = ApplyReport {
+            outputs: vec![
+                ("DP-1".to_string(), Ok(())),
+                ("HDMI-A-1".to_string(), Err("swaybg not found".to_string())),
+            ],
+        };
+        assert!(!report.is_ok());
+        assert_eq!(report.succeeded().collect::<Vec<_>>(), ["DP-1"]);
+        assert_eq!(
+            report.failed().collect::<Vec<_>>(),
+            [("HDMI-A-1", "swaybg not found")]
+        );
+        assert_eq!(
+            report.into_result().unwrap_err().to_string(),
+            WallmanError::Output("HDMI-A-1: swaybg not found".to_string()).to_string()
+        );
+        assert!(ApplyReport::default().into_result().is_ok());
+    }
+
+    #[test]
+    fn test_missing_image_falls_back_to_section_color() {
+        let config: crate::Config = toml::from_str(
+            r##"
+                [timeConfig."*"]
+                day = "day.png"
+                night = "night.png"
+                fallback_color = "#1e1e2e"
+
+                [timeConfig.DP-2]
+                day = "day.png"
+                night = "night.png"
+            "##,
+        )
+        .unwrap();
+
+        let missing = with_fallback(change("DP-1", "/no/such/day.png"), Some(&config));
+        assert_eq!(missing.image_path, "#1e1e2e");
+        assert_eq!(
+            swaybg_args(&[&missing], "fill"),
+            ["-o", "DP-1", "-c", "#1e1e2e"]
+        );
+
+        // Outputs without a fallback keep the path; existing images are untouched.
+        let no_fallback = with_fallback(change("DP-2", "/no/such/day.png"), Some(&config));
+        assert_eq!(no_fallback.image_path, "/no/such/day.png");
+        let exe = std::env::current_exe()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(
+            with_fallback(change("DP-1", &exe), Some(&config)).image_path,
+            exe
+        );
+
+        assert!(is_solid_color("#A0b1C2"));
+        assert!(!is_solid_color("1e1e2e"));
+        assert!(!is_solid_color("#1e1e2"));
+    }
+
+    #[test]
+    fn test_only_outputs_with_a_new_image_count_as_changed() {
+        let respawned = change("DP-1", "/a.png");
+        let switched = change("DP-2", "/b.png");
+        let first = change("HDMI-1", "/a.png");
+        let current = |output: &str| match output {
+            "DP-1" | "DP-2" => Some("/a.png".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            changed_outputs(&[&respawned, &switched, &first], current),
+            ["DP-2", "HDMI-1"]
+        );
+    }
+
+    #[test]
+    fn test_palette_follows_primary_output() {
+        let left = change("DP-1", "/left.png");
+        let right = change("DP-2", "/right.png");
+        let plain = change("HDMI-1", "#000000");
+        let applied = [&left, &right, &plain];
+
+        let source = |primary| palette_source(&applied, primary).map(|c| c.output.as_str());
+        assert_eq!(source(Some("DP-2")), Some("DP-2"));
+        assert_eq!(source(Some("HDMI-1")), Some("DP-1"));
+        assert_eq!(source(None), Some("DP-1"));
+        assert_eq!(palette_source(&[&plain], Some("HDMI-1")), None);
+    }
+
+    #[test]
+    fn test_only_changed_outputs_fade() {
+        let respawned = change("DP-1", "/a.png");
+        let switched = change("DP-2", "/b.png");
+        let current = |_: &str| Some("/a.png".to_string());
+        let changed = changed_outputs(&[&respawned, &switched], current);
+
+        let fades = HashMap::from([("DP-1".to_string(), 800), ("DP-2".to_string(), 800)]);
+        let fades = fades_for_changed(fades, &changed);
+        assert_eq!(fades.get("DP-2"), Some(&800));
+        assert_eq!(fades.get("DP-1"), None);
+
+        let command = CommandTemplate::parse("mybg {output} {image} {fade}").unwrap();
+        let fade = |output: &str| fades.get(output).copied().unwrap_or(0);
+        assert_eq!(
+            command.expand("DP-1", "/a.png", "fill", fade("DP-1"))[2],
+            "0"
+        );
+        assert_eq!(
+            command.expand("DP-2", "/b.png", "fill", fade("DP-2"))[2],
+            "800"
+        );
+    }
+
+    #[test]
+    fn test_locked_outputs_only_get_their_first_wallpaper() {
+        let config = crate::Config {
+            locked_outputs: Some(vec!["DP-2".to_string()]),
+            ..crate::Config::default()
+        };
+        let changes = || vec![change("DP-1", "/a.png"), change("DP-2", "/a.png")];
+
+        // Nothing drawn yet: the locked output gets its initial wallpaper.
+        let kept = drop_locked(changes(), &config, |_| false);
+        assert_eq!(kept.len(), 2);
+
+        // Once drawn, only the unlocked output changes.
+        let kept = drop_locked(changes(), &config, |_| true);
+        let outputs: Vec<&str> = kept.iter().map(|c| c.output.as_str()).collect();
+        assert_eq!(outputs, ["DP-1"]);
+    }
+
+    #[test]
+    fn test_configured_fill_mode_reaches_swaybg() {
+        let config: crate::Config = toml::from_str(
+            r#"
+                [background."*"]
+                image = "/a.png"
+                fillMode = "crop"
+
+                [background.DP-2]
+                image = "/a.png"
+            "#,
+        )
+        .unwrap();
+        let resolver =
+            crate::OutputResolver::from_outputs(vec!["DP-1".to_string(), "DP-2".to_string()]);
+        let changes: Vec<OutputChange> = resolver
+            .resolve_map(config.background.as_ref().unwrap())
+            .into_iter()
+            .map(|(output, background)| OutputChange {
+                output,
+                image_path: background.image.clone().unwrap(),
+                fill_mode: config.fill_mode_for(&background),
+            })
+            .collect();
+
+        // Same image, different modes: one swaybg each.
+        let groups = group_by_image(changes.iter());
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            swaybg_args(&groups[0], groups[0][0].fill_mode.as_swaybg_mode()),
+            ["-o", "DP-1", "-i", "/a.png", "-m", "center"]
+        );
+        assert_eq!(
+            swaybg_args(&groups[1], groups[1][0].fill_mode.as_swaybg_mode()),
+            ["-o", "DP-2", "-i", "/a.png", "-m", "fill"]
+        );
+
+        // Other sections follow defaultFillMode.
+        assert_eq!(config.base_fill_mode(), FillMode::Fill);
+        let config = crate::Config {
+            default_fill_mode: Some(FillMode::Scale),
+            ..config
+        };
+        assert_eq!(config.base_fill_mode(), FillMode::Scale);
+    }
+
+    #[test]
+    fn test_sidecar_fill_mode_reaches_swaybg() {
+        let dir = std::env::temp_dir().join("wallman_test_apply_sidecar");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("pine.png");
+        image::RgbImage::new(2, 2).save(&image).unwrap();
+        std::fs::write(ImageMeta::sidecar_path(&image), "fill_mode = \"crop\"\n").unwrap();
+        let image = image.to_string_lossy().to_string();
+
+        let shown = with_sidecar(change("DP-1", &image));
+        // No rotate/blur: the original file is shown, with the sidecar's mode.
+        assert_eq!(shown.image_path, image);
+        assert_eq!(shown.fill_mode, FillMode::Crop);
+        assert_eq!(
+            swaybg_args(&[&shown], shown.fill_mode.as_swaybg_mode()),
+            ["-o", "DP-1", "-i", image.as_str(), "-m", "center"]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_paths_with_spaces_and_unicode_stay_single_arguments() {
+        let dir = std::env::temp_dir().join("wallman test apply «paths»");
+        let _ = std::fs::remove_dir_all(&dir);
+        let images = dir.join("ciel étoilé").join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        let name = "nuit 🌙 \"d'été\" $(id).png";
+        image::RgbImage::from_pixel(2, 2, image::Rgb([200, 40, 40]))
+            .save(images.join(name))
+            .unwrap();
+        std::fs::write(
+            ImageMeta::sidecar_path(&images.join(name)),
+            "fill_mode = \"crop\"\n",
+        )
+        .unwrap();
+
+        let config: crate::Config =
+            toml::from_str(&format!("[background.DP-1]\nimage = {:?}\n", name)).unwrap();
+        let pool = dir.join("ciel étoilé").to_string_lossy().to_string();
+        let state = crate::AppState::new(
+            config.clone(),
+            dir.join("config.toml").to_string_lossy().to_string(),
+            Some(pool),
+            true,
+        )
+        .unwrap();
+        let configured = config.background.as_ref().unwrap()["DP-1"]
+            .image
+            .clone()
+            .unwrap();
+        let resolved = state.resolve_image_for_output("DP-1", &configured, None);
+        assert_eq!(resolved, images.join(name).to_string_lossy());
+
+        // The sidecar next to the awkward name is found and the path stays one word.
+        let shown = with_sidecar(change("DP-1", &resolved));
+        assert_eq!(
+            swaybg_args(&[&shown], shown.fill_mode.as_swaybg_mode()),
+            ["-o", "DP-1", "-i", resolved.as_str(), "-m", "center"]
+        );
+        let command = CommandTemplate::parse("mybg {output} {image}").unwrap();
+        assert_eq!(
+            command.expand("DP-1", &resolved, "crop", 0),
+            ["DP-1", &resolved]
+        );
+        assert!(crate::wallpaper::colors::extract_palette(Path::new(&resolved), 1).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    process::ExitStatus,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{Config, Result, wallpaper::command::CommandTemplate};
+
+/// Backend name that selects the built-in swaybg invocation.
+pub const SWAYBG: &str = "swaybg";
+
+/// Backend name that selects the XDG desktop portal (`--features portal`).
+pub const PORTAL: &str = "portal";
+
+/// Consecutive immediate failures before the next backend in the chain is used.
+pub const MAX_FAILURES: u32 = 3;
+
+/// A backend process that exits sooner than this after starting counts as a failure.
+pub const IMMEDIATE_EXIT: Duration = Duration::from_secs(5);
+
+/// How wallpapers are drawn: swaybg, an `applyCommand`-style template, or
+/// the desktop portal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backend {
+    Swaybg,
+    Command(CommandTemplate),
+    #[cfg(feature = "portal")]
+    Portal,
+}
+
+impl Backend {
+    /// `"swaybg"`, `"portal"` or a command template.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim() {
+            SWAYBG => Ok(Backend::Swaybg),
+            #[cfg(feature = "portal")]
+            PORTAL => Ok(Backend::Portal),
+            #[cfg(not(feature = "portal"))]
+            PORTAL => Err(crate::WallmanError::Config(
+                "the portal backend needs wallman built with `--features portal`".to_string(),
+            )),
+            _ => CommandTemplate::parse(value).map(Backend::Command),
+        }
+    }
+}
+
+/// Backends to try in order: `applyCommand` (else `backend`, else swaybg),
+/// then `backendFallbacks`.
+///
+/// swaybg is the implicit last resort when no fallbacks are configured.
+pub fn chain(config: &Config) -> Vec<String> {
+    let mut chain = vec![
+        config
+            .apply_command
+            .clone()
+            .or_else(|| config.backend.clone())
+            .unwrap_or_else(|| SWAYBG.to_string()),
+    ];
+    match &config.backend_fallbacks {
+        Some(fallbacks) => chain.extend(fallbacks.iter().cloned()),
+        None => chain.push(SWAYBG.to_string()),
+    }
+    chain.dedup();
+    chain
+}
+
+/// Failure count for the backend currently in use.
+#[derive(Debug, Default)]
+pub struct BackendHealth {
+    /// Index into the backend chain.
+    active: usize,
+    failures: u32,
+}
+
+impl BackendHealth {
+    /// Count a failure; after `MAX_FAILURES` in a row, move to the next
+    /// backend of a chain `chain_len` long. Returns true when it switched.
+    pub fn record_failure(&mut self, chain_len: usize) -> bool {
+        self.failures += 1;
+        if self.failures < MAX_FAILURES || self.active + 1 >= chain_len {
+            return false;
+        }
+        self.active += 1;
+        self.failures = 0;
+        true
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+    }
+
+    /// The backend in use, clamped to the chain.
+    pub fn active<'a>(&self, chain: &'a [String]) -> &'a str {
+        &chain[self.active.min(chain.len() - 1)]
+    }
+}
+
+static HEALTH: Mutex<BackendHealth> = Mutex::new(BackendHealth {
+    active: 0,
+    failures: 0,
+});
+
+/// The backend to draw with for `config`.
+pub fn active(config: &Config) -> Result<Backend> {
+    let chain = chain(config);
+    Backend::parse(HEALTH.lock().unwrap().active(&chain))
+}
+
+/// Record that the backend failed to start or exited right away.
+pub fn record_failure() {
+    let Some(chain) = crate::APP_STATE
+        .get()
+        .map(|state| chain(&state.lock().unwrap().config))
+    else {
+        return;
+    };
+    let mut health = HEALTH.lock().unwrap();
+    let failed = health.active(&chain).to_string();
+    if health.record_failure(chain.len()) {
+        let next = health.active(&chain);
+        tracing::error!(
+            "Backend '{}' failed {} times in a row — falling back to '{}'",
+            failed,
+            MAX_FAILURES,
+            next
+        );
+        crate::daemon::status::record_backend(next);
+    }
+}
+
+/// Record that a backend process outlived `IMMEDIATE_EXIT`.
+pub fn record_success() {
+    HEALTH.lock().unwrap().record_success();
+}
+
+/// Whether a process started at `started` exited too soon to count as working.
+pub fn exited_immediately(started: Instant) -> bool {
+    started.elapsed() < IMMEDIATE_EXIT
+}
+
+/// Whether a process started at `started` failed (non-zero exit or a
+/// signal) too soon to count as working. Exiting successfully never does.
+pub fn failed_immediately(started: Instant, status: &ExitStatus) -> bool {
+    !status.success() && exited_immediately(started)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_spawn_failures_fall_back_along_the_chain() {
+        let config = Config {
+            apply_command: Some("wallman-missing-backend {output} {image}".to_string()),
+            backend_fallbacks: Some(vec![
+                "other {output} {image}".to_string(),
+                SWAYBG.to_string(),
+            ]),
+            ..Config::default()
+        };
+        let chain = chain(&config);
+        assert_eq!(chain.len(), 3);
+
+        let mut health = BackendHealth::default();
+        let spawn = |health: &BackendHealth| match Backend::parse(health.active(&chain)).unwrap() {
+            Backend::Command(command) => std::process::Command::new(&command.program)
+                .spawn()
+                .map(|_| ()),
+            _ => Ok(()),
+        };
+
+        // The missing binary fails every time until the chain moves on.
+        for attempt in 1..=MAX_FAILURES {
+            assert!(spawn(&health).is_err());
+            assert_eq!(health.record_failure(chain.len()), attempt == MAX_FAILURES);
+        }
+        assert_eq!(health.active(&chain), "other {output} {image}");
+
+        // A success resets the count; the last backend is never given up on.
+        health.record_success();
+        for _ in 0..MAX_FAILURES {
+            health.record_failure(chain.len());
+        }
+        assert_eq!(health.active(&chain), SWAYBG);
+        for _ in 0..MAX_FAILURES * 2 {
+            assert!(!health.record_failure(chain.len()));
+        }
+        assert_eq!(
+            Backend::parse(health.active(&chain)).unwrap(),
+            Backend::Swaybg
+        );
+    }
+
+    #[test]
+    fn test_backend_key_selects_the_primary_backend() {
+        let config = Config {
+            backend: Some(PORTAL.to_string()),
+            ..Config::default()
+        };
+        assert_eq!(chain(&config), [PORTAL, SWAYBG]);
+        #[cfg(not(feature = "portal"))]
+        assert!(Backend::parse(PORTAL).is_err());
+        #[cfg(feature = "portal")]
+        assert_eq!(Backend::parse(PORTAL).unwrap(), Backend::Portal);
+    }
+}
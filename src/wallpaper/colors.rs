@@ -0,0 +1,175 @@
+use std::{fs, path::Path, result::Result as StdResult, sync::Mutex, thread::JoinHandle};
+
+use image::Rgb;
+
+use crate::{ColorsConfig, constants::config_folder};
+
+/// Number of colors written to the palette.
+const PALETTE_SIZE: usize = 8;
+
+/// Longest edge of the thumbnail sampled for the palette.
+const SAMPLE_EDGE: u32 = 64;
+
+/// The most recent export, for `wait_for_palette_export`.
+static LAST_EXPORT: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// Extract and write the palette for `image_path` on a background thread.
+pub fn spawn_palette_export(colors: ColorsConfig, image_path: String) {
+    let handle = std::thread::spawn(move || {
+        if let Err(e) = export_palette(&colors, &image_path) {
+            tracing::warn!("Failed to export palette for '{}': {}", image_path, e);
+        }
+    });
+    *LAST_EXPORT.lock().unwrap() = Some(handle);
+}
+
+/// Block until the most recent palette export has been written. Short-lived
+/// callers use this so the export is not cut off when the process exits.
+pub fn wait_for_palette_export() {
+    let handle = LAST_EXPORT.lock().unwrap().take();
+    if let Some(handle) = handle {
+        let _ = handle.join();
+    }
+}
+
+/// Write `colors.json` (and optionally `colors.css`) into the config folder.
+fn export_palette(
+    colors: &ColorsConfig,
+    image_path: &str,
+) -> StdResult<(), Box<dyn std::error::Error>> {
+    let palette = extract_palette(Path::new(image_path), PALETTE_SIZE)?;
+    let hex: Vec<String> = palette.iter().map(to_hex).collect();
+
+    let dir = config_folder();
+    fs::create_dir_all(&dir)?;
+
+    let named: serde_json::Map<String, serde_json::Value> = hex
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (format!("color{}", i), serde_json::Value::from(c.clone())))
+        .collect();
+    let json = serde_json::json!({
+        "wallpaper": image_path,
+        "colors": named,
+    });
+    fs::write(
+        dir.join("colors.json"),
+        serde_json::to_string_pretty(&json)?,
+    )?;
+
+    if colors.css {
+        let mut css = String::from(":root {\n");
+        for (i, c) in hex.iter().enumerate() {
+            css.push_str(&format!("  --color{}: {};\n", i, c));
+        }
+        css.push_str("}\n");
+        fs::write(dir.join("colors.css"), css)?;
+    }
+
+    tracing::info!("Palette for '{}' written to {}", image_path, dir.display());
+    Ok(())
+}
+
+/// Extract up to `count` dominant colors using median cut over a downscaled image.
+///
+/// Colors are returned darkest first, which mirrors the pywal convention of
+/// `color0` being the background shade.
+pub fn extract_palette(
+    path: &Path,
+    count: usize,
+) -> StdResult<Vec<Rgb<u8>>, Box<dyn std::error::Error>> {
+    let img = image::open(path)?
+        .thumbnail(SAMPLE_EDGE, SAMPLE_EDGE)
+        .to_rgb8();
+    let pixels: Vec<[u8; 3]> = img.pixels().map(|p| p.0).collect();
+    Ok(median_cut(pixels, count))
+}
+
+/// Split the pixel set along its widest channel until `count` boxes exist.
+fn median_cut(pixels: Vec<[u8; 3]>, count: usize) -> Vec<Rgb<u8>> {
+    if pixels.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        // Pick the box with the widest single-channel range.
+        let (idx, channel, range) = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let (c, r) = widest_channel(b);
+                (i, c, r)
+            })
+            .max_by_key(|&(_, _, r)| r)
+            .unwrap();
+
+        if range == 0 {
+            break; // Every box is a single color; nothing left to split.
+        }
+
+        let mut bucket = boxes.swap_remove(idx);
+        bucket.sort_unstable_by_key(|p| p[channel]);
+        let upper = bucket.split_off(bucket.len() / 2);
+        boxes.push(bucket);
+        boxes.push(upper);
+    }
+
+    let mut palette: Vec<Rgb<u8>> = boxes.iter().map(|b| average(b)).collect();
+    palette.sort_by_key(luminance);
+    palette
+}
+
+/// Return the channel index with the largest value range and that range.
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let min = pixels.iter().map(|p| p[c]).min().unwrap_or(0);
+            let max = pixels.iter().map(|p| p[c]).max().unwrap_or(0);
+            (c, max - min)
+        })
+        .max_by_key(|&(_, r)| r)
+        .unwrap()
+}
+
+fn average(pixels: &[[u8; 3]]) -> Rgb<u8> {
+    let n = pixels.len().max(1) as u64;
+    let mut sum = [0u64; 3];
+    for p in pixels {
+        for c in 0..3 {
+            sum[c] += p[c] as u64;
+        }
+    }
+    Rgb([(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8])
+}
+
+fn luminance(c: &Rgb<u8>) -> u32 {
+    299 * c.0[0] as u32 + 587 * c.0[1] as u32 + 114 * c.0[2] as u32
+}
+
+fn to_hex(c: &Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.0[0], c.0[1], c.0[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median_cut_separates_distinct_colors() {
+        let mut pixels = vec![[0u8, 0, 0]; 50];
+        pixels.extend(vec![[255u8, 255, 255]; 50]);
+
+        let palette = median_cut(pixels, 2);
+
+        assert_eq!(palette.len(), 2);
+        assert_eq!(to_hex(&palette[0]), "#000000");
+        assert_eq!(to_hex(&palette[1]), "#ffffff");
+    }
+
+    #[test]
+    fn test_median_cut_stops_on_uniform_image() {
+        let palette = median_cut(vec![[10u8, 20, 30]; 16], 8);
+        assert_eq!(palette.len(), 1);
+    }
+}
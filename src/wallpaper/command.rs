@@ -0,0 +1,201 @@
+use crate::{Result, WallmanError};
+
+/// Placeholders `applyCommand` must contain.
+const REQUIRED_PLACEHOLDERS: [&str; 2] = ["{output}", "{image}"];
+
+/// A parsed `applyCommand`: the program and its argument templates.
+///
+/// The template is split like a shell would (whitespace, quotes, backslash
+/// escapes) but never run through one; placeholders are substituted inside
+/// each word afterwards, so images with spaces stay a single argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandTemplate {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl CommandTemplate {
+    /// Split and validate a template such as
+    /// `mybg --output {output} --image {image} --mode {fill}`.
+    pub fn parse(template: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            WallmanError::Config(format!("invalid applyCommand '{}': {}", template, reason))
+        };
+
+        let mut words = split_words(template).map_err(invalid)?;
+        if words.is_empty() {
+            return Err(invalid("empty command"));
+        }
+        if words[0].contains('{') {
+            return Err(invalid("the program cannot be a placeholder"));
+        }
+        for placeholder in REQUIRED_PLACEHOLDERS {
+            if !words[1..].iter().any(|w| w.contains(placeholder)) {
+                return Err(invalid(&format!("missing {} placeholder", placeholder)));
+            }
+        }
+
+        let program = words.remove(0);
+        Ok(CommandTemplate {
+            program,
+            args: words,
+        })
+    }
+
+    /// Arguments for one output, with `{output}`, `{image}`, `{fill}` and
+    /// `{fade}` (milliseconds) filled in.
+    ///
+    /// Each word is scanned once, so braces inside the substituted values
+    /// (an image named `{fill}.png`) are left as they are.
+    pub fn expand(&self, output: &str, image: &str, fill: &str, fade_ms: u64) -> Vec<String> {
+        let fade = fade_ms.to_string();
+        let value = |name: &str| match name {
+            "output" => Some(output),
+            "image" => Some(image),
+            "fill" => Some(fill),
+            "fade" => Some(fade.as_str()),
+            _ => None,
+        };
+        self.args.iter().map(|arg| substitute(arg, value)).collect()
+    }
+}
+
+/// Replace each `{name}` in `arg` for which `value` returns a value, in one
+/// pass; unknown names and unmatched braces are copied through.
+fn substitute<'a>(arg: &str, value: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut expanded = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(open) = rest.find('{') {
+        expanded.push_str(&rest[..open]);
+        let token = &rest[open..];
+        match token
+            .find('}')
+            .and_then(|close| Some((close, value(&token[1..close])?)))
+        {
+            Some((close, replacement)) => {
+                expanded.push_str(replacement);
+                rest = &token[close + 1..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &token[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Shell-style word splitting without expansion.
+fn split_words(input: &str) -> std::result::Result<Vec<String>, &'static str> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote"),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote"),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote"),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => word.push(c),
+                    None => return Err("trailing backslash"),
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_splits_quotes_and_expands_per_word() {
+        let template = CommandTemplate::parse(
+            r#"mybg --output {output} --image "{image}" --title 'my bg' --fade={fade}"#,
+        )
+        .unwrap();
+        assert_eq!(template.program, "mybg");
+        assert_eq!(
+            template.expand("DP-1", "/walls/a b.png", "fill", 500),
+            [
+                "--output",
+                "DP-1",
+                "--image",
+                "/walls/a b.png",
+                "--title",
+                "my bg",
+                "--fade=500"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_leaves_braces_in_values_alone() {
+        let template = CommandTemplate::parse("mybg -o {output} -i {image} -m {fill} {x}").unwrap();
+        assert_eq!(
+            template.expand("{image}", "/walls/{fill}.png", "fit", 0),
+            [
+                "-o",
+                "{image}",
+                "-i",
+                "/walls/{fill}.png",
+                "-m",
+                "fit",
+                "{x}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_template_requires_output_and_image() {
+        assert!(CommandTemplate::parse("mybg --image {image}").is_err());
+        assert!(CommandTemplate::parse("mybg {output}").is_err());
+        assert!(CommandTemplate::parse("mybg {output} '{image}").is_err());
+        assert!(CommandTemplate::parse("   ").is_err());
+        assert!(CommandTemplate::parse("{image} {output}").is_err());
+    }
+}
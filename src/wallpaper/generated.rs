@@ -0,0 +1,385 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    result::Result as StdResult,
+};
+
+use image::{Rgb, RgbImage};
+
+/// Size of generated images; 16:9 so `fill` does not crop gradients away.
+/// `gradient:` and `pattern:` images use the output's resolution when known.
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+
+/// Cell size of `pattern:` images without an explicit one, in pixels.
+const DEFAULT_CELL: u32 = 64;
+
+/// `builtin:<name>` gradients, top color to bottom color.
+const BUILTINS: &[(&str, [u8; 3], [u8; 3])] = &[
+    ("dusk", [0x2b, 0x1e, 0x3f], [0xe0, 0x7a, 0x5f]),
+    ("ocean", [0x0b, 0x2a, 0x4a], [0x2e, 0x8b, 0xa8]),
+    ("forest", [0x1b, 0x3a, 0x2b], [0x6a, 0x8f, 0x4e]),
+    ("slate", [0x1e, 0x1e, 0x2e], [0x45, 0x47, 0x5a]),
+    ("sunrise", [0xf6, 0xd3, 0x65], [0xfd, 0xa0, 0x85]),
+];
+
+/// Folder holding generated placeholder images.
+pub fn generated_folder() -> PathBuf {
+    crate::data_folder().join("generated")
+}
+
+/// Whether `value` uses one of the `color:` / `builtin:` / `gradient:` /
+/// `pattern:` schemes.
+pub fn is_generated(value: &str) -> bool {
+    value.starts_with("color:") || value.starts_with("builtin:") || is_procedural(value)
+}
+
+/// Whether `value` is a `gradient:` or `pattern:` spec, generated at the
+/// output's resolution.
+pub fn is_procedural(value: &str) -> bool {
+    value.starts_with("gradient:") || value.starts_with("pattern:")
+}
+
+/// Check a `gradient:` / `pattern:` spec without generating anything.
+pub fn validate(spec: &str) -> StdResult<(), String> {
+    parse(spec).map(|_| ())
+}
+
+/// Direction of a `gradient:`, from the first color to the second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    /// Top to bottom.
+    Vertical,
+    /// Left to right.
+    Horizontal,
+    /// Top-left to bottom-right.
+    Diagonal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pattern {
+    Checker,
+    /// Diagonal stripes.
+    Stripes,
+}
+
+/// A parsed generated-image value.
+#[derive(Debug, Clone, PartialEq)]
+enum Spec {
+    /// `color:` and `builtin:`: a fixed-size vertical gradient (equal colors
+    /// for a solid fill).
+    Fixed {
+        file_name: String,
+        top: [u8; 3],
+        bottom: [u8; 3],
+    },
+    Gradient {
+        from: [u8; 3],
+        to: [u8; 3],
+        direction: Direction,
+    },
+    Pattern {
+        pattern: Pattern,
+        a: [u8; 3],
+        b: [u8; 3],
+        cell: u32,
+    },
+}
+
+fn color(value: &str) -> StdResult<[u8; 3], String> {
+    parse_hex(value).ok_or_else(|| format!("'{}' is not a #rgb or #rrggbb color", value))
+}
+
+fn parse(spec: &str) -> StdResult<Spec, String> {
+    if let Some(color) = spec.strip_prefix("color:") {
+        let rgb = parse_hex(color).ok_or_else(|| format!("'{}' is not a #rrggbb color", color))?;
+        Ok(Spec::Fixed {
+            file_name: format!("color-{}.png", hex(rgb)),
+            top: rgb,
+            bottom: rgb,
+        })
+    } else if let Some(name) = spec.strip_prefix("builtin:") {
+        let (name, top, bottom) = BUILTINS
+            .iter()
+            .find(|(builtin, _, _)| builtin.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                format!(
+                    "unknown builtin '{}' (available: {})",
+                    name,
+                    builtin_names().join(", ")
+                )
+            })?;
+        Ok(Spec::Fixed {
+            file_name: format!("builtin-{}.png", name),
+            top: *top,
+            bottom: *bottom,
+        })
+    } else if let Some(rest) = spec.strip_prefix("gradient:") {
+        let (colors, direction) = rest.split_once(':').unwrap_or((rest, "vertical"));
+        let (from, to) = colors
+            .split_once('-')
+            .ok_or_else(|| format!("gradient '{}' needs two colors: <from>-<to>", colors))?;
+        let direction = match direction {
+            "vertical" => Direction::Vertical,
+            "horizontal" => Direction::Horizontal,
+            "diagonal" => Direction::Diagonal,
+            other => {
+                return Err(format!(
+                    "unknown gradient direction '{}' (available: vertical, horizontal, diagonal)",
+                    other
+                ));
+            }
+        };
+        Ok(Spec::Gradient {
+            from: color(from)?,
+            to: color(to)?,
+            direction,
+        })
+    } else if let Some(rest) = spec.strip_prefix("pattern:") {
+        let parts: Vec<&str> = rest.split(':').collect();
+        let (name, a, b, cell) = match parts[..] {
+            [name, a, b] => (name, a, b, None),
+            [name, a, b, cell] => (name, a, b, Some(cell)),
+            _ => {
+                return Err(format!(
+                    "pattern '{}' must look like <name>:<color>:<color>[:<cell px>]",
+                    rest
+                ));
+            }
+        };
+        let pattern = match name {
+            "checker" => Pattern::Checker,
+            "stripes" => Pattern::Stripes,
+            other => {
+                return Err(format!(
+                    "unknown pattern '{}' (available: checker, stripes)",
+                    other
+                ));
+            }
+        };
+        let cell =
+            match cell {
+                None => DEFAULT_CELL,
+                Some(cell) => cell.parse().ok().filter(|c| *c > 0).ok_or_else(|| {
+                    format!("pattern cell size '{}' is not a positive number", cell)
+                })?,
+            };
+        Ok(Spec::Pattern {
+            pattern,
+            a: color(a)?,
+            b: color(b)?,
+            cell,
+        })
+    } else {
+        Err(format!("'{}' is not a generated image", spec))
+    }
+}
+
+impl Spec {
+    /// Cache file name; `gradient:`/`pattern:` images include their size so
+    /// a resolution change generates a new one.
+    fn file_name(&self, (width, height): (u32, u32)) -> String {
+        match self {
+            Spec::Fixed { file_name, .. } => file_name.clone(),
+            Spec::Gradient {
+                from,
+                to,
+                direction,
+            } => format!(
+                "gradient-{}-{}-{}-{}x{}.png",
+                hex(*from),
+                hex(*to),
+                format!("{:?}", direction).to_lowercase(),
+                width,
+                height
+            ),
+            Spec::Pattern {
+                pattern,
+                a,
+                b,
+                cell,
+            } => format!(
+                "pattern-{}-{}-{}-{}-{}x{}.png",
+                format!("{:?}", pattern).to_lowercase(),
+                hex(*a),
+                hex(*b),
+                cell,
+                width,
+                height
+            ),
+        }
+    }
+
+    fn render(&self, (width, height): (u32, u32)) -> RgbImage {
+        let blend = |from: [u8; 3], to: [u8; 3], t: f32| {
+            Rgb(std::array::from_fn(|i| {
+                (from[i] as f32 + (to[i] as f32 - from[i] as f32) * t).round() as u8
+            }))
+        };
+        let fraction = |value: u32, max: u32| value as f32 / max.saturating_sub(1).max(1) as f32;
+        match *self {
+            Spec::Fixed { top, bottom, .. } => RgbImage::from_fn(width, height, |_, y| {
+                blend(top, bottom, fraction(y, height))
+            }),
+            Spec::Gradient {
+                from,
+                to,
+                direction,
+            } => RgbImage::from_fn(width, height, |x, y| {
+                let t = match direction {
+                    Direction::Vertical => fraction(y, height),
+                    Direction::Horizontal => fraction(x, width),
+                    Direction::Diagonal => fraction(x + y, width + height - 1),
+                };
+                blend(from, to, t)
+            }),
+            Spec::Pattern {
+                pattern,
+                a,
+                b,
+                cell,
+            } => RgbImage::from_fn(width, height, |x, y| {
+                let even = match pattern {
+                    Pattern::Checker => (x / cell + y / cell) % 2 == 0,
+                    Pattern::Stripes => ((x + y) / cell) % 2 == 0,
+                };
+                Rgb(if even { a } else { b })
+            }),
+        }
+    }
+}
+
+/// Names accepted by `builtin:`.
+pub fn builtin_names() -> Vec<&'static str> {
+    BUILTINS.iter().map(|(name, _, _)| *name).collect()
+}
+
+/// Path of the image for a generated value, creating it on first use.
+///
+/// `size` is the output's resolution in pixels; `gradient:` and `pattern:`
+/// images are generated at it (1920x1080 when unknown), `color:` and
+/// `builtin:` ones always at 1920x1080.
+pub fn generated_image(spec: &str, size: Option<(u32, u32)>) -> StdResult<PathBuf, String> {
+    generated_image_in(&generated_folder(), spec, size)
+}
+
+fn generated_image_in(
+    dir: &Path,
+    spec: &str,
+    size: Option<(u32, u32)>,
+) -> StdResult<PathBuf, String> {
+    let spec = parse(spec)?;
+    let size = match (&spec, size) {
+        (Spec::Fixed { .. }, _) | (_, None) => (WIDTH, HEIGHT),
+        (_, Some(size)) => size,
+    };
+
+    let target = dir.join(spec.file_name(size));
+    if target.exists() {
+        return Ok(target);
+    }
+
+    tracing::info!("Generating placeholder {}", target.display());
+    let img = spec.render(size);
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    // Write under a temp name so a crash never leaves a truncated cache hit.
+    let partial = target.with_extension("png.partial");
+    img.save_with_format(&partial, image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    fs::rename(&partial, &target).map_err(|e| e.to_string())?;
+    Ok(target)
+}
+
+fn parse_hex(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        6 => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            Some([channel(0)?, channel(2)?, channel(4)?])
+        }
+        // `#rgb` shorthand: each digit is doubled.
+        3 => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|c| c * 17);
+            Some([channel(0)?, channel(1)?, channel(2)?])
+        }
+        _ => None,
+    }
+}
+
+fn hex(rgb: [u8; 3]) -> String {
+    rgb.iter().map(|c| format!("{:02x}", c)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_images_are_created_once() {
+        let dir = std::env::temp_dir().join("wallman_test_generated");
+        let _ = fs::remove_dir_all(&dir);
+
+        let solid = generated_image_in(&dir, "color:#1E1E2E", Some((800, 600))).unwrap();
+        assert_eq!(solid.file_name().unwrap(), "color-1e1e2e.png");
+        let img = image::open(&solid).unwrap().to_rgb8();
+        assert_eq!(img.get_pixel(0, 0).0, [0x1e, 0x1e, 0x2e]);
+        assert_eq!(img.get_pixel(WIDTH - 1, HEIGHT - 1).0, [0x1e, 0x1e, 0x2e]);
+
+        let gradient = generated_image_in(&dir, "builtin:Ocean", None).unwrap();
+        let img = image::open(&gradient).unwrap().to_rgb8();
+        assert_eq!(img.get_pixel(0, 0).0, [0x0b, 0x2a, 0x4a]);
+        assert_eq!(img.get_pixel(0, HEIGHT - 1).0, [0x2e, 0x8b, 0xa8]);
+        assert_eq!(
+            generated_image_in(&dir, "builtin:ocean", None).unwrap(),
+            gradient
+        );
+
+        assert!(generated_image_in(&dir, "color:blue", None).is_err());
+        assert!(generated_image_in(&dir, "builtin:nope", None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_gradients_and_patterns_follow_the_output_size() {
+        let dir = std::env::temp_dir().join("wallman_test_generated_sized");
+        let _ = fs::remove_dir_all(&dir);
+
+        let spec = "gradient:#1e1e2e-#313244:horizontal";
+        let small = generated_image_in(&dir, spec, Some((320, 200))).unwrap();
+        assert_eq!(
+            small.file_name().unwrap(),
+            "gradient-1e1e2e-313244-horizontal-320x200.png"
+        );
+        let img = image::open(&small).unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (320, 200));
+        assert_eq!(img.get_pixel(0, 199).0, [0x1e, 0x1e, 0x2e]);
+        assert_eq!(img.get_pixel(319, 0).0, [0x31, 0x32, 0x44]);
+        // A new resolution is a new cache entry.
+        let large = generated_image_in(&dir, spec, Some((640, 400))).unwrap();
+        assert_ne!(large, small);
+        assert_eq!(image::open(&large).unwrap().width(), 640);
+
+        let checker =
+            generated_image_in(&dir, "pattern:checker:#222:#333:10", Some((40, 40))).unwrap();
+        let img = image::open(&checker).unwrap().to_rgb8();
+        assert_eq!(img.get_pixel(0, 0).0, [0x22; 3]);
+        assert_eq!(img.get_pixel(10, 0).0, [0x33; 3]);
+        assert_eq!(img.get_pixel(10, 10).0, [0x22; 3]);
+
+        for bad in [
+            "gradient:#1e1e2e",
+            "gradient:#1e1e2e-#313244:radial",
+            "gradient:#1e1e2e-blue",
+            "pattern:waves:#222:#333",
+            "pattern:checker:#222",
+            "pattern:checker:#222:#333:0",
+        ] {
+            assert!(validate(bad).is_err(), "{} should be rejected", bad);
+        }
+        assert!(validate("gradient:#000-#fff").is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use std::process::{Command, Stdio};
+
+use crate::trigger::OutputChange;
+
+/// Run the user's `onChange` script for an output that just changed wallpaper.
+///
+/// The script receives the output name and image path as arguments and as
+/// `WALLMAN_OUTPUT` / `WALLMAN_IMAGE` environment variables. It is spawned
+/// without waiting; a watcher thread reaps it and logs non-zero exits.
+pub fn run_on_change(script: &str, change: &OutputChange) {
+    let spawned = hook_command(script, change)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            tracing::warn!("Failed to run onChange hook '{}': {}", script, e);
+            return;
+        }
+    };
+
+    let script = script.to_string();
+    let output = change.output.clone();
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => {
+            tracing::warn!(
+                "onChange hook '{}' for output '{}' exited with {}",
+                script,
+                output,
+                status
+            );
+        }
+        Ok(_) => tracing::debug!("onChange hook '{}' finished for '{}'", script, output),
+        Err(e) => tracing::warn!("Failed to wait for onChange hook '{}': {}", script, e),
+    });
+}
+
+/// The hook invocation. The script is executed directly, never through a
+/// shell, so paths with spaces, quotes or newlines reach it verbatim.
+fn hook_command(script: &str, change: &OutputChange) -> Command {
+    let mut command = Command::new(script);
+    command
+        .args([&change.output, &change.image_path])
+        .env("WALLMAN_OUTPUT", &change.output)
+        .env("WALLMAN_IMAGE", &change.image_path);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_hook_gets_awkward_paths_verbatim() {
+        let dir = std::env::temp_dir().join("wallman_test_hook_paths");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("hook.sh");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\nprintf '%s|%s|%s' \"$1\" \"$2\" \"$WALLMAN_IMAGE\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let image = "/walls/my night\n'$(touch pwned)' \"ciel\" étoilé 🌙.png";
+        let change = OutputChange {
+            output: "DP-1".to_string(),
+            image_path: image.to_string(),
+            fill_mode: crate::FillMode::Fill,
+        };
+        let output = hook_command(&script.to_string_lossy(), &change)
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("DP-1|{}|{}", image, image)
+        );
+        assert!(!dir.join("pwned").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
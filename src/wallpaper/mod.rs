@@ -1,25 +1,184 @@
 pub mod apply;
+pub mod backend;
+pub mod colors;
+pub mod command;
+pub mod generated;
+pub mod hook;
+#[cfg(feature = "portal")]
+pub mod portal;
+pub mod preload;
+pub mod process;
 
+use crate::{FillMode, trigger::OutputChange};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
-use std::process::Child;
+use std::path::PathBuf;
+use std::process::{Child, ExitStatus};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// The process behind a tracked wallpaper.
+pub enum ProcessHandle {
+    /// Started by this process.
+    Spawned(Child),
+    /// Left running by a previous daemon and taken over at start. It is not
+    /// our child, so there is no exit status to collect.
+    Adopted(u32),
+}
+
+impl From<Child> for ProcessHandle {
+    fn from(child: Child) -> Self {
+        Self::Spawned(child)
+    }
+}
+
+/// Whether a tracked process still runs, and how it ended if not.
+enum Liveness {
+    Running,
+    Succeeded,
+    /// Non-zero exit or killed by a signal; `None` for an adopted process,
+    /// whose status is unknown.
+    Failed(Option<ExitStatus>),
+}
+
+impl ProcessHandle {
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::Spawned(child) => child.id(),
+            Self::Adopted(pid) => *pid,
+        }
+    }
+
+    /// Whether the process is still running.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.liveness(), Liveness::Running)
+    }
+
+    fn liveness(&mut self) -> Liveness {
+        match self {
+            Self::Spawned(child) => match child.try_wait() {
+                Ok(Some(status)) if status.success() => Liveness::Succeeded,
+                Ok(Some(status)) => Liveness::Failed(Some(status)),
+                _ => Liveness::Running,
+            },
+            Self::Adopted(pid) if crate::daemon::status::is_running(*pid) => Liveness::Running,
+            Self::Adopted(_) => Liveness::Failed(None),
+        }
+    }
+
+    /// Stop the process; a spawned one is also reaped so it leaves no zombie.
+    fn kill(&mut self) {
+        match self {
+            Self::Spawned(child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            #[cfg(unix)]
+            Self::Adopted(pid) => {
+                let _ = nix::sys::signal::kill(
+                    nix::unistd::Pid::from_raw(*pid as i32),
+                    nix::sys::signal::Signal::SIGTERM,
+                );
+            }
+            #[cfg(not(unix))]
+            Self::Adopted(_) => {}
+        }
+    }
+}
+
+/// A running swaybg process together with the image it is displaying.
+pub struct TrackedProcess {
+    pub process: ProcessHandle,
+    pub image_path: String,
+    pub fill_mode: FillMode,
+    /// Outputs this process draws; several when one swaybg serves a group.
+    pub outputs: Vec<String>,
+    pub started: Instant,
+}
 
 lazy_static! {
-    /// Tracks active swaybg processes per output name.
-    static ref PROCESS_TRACKER: Arc<Mutex<HashMap<String, Child>>> = Arc::new(Mutex::new(HashMap::new()));
+    /// Tracks active swaybg processes by `process_key` of the outputs they draw.
+    static ref PROCESS_TRACKER: Arc<Mutex<HashMap<String, TrackedProcess>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    /// Image last drawn on each output. Unlike the tracker it survives a
+    /// process exiting, so a respawn can be told apart from a change.
+    static ref CURRENT_IMAGES: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
 }
 
-/// Kill the existing swaybg process for a specific output if it exists.
-pub fn kill_for_output(output_name: &str) {
-    let mut tracker = PROCESS_TRACKER.lock().unwrap();
-    if let Some(mut child) = tracker.remove(output_name) {
-        tracing::debug!("Killing existing swaybg for output '{}'", output_name);
-        let _ = child.kill();
-        let _ = child.wait(); // Prevent zombies
+/// Tracker key for a process drawing `outputs`: the output name itself, or a
+/// synthetic `a+b+…` key for a shared process.
+fn process_key(outputs: &[String]) -> String {
+    outputs.join("+")
+}
+
+/// Tracker key of the process currently drawing `output_name`.
+fn key_for_output(tracker: &HashMap<String, TrackedProcess>, output_name: &str) -> Option<String> {
+    tracker
+        .iter()
+        .find(|(_, tracked)| tracked.outputs.iter().any(|o| o == output_name))
+        .map(|(key, _)| key.clone())
+}
+
+impl TrackedProcess {
+    /// What this process draws on each of its outputs.
+    fn changes(&self) -> impl Iterator<Item = OutputChange> + '_ {
+        self.outputs.iter().map(|output| OutputChange {
+            output: output.clone(),
+            image_path: self.image_path.clone(),
+            fill_mode: self.fill_mode.clone(),
+        })
+    }
+
+    /// The changes for the outputs other than `output_name` this process
+    /// draws; they lose their wallpaper when it is stopped.
+    pub fn other_outputs(&self, output_name: &str) -> Vec<OutputChange> {
+        self.changes()
+            .filter(|change| change.output != output_name)
+            .collect()
+    }
+
+    /// Kill the process and reap it.
+    pub fn stop(mut self) {
+        tracing::debug!(
+            "Killing swaybg for output(s) '{}'",
+            self.outputs.join("', '")
+        );
+        self.process.kill();
     }
 }
 
+/// Stop tracking the swaybg process drawing `output_name` and hand it over,
+/// still running, so the caller decides when to stop it.
+pub fn take_for_output(output_name: &str) -> Option<TrackedProcess> {
+    let mut tracker = PROCESS_TRACKER.lock().unwrap();
+    key_for_output(&tracker, output_name).and_then(|key| tracker.remove(&key))
+}
+
+/// Kill the existing swaybg process for a specific output if it exists.
+///
+/// When that process was shared with other outputs they lose their wallpaper
+/// too; they are returned as changes so the caller can redraw them. The
+/// tracker lock is released before killing so outputs can be replaced
+/// concurrently.
+pub fn kill_for_output(output_name: &str) -> Vec<OutputChange> {
+    let Some(tracked) = take_for_output(output_name) else {
+        return Vec::new();
+    };
+    let orphans = tracked.other_outputs(output_name);
+    tracked.stop();
+    orphans
+}
+
+/// PIDs of the tracked backend processes, with what each one draws.
+pub fn tracked_processes() -> Vec<(u32, Vec<OutputChange>)> {
+    PROCESS_TRACKER
+        .lock()
+        .unwrap()
+        .values()
+        .map(|tracked| (tracked.process.id(), tracked.changes().collect()))
+        .collect()
+}
+
 /// Kill all tracked swaybg processes.
 pub fn kill_all() {
     let mut tracker = PROCESS_TRACKER.lock().unwrap();
@@ -27,14 +186,299 @@ pub fn kill_all() {
         "Killing all tracked swaybg processes (count: {})",
         tracker.len()
     );
-    for (_, mut child) in tracker.drain() {
-        let _ = child.kill();
-        let _ = child.wait();
+    for (_, mut tracked) in tracker.drain() {
+        tracked.process.kill();
     }
+    CURRENT_IMAGES.lock().unwrap().clear();
+}
+
+/// The image last drawn on `output_name`, even if its process has since exited.
+pub fn current_image(output_name: &str) -> Option<String> {
+    CURRENT_IMAGES.lock().unwrap().get(output_name).cloned()
 }
 
-/// Register a new swaybg process for an output.
-pub fn register_process(output_name: String, child: Child) {
+/// Register a new swaybg process drawing `image_path` on `outputs` in `fill_mode`.
+///
+/// Any process still tracked for one of `outputs` is stopped first so it is
+/// never orphaned; each output is drawn by at most one tracked process.
+pub fn register_process(
+    outputs: Vec<String>,
+    process: impl Into<ProcessHandle>,
+    image_path: String,
+    fill_mode: FillMode,
+) {
+    let process = process.into();
+    record_current_image(&outputs, &image_path);
+    let replaced: Vec<TrackedProcess> = {
+        let mut tracker = PROCESS_TRACKER.lock().unwrap();
+        let stale: Vec<String> = tracker
+            .iter()
+            .filter(|(_, tracked)| tracked.outputs.iter().any(|o| outputs.contains(o)))
+            .map(|(key, _)| key.clone())
+            .collect();
+        let replaced = stale.iter().filter_map(|key| tracker.remove(key)).collect();
+        tracker.insert(
+            process_key(&outputs),
+            TrackedProcess {
+                process,
+                image_path,
+                fill_mode,
+                outputs,
+                started: Instant::now(),
+            },
+        );
+        replaced
+    };
+    for old in replaced {
+        tracing::warn!(
+            "swaybg for output(s) '{}' was still tracked; stopping it",
+            old.outputs.join("', '")
+        );
+        old.stop();
+    }
+}
+
+/// Record that `outputs` now show `image_path`. `register_process` does this
+/// itself; backends without a process of their own (the portal) call it directly.
+pub fn record_current_image(outputs: &[String], image_path: &str) {
+    let mut current = CURRENT_IMAGES.lock().unwrap();
+    for output in outputs {
+        current.insert(output.clone(), image_path.to_string());
+    }
+}
+
+/// Returns true if a swaybg process is tracked for the output.
+pub fn is_tracked(output_name: &str) -> bool {
+    key_for_output(&PROCESS_TRACKER.lock().unwrap(), output_name).is_some()
+}
+
+/// Returns true if a live swaybg process is already showing `image_path` on
+/// the output in `fill_mode`.
+pub fn is_showing(output_name: &str, image_path: &str, fill_mode: &FillMode) -> bool {
+    let mut tracker = PROCESS_TRACKER.lock().unwrap();
+    let key = key_for_output(&tracker, output_name);
+    match key.and_then(|key| tracker.get_mut(&key)) {
+        Some(tracked) => {
+            tracked.image_path == image_path
+                && tracked.fill_mode == *fill_mode
+                && tracked.process.is_running()
+        }
+        None => false,
+    }
+}
+
+/// Remove and return tracked processes that exited on their own.
+///
+/// Intentional kills go through `kill_for_output`/`kill_all`, which drop the
+/// entry from the tracker first, so anything found here died unexpectedly.
+/// Returns one change per output a failed process drew (non-zero exit or
+/// killed by a signal), so the caller can re-apply them. A process that
+/// exited successfully is done, as `applyCommand` setters that hand the
+/// image to another program are, and is dropped without a respawn.
+///
+/// Processes that failed right after starting count towards falling back to
+/// the next backend (see `backend`); one that has kept running resets the count.
+pub fn take_exited() -> Vec<OutputChange> {
     let mut tracker = PROCESS_TRACKER.lock().unwrap();
-    tracker.insert(output_name, child);
+    let mut immediate = 0;
+    let mut healthy = false;
+    let mut finished = Vec::new();
+    let mut failed = Vec::new();
+    for (key, tracked) in tracker.iter_mut() {
+        match tracked.process.liveness() {
+            Liveness::Succeeded => {
+                tracing::debug!("Backend process for output '{}' finished", key);
+                finished.push(key.clone());
+            }
+            Liveness::Failed(Some(status)) => {
+                tracing::warn!(
+                    "Backend process for output '{}' exited unexpectedly ({})",
+                    key,
+                    status
+                );
+                if backend::failed_immediately(tracked.started, &status) {
+                    immediate += 1;
+                }
+                failed.push(key.clone());
+            }
+            Liveness::Failed(None) => {
+                tracing::warn!("Adopted backend process for output '{}' is gone", key);
+                failed.push(key.clone());
+            }
+            Liveness::Running => healthy |= !backend::exited_immediately(tracked.started),
+        }
+    }
+
+    for key in finished {
+        tracker.remove(&key);
+    }
+    let exited: Vec<TrackedProcess> = failed
+        .into_iter()
+        .filter_map(|key| tracker.remove(&key))
+        .collect();
+    // Recorded without the tracker lock: `record_failure` takes APP_STATE.
+    drop(tracker);
+    if healthy {
+        backend::record_success();
+    }
+    for _ in 0..immediate {
+        backend::record_failure();
+    }
+
+    exited
+        .iter()
+        .flat_map(|tracked| tracked.changes())
+        .collect()
+}
+
+/// Locate an executable by name in the directories listed in `$PATH`.
+pub fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| candidate.is_file())
+}
+
+/// The binaries from `names` that are not in `$PATH`, in order.
+pub fn missing_binaries(names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| find_in_path(name).is_none())
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_exited_returns_crashed_process() {
+        for (output, program) in [("TEST-CRASH-1", "false"), ("TEST-DONE-1", "true")] {
+            register_process(
+                vec![output.to_string()],
+                std::process::Command::new(program).spawn().unwrap(),
+                "/tmp/a.png".to_string(),
+                FillMode::Fill,
+            );
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+
+        // Only the failure is respawned; the successful exit is just forgotten.
+        let exited = take_exited();
+        assert!(exited.contains(&OutputChange {
+            output: "TEST-CRASH-1".to_string(),
+            image_path: "/tmp/a.png".to_string(),
+            fill_mode: FillMode::Fill,
+        }));
+        assert!(!exited.iter().any(|change| change.output == "TEST-DONE-1"));
+        assert!(!is_tracked("TEST-CRASH-1"));
+        assert!(!is_tracked("TEST-DONE-1"));
+    }
+
+    #[test]
+    fn test_shared_process_is_tracked_for_every_output() {
+        let child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let outputs = vec!["TEST-SHARED-1".to_string(), "TEST-SHARED-2".to_string()];
+        register_process(outputs, child, "/tmp/s.png".to_string(), FillMode::Crop);
+
+        assert!(is_showing("TEST-SHARED-1", "/tmp/s.png", &FillMode::Crop));
+        assert!(is_showing("TEST-SHARED-2", "/tmp/s.png", &FillMode::Crop));
+        assert!(!is_showing("TEST-SHARED-2", "/tmp/s.png", &FillMode::Fill));
+
+        // Replacing one output kills the shared process; the other must be redrawn.
+        let orphans = kill_for_output("TEST-SHARED-1");
+        assert_eq!(
+            orphans,
+            vec![OutputChange {
+                output: "TEST-SHARED-2".to_string(),
+                image_path: "/tmp/s.png".to_string(),
+                fill_mode: FillMode::Crop,
+            }]
+        );
+        assert!(!is_showing("TEST-SHARED-2", "/tmp/s.png", &FillMode::Crop));
+    }
+
+    #[test]
+    fn test_taken_process_keeps_running_until_stopped() {
+        let child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        register_process(
+            vec!["TEST-TAKE-1".to_string()],
+            child,
+            "/tmp/t.png".to_string(),
+            FillMode::Fill,
+        );
+
+        let mut taken = take_for_output("TEST-TAKE-1").unwrap();
+        assert!(!is_showing("TEST-TAKE-1", "/tmp/t.png", &FillMode::Fill));
+        assert!(taken.process.is_running());
+        assert!(taken.other_outputs("TEST-TAKE-1").is_empty());
+        taken.stop();
+        assert!(take_for_output("TEST-TAKE-1").is_none());
+    }
+
+    #[test]
+    fn test_adopted_process_is_shown_and_stopped_by_pid() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        register_process(
+            vec!["TEST-ADOPT-1".to_string()],
+            ProcessHandle::Adopted(child.id()),
+            "/tmp/o.png".to_string(),
+            FillMode::Fill,
+        );
+        assert!(is_showing("TEST-ADOPT-1", "/tmp/o.png", &FillMode::Fill));
+        assert_eq!(current_image("TEST-ADOPT-1").as_deref(), Some("/tmp/o.png"));
+
+        kill_for_output("TEST-ADOPT-1");
+        assert!(!child.wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_register_stops_process_already_drawing_the_output() {
+        let spawn = || {
+            std::process::Command::new("sleep")
+                .arg("30")
+                .spawn()
+                .unwrap()
+        };
+        let first = spawn();
+        let first_pid = first.id() as i32;
+        register_process(
+            vec!["TEST-DUP-1".to_string()],
+            first,
+            "/tmp/d1.png".to_string(),
+            FillMode::Fill,
+        );
+        register_process(
+            vec!["TEST-DUP-1".to_string()],
+            spawn(),
+            "/tmp/d2.png".to_string(),
+            FillMode::Fill,
+        );
+
+        // The first child was killed and reaped rather than orphaned.
+        let alive = nix::sys::signal::kill(nix::unistd::Pid::from_raw(first_pid), None).is_ok();
+        assert!(!alive);
+        assert!(is_showing("TEST-DUP-1", "/tmp/d2.png", &FillMode::Fill));
+        kill_for_output("TEST-DUP-1");
+    }
+
+    #[test]
+    fn test_find_in_path() {
+        assert!(find_in_path("sh").is_some());
+        assert!(find_in_path("wallman-no-such-binary").is_none());
+        assert_eq!(
+            missing_binaries(&["sh".to_string(), "wallman-no-such-binary".to_string()]),
+            ["wallman-no-such-binary"]
+        );
+    }
 }
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use zbus::{blocking::Connection, zvariant::Value};
+
+use crate::{Result, WallmanError};
+
+/// Bus name of the XDG desktop portal.
+const PORTAL_BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+
+/// Object path the portal interfaces are served at.
+const PORTAL_OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+
+/// Interface providing `SetWallpaperURI`.
+const WALLPAPER_INTERFACE: &str = "org.freedesktop.portal.Wallpaper";
+
+/// Whether a desktop portal is running on the session bus.
+pub fn is_available() -> bool {
+    Connection::session()
+        .and_then(|connection| zbus::blocking::fdo::DBusProxy::new(&connection))
+        .and_then(|proxy| {
+            proxy
+                .name_has_owner(PORTAL_BUS_NAME.try_into()?)
+                .map_err(Into::into)
+        })
+        .unwrap_or(false)
+}
+
+/// Ask the portal to show `image_path` as the desktop background.
+///
+/// The portal has no notion of outputs: the compositor applies the image to
+/// every monitor it manages.
+pub fn set_wallpaper(image_path: &str) -> Result<()> {
+    let unavailable = |e: zbus::Error| {
+        WallmanError::Output(format!(
+            "XDG desktop portal unavailable ({}): {}",
+            WALLPAPER_INTERFACE, e
+        ))
+    };
+    let connection = Connection::session().map_err(unavailable)?;
+    let options: HashMap<&str, Value> = HashMap::from([
+        ("show-preview", Value::from(false)),
+        ("set-on", Value::from("background")),
+    ]);
+    connection
+        .call_method(
+            Some(PORTAL_BUS_NAME),
+            PORTAL_OBJECT_PATH,
+            Some(WALLPAPER_INTERFACE),
+            "SetWallpaperURI",
+            &("", file_uri(image_path), options),
+        )
+        .map_err(unavailable)?;
+    Ok(())
+}
+
+/// `file://` URI for an absolute path, percent-encoding everything but
+/// unreserved characters and `/`.
+fn file_uri(path: &str) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_uri_encodes_special_characters() {
+        assert_eq!(
+            file_uri("/walls/nuit étoilée #2.png"),
+            "file:///walls/nuit%20%C3%A9toil%C3%A9e%20%232.png"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use crate::{
+    AppState, ImageMeta, OutputResolver, TriggerKind,
+    wallpaper::process::{ProcessOptions, processed_image},
+};
+
+/// Warm the active config's images on a background thread when
+/// `preload = true`.
+///
+/// Called when the daemon starts and after a reload or theme switch.
+/// Generated and processed images are rendered into their caches; plain
+/// files are read once so the backend finds them in the page cache.
+pub fn spawn() {
+    let Some(state) = crate::APP_STATE.get() else {
+        return;
+    };
+    let snapshot = {
+        let state = state.lock().unwrap();
+        if state.config.preload != Some(true) {
+            return;
+        }
+        state.clone()
+    };
+    let spawned = std::thread::Builder::new()
+        .name("preload".to_string())
+        .spawn(move || match OutputResolver::detect_cached() {
+            Ok(resolver) => {
+                let count = preload(&snapshot, &resolver);
+                tracing::info!("Preloaded {} image(s)", count);
+            }
+            Err(e) => tracing::warn!("Preload skipped: {}", e),
+        });
+    if let Err(e) = spawned {
+        tracing::warn!("Could not start preloading: {}", e);
+    }
+}
+
+/// Warm every image `state` references for `resolver`'s outputs, one at a
+/// time; returns how many files were warmed.
+///
+/// Only images the config names are touched: a `mode = "random"` pool is a
+/// directory and is skipped rather than read in full.
+fn preload(state: &AppState, resolver: &OutputResolver) -> usize {
+    let mut warmed = HashSet::new();
+    for listed in crate::images::list(state, resolver) {
+        // Blur/brightness only apply to `[background]` images, as in StaticTrigger.
+        let background = state.config.background.as_ref().and_then(|map| {
+            map.get(OutputResolver::matching_key(map, &listed.output)?)
+                .and_then(ProcessOptions::from_background)
+        });
+        for entry in listed.entries {
+            let path = Path::new(&entry.resolved);
+            if !entry.exists || !path.is_file() {
+                continue;
+            }
+            let base = (entry.section == TriggerKind::Static)
+                .then_some(background)
+                .flatten();
+            let meta = ImageMeta::for_image(path).unwrap_or_default();
+            let shown = match ProcessOptions::with_meta(base, &meta) {
+                Some(options) => match processed_image(path, &options) {
+                    Ok(processed) => processed.to_string_lossy().to_string(),
+                    Err(e) => {
+                        tracing::debug!("Preload: could not process '{}': {}", entry.resolved, e);
+                        entry.resolved.clone()
+                    }
+                },
+                None => entry.resolved.clone(),
+            };
+            if warmed.insert(shown.clone())
+                && let Err(e) = warm(Path::new(&shown))
+            {
+                tracing::debug!("Preload: could not read '{}': {}", shown, e);
+                warmed.remove(&shown);
+            }
+        }
+    }
+    warmed.len()
+}
+
+/// Read `path` start to end without keeping it in memory.
+fn warm(path: &Path) -> io::Result<u64> {
+    io::copy(&mut fs::File::open(path)?, &mut io::sink())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn test_preload_reads_each_referenced_file_once() {
+        let dir = std::env::temp_dir().join("wallman_test_preload");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("day.png"), b"day").unwrap();
+        fs::write(dir.join("night.png"), b"night").unwrap();
+
+        let config: Config = toml::from_str(
+            r##"
+                preload = true
+
+                [background.DP-1]
+                image = "day.png"
+
+                [timeConfig."*"]
+                day = "day.png"
+                night = "night.png"
+
+                [network."*"]
+                default = "missing.png"
+            "##,
+        )
+        .unwrap();
+        let state = AppState::new(
+            config,
+            dir.join("config.toml").to_string_lossy().to_string(),
+            None,
+            false,
+        )
+        .unwrap();
+        let resolver = OutputResolver::from_outputs(vec!["DP-1".into(), "HDMI-1".into()]);
+
+        // day.png is referenced three times but read once; missing.png is skipped.
+        assert_eq!(preload(&state, &resolver), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
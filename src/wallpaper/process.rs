@@ -0,0 +1,219 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    result::Result as StdResult,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{BackgroundConfig, ImageMeta};
+
+/// Blur/brightness/rotation adjustments applied before an image is displayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessOptions {
+    /// Gaussian blur sigma in pixels; `None` or `0` disables blurring.
+    pub blur_sigma: Option<f32>,
+    /// Brightness multiplier (`0.5` halves it); `None` or `1.0` leaves it.
+    pub brightness: Option<f32>,
+    /// Clockwise rotation in degrees (90, 180 or 270).
+    pub rotate: Option<u32>,
+}
+
+impl ProcessOptions {
+    /// Options from a background section, or `None` when nothing is requested.
+    pub fn from_background(bg: &BackgroundConfig) -> Option<Self> {
+        let options = Self {
+            blur_sigma: bg.blur_sigma.filter(|s| *s > 0.0),
+            brightness: bg.brightness.filter(|b| (*b - 1.0).abs() > f32::EPSILON),
+            rotate: None,
+        };
+        options.is_active().then_some(options)
+    }
+
+    /// `options` with an image sidecar's `blur` and `rotate` taking precedence.
+    pub fn with_meta(options: Option<Self>, meta: &ImageMeta) -> Option<Self> {
+        let mut options = options.unwrap_or(Self {
+            blur_sigma: None,
+            brightness: None,
+            rotate: None,
+        });
+        if let Some(blur) = meta.blur {
+            options.blur_sigma = (blur > 0.0).then_some(blur);
+        }
+        if let Some(rotate) = meta.rotate {
+            options.rotate = (rotate % 360 != 0).then_some(rotate);
+        }
+        options.is_active().then_some(options)
+    }
+
+    fn is_active(&self) -> bool {
+        self.blur_sigma.is_some() || self.brightness.is_some() || self.rotate.is_some()
+    }
+}
+
+/// Folder holding processed copies of wallpapers.
+pub fn processed_folder() -> PathBuf {
+    crate::data_folder().join("processed")
+}
+
+/// Return a processed copy of `source`, creating it on first use.
+///
+/// The cache key covers the source path, its size and mtime and the options,
+/// so editing the image or the config produces a new file.
+pub fn processed_image(
+    source: &Path,
+    options: &ProcessOptions,
+) -> StdResult<PathBuf, Box<dyn std::error::Error>> {
+    processed_image_in(&processed_folder(), source, options)
+}
+
+fn processed_image_in(
+    dir: &Path,
+    source: &Path,
+    options: &ProcessOptions,
+) -> StdResult<PathBuf, Box<dyn std::error::Error>> {
+    let target = dir.join(format!("{}.png", cache_key(source, options)?));
+    if target.exists() {
+        tracing::debug!("Using processed wallpaper {}", target.display());
+        return Ok(target);
+    }
+
+    tracing::info!(
+        "Processing '{}' (blur={:?}, brightness={:?}, rotate={:?})",
+        source.display(),
+        options.blur_sigma,
+        options.brightness,
+        options.rotate
+    );
+    let mut img = image::open(source)?;
+    if let Some(sigma) = options.blur_sigma {
+        img = img.fast_blur(sigma);
+    }
+    if let Some(factor) = options.brightness {
+        let mut rgba = img.to_rgba8();
+        for pixel in rgba.pixels_mut() {
+            for c in &mut pixel.0[..3] {
+                *c = (*c as f32 * factor).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        img = rgba.into();
+    }
+    img = match options.rotate {
+        Some(90) => img.rotate90(),
+        Some(180) => img.rotate180(),
+        Some(270) => img.rotate270(),
+        _ => img,
+    };
+
+    fs::create_dir_all(dir)?;
+    // Write under a temp name so a crash never leaves a truncated cache hit.
+    let partial = target.with_extension("png.partial");
+    img.save_with_format(&partial, image::ImageFormat::Png)?;
+    fs::rename(&partial, &target)?;
+    Ok(target)
+}
+
+fn cache_key(
+    source: &Path,
+    options: &ProcessOptions,
+) -> StdResult<String, Box<dyn std::error::Error>> {
+    let meta = fs::metadata(source)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    let mut hasher = Sha256::new();
+    hasher.update(source.to_string_lossy().as_bytes());
+    hasher.update(meta.len().to_le_bytes());
+    hasher.update(mtime.to_le_bytes());
+    hasher.update(
+        format!(
+            "{:?}|{:?}|{:?}",
+            options.blur_sigma, options.brightness, options.rotate
+        )
+        .as_bytes(),
+    );
+    Ok(hasher
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_processed_image_darkens_and_caches() {
+        let dir = std::env::temp_dir().join("wallman_test_process");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("src.png");
+        RgbImage::from_pixel(4, 4, Rgb([200, 100, 50]))
+            .save(&source)
+            .unwrap();
+
+        let options = ProcessOptions {
+            blur_sigma: None,
+            brightness: Some(0.5),
+            rotate: None,
+        };
+        let out = processed_image_in(&dir.join("cache"), &source, &options).unwrap();
+        let pixel = image::open(&out).unwrap().to_rgb8().get_pixel(0, 0).0;
+        assert_eq!(pixel, [100, 50, 25]);
+
+        // Same inputs hit the cache; different options get their own file.
+        assert_eq!(
+            processed_image_in(&dir.join("cache"), &source, &options).unwrap(),
+            out
+        );
+        let blurred = ProcessOptions {
+            blur_sigma: Some(2.0),
+            brightness: Some(0.5),
+            rotate: None,
+        };
+        assert_ne!(
+            processed_image_in(&dir.join("cache"), &source, &blurred).unwrap(),
+            out
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sidecar_rotates_and_overrides_blur() {
+        let dir = std::env::temp_dir().join("wallman_test_process_rotate");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("src.png");
+        RgbImage::from_pixel(4, 2, Rgb([10, 20, 30]))
+            .save(&source)
+            .unwrap();
+
+        let per_output = ProcessOptions {
+            blur_sigma: Some(8.0),
+            brightness: Some(0.5),
+            rotate: None,
+        };
+        let meta = ImageMeta {
+            rotate: Some(90),
+            blur: Some(0.0),
+            ..ImageMeta::default()
+        };
+        let options = ProcessOptions::with_meta(Some(per_output), &meta).unwrap();
+        assert_eq!(options.blur_sigma, None);
+        assert_eq!(options.brightness, Some(0.5));
+
+        let out = processed_image_in(&dir.join("cache"), &source, &options).unwrap();
+        let img = image::open(&out).unwrap();
+        assert_eq!((img.width(), img.height()), (2, 4));
+
+        assert_eq!(ProcessOptions::with_meta(None, &ImageMeta::default()), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
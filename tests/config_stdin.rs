@@ -0,0 +1,93 @@
+//! Pipes configs into the real binary with `--config -`.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn wallman_with_stdin(config: &str, args: &[&str]) -> Output {
+    let home = std::env::temp_dir().join("wallman_test_config_stdin");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wallman"))
+        .args(["--config", "-"])
+        .args(args)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(config.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+const VALID: &str = r##"
+lat = 40.7
+lon = -74.0
+
+[background."*"]
+image = "color:#1e1e2e"
+"##;
+
+#[test]
+fn test_valid_config_from_stdin() {
+    let output = wallman_with_stdin(VALID, &["config", "validate"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Config is valid."));
+
+    let output = wallman_with_stdin(VALID, &["config", "get", "lat"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "40.7");
+}
+
+#[test]
+fn test_invalid_config_from_stdin() {
+    let output = wallman_with_stdin("dayRange = \"08-25\"\n", &["config", "validate"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("dayRange"));
+
+    let output = wallman_with_stdin("[background\n", &["config", "validate"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_stdin_config_is_never_written_back() {
+    for args in [
+        &["config", "set", "lat", "1.0"][..],
+        &["config", "migrate"][..],
+        &["config", "set-lat", "1.0"][..],
+    ] {
+        let output = wallman_with_stdin(VALID, args);
+        assert!(!output.status.success(), "{:?} succeeded", args);
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("stdin"),
+            "{:?}: {:?}",
+            args,
+            output
+        );
+    }
+}
+
+#[test]
+fn test_detached_daemon_refuses_stdin_config() {
+    for args in [
+        &["daemon", "start", "--force"][..],
+        &["daemon", "restart", "--force"][..],
+    ] {
+        let output = wallman_with_stdin(VALID, args);
+        assert!(!output.status.success(), "{:?} succeeded", args);
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("stdin"),
+            "{:?}: {:?}",
+            args,
+            output
+        );
+    }
+}
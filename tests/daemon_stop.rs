@@ -0,0 +1,305 @@
+//! Runs the real binary against stub `swaymsg`/`swaybg` scripts and checks
+//! which swaybg processes a start, stop or `set` leaves behind.
+#![cfg(unix)]
+
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+fn write_script(path: &Path, body: &str) {
+    fs::write(path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn is_alive(pid: i32) -> bool {
+    // SAFETY: signal 0 only checks that the process exists.
+    unsafe { libc::kill(pid, 0) == 0 }
+}
+
+/// Like `is_alive`, but an unreaped zombie of an orphan counts as dead.
+fn is_running(pid: i32) -> bool {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| Some(stat.rsplit_once(") ")?.1.starts_with('Z')))
+        .is_some_and(|zombie| !zombie)
+}
+
+fn wait_until(timeout: Duration, mut done: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if done() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    done()
+}
+
+struct Harness {
+    root: PathBuf,
+}
+
+impl Harness {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        let bin = root.join("bin");
+        fs::create_dir_all(&bin).unwrap();
+
+        write_script(
+            &bin.join("swaymsg"),
+            r#"echo '[{"name":"TEST-1","active":true},{"name":"TEST-2","active":true}]'"#,
+        );
+        // No `exec`: the process keeps the name `swaybg` the daemon records.
+        write_script(
+            &bin.join("swaybg"),
+            &format!(
+                "echo $$ >> {}\necho \"$@\" >> {}\nsleep 60",
+                root.join("swaybg.pids").display(),
+                root.join("swaybg.args").display()
+            ),
+        );
+
+        fs::write(root.join("bg.png"), b"png").unwrap();
+        fs::write(
+            root.join("config.toml"),
+            format!(
+                "[background.\"*\"]\nimage = \"{}\"\nfill_mode = \"fill\"\n",
+                root.join("bg.png").display()
+            ),
+        )
+        .unwrap();
+
+        Self { root }
+    }
+
+    fn wallman(&self, args: &[&str]) -> Command {
+        let mut command = self.command(&["daemon"]);
+        command
+            .arg("--pid-file")
+            .arg(self.root.join("daemon.pid"))
+            .args(args);
+        command
+    }
+
+    /// `wallman <args>` with the stubs on `PATH` and everything under `root`.
+    fn command(&self, args: &[&str]) -> Command {
+        let path = format!(
+            "{}:{}",
+            self.root.join("bin").display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+        let mut command = Command::new(env!("CARGO_BIN_EXE_wallman"));
+        command
+            .arg("--config")
+            .arg(self.root.join("config.toml"))
+            .args(args)
+            .env("PATH", path)
+            .env("WALLMAN_PID_FILE", self.root.join("daemon.pid"))
+            .env("HOME", &self.root)
+            .env("XDG_CONFIG_HOME", self.root.join("config"))
+            .env("XDG_DATA_HOME", self.root.join("data"))
+            .env("XDG_CACHE_HOME", self.root.join("cache"))
+            // Pass the daemon's Wayland session check; swaybg is a stub.
+            .env("WAYLAND_DISPLAY", "wayland-test")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    }
+
+    fn swaybg_pids(&self) -> Vec<i32> {
+        fs::read_to_string(self.root.join("swaybg.pids"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect()
+    }
+}
+
+#[test]
+fn test_stop_kills_tracked_swaybg() {
+    let harness = Harness::new("wallman_test_daemon_stop");
+    let mut daemon: Child = harness.wallman(&["start", "--foreground"]).spawn().unwrap();
+
+    // The wildcard background shows one image, so a single swaybg draws both outputs.
+    assert!(
+        wait_until(Duration::from_secs(10), || harness.swaybg_pids().len() == 1),
+        "daemon did not start swaybg"
+    );
+    let args = fs::read_to_string(harness.root.join("swaybg.args")).unwrap();
+    assert!(
+        args.contains("-o TEST-1") && args.contains("-o TEST-2"),
+        "swaybg args: {}",
+        args
+    );
+    let pids = harness.swaybg_pids();
+    assert!(pids.iter().all(|&pid| is_alive(pid)));
+
+    let status = harness.wallman(&["stop"]).status().unwrap();
+    assert!(status.success());
+
+    assert!(
+        wait_until(Duration::from_secs(10), || daemon
+            .try_wait()
+            .unwrap()
+            .is_some()),
+        "daemon did not exit after stop"
+    );
+    assert!(daemon.wait().unwrap().success());
+    for pid in pids {
+        assert!(!is_alive(pid), "swaybg {} survived the stop", pid);
+    }
+    assert!(!harness.root.join("daemon.pid").exists());
+
+    fs::remove_dir_all(&harness.root).unwrap();
+}
+
+#[test]
+fn test_start_takes_over_only_the_swaybg_a_crashed_daemon_left() {
+    let harness = Harness::new("wallman_test_daemon_crash");
+    // Somebody else's swaybg, running before the daemon starts.
+    let mut foreign = Command::new(harness.root.join("bin/swaybg"))
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    assert!(wait_until(Duration::from_secs(10), || harness
+        .swaybg_pids()
+        .len()
+        == 1));
+
+    let mut crashed = harness.wallman(&["start", "--foreground"]).spawn().unwrap();
+    assert!(
+        wait_until(Duration::from_secs(10), || harness.swaybg_pids().len() == 2),
+        "daemon did not start swaybg"
+    );
+    let left_behind = harness.swaybg_pids()[1];
+    // Give the daemon a moment to record its swaybg in the state file.
+    thread::sleep(Duration::from_millis(500));
+    crashed.kill().unwrap();
+    crashed.wait().unwrap();
+    assert!(is_running(left_behind));
+
+    // The new daemon keeps the crashed one's swaybg, which already shows the
+    // right image, instead of drawing it again.
+    let mut daemon = harness.wallman(&["start", "--foreground"]).spawn().unwrap();
+    assert!(wait_until(Duration::from_secs(10), || harness
+        .root
+        .join("daemon.pid")
+        .exists()));
+    thread::sleep(Duration::from_secs(1));
+    assert_eq!(harness.swaybg_pids().len(), 2, "the wallpaper was redrawn");
+    assert!(is_running(left_behind));
+    assert!(
+        is_running(foreign.id() as i32),
+        "a foreign swaybg was killed"
+    );
+
+    // Taken over means stopped with the daemon, too.
+    assert!(harness.wallman(&["stop"]).status().unwrap().success());
+    assert!(daemon.wait().unwrap().success());
+    assert!(
+        wait_until(Duration::from_secs(10), || !is_running(left_behind)),
+        "the taken-over swaybg survived the stop"
+    );
+    assert!(is_running(foreign.id() as i32));
+    foreign.kill().unwrap();
+    foreign.wait().unwrap();
+    fs::remove_dir_all(&harness.root).unwrap();
+}
+
+#[test]
+fn test_set_without_a_daemon_leaves_detached_swaybg_for_stop() {
+    let harness = Harness::new("wallman_test_set_detached");
+    let image = harness.root.join("bg.png");
+    // Run `set` and return the PID of the swaybg it started.
+    let set = |output: &str| {
+        let started = harness.swaybg_pids().len();
+        let status = harness
+            .command(&[
+                "set",
+                "--image",
+                image.to_str().unwrap(),
+                "--output",
+                output,
+            ])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(wait_until(Duration::from_secs(10), || harness
+            .swaybg_pids()
+            .len()
+            > started));
+        harness.swaybg_pids()[started]
+    };
+
+    let first = set("TEST-1");
+    assert!(is_running(first));
+    // SAFETY: getsid only reads the session of an existing process.
+    assert_eq!(
+        unsafe { libc::getsid(first) },
+        first,
+        "swaybg is not in its own session"
+    );
+    let state = fs::read_to_string(harness.root.join("daemon.state.json")).unwrap();
+    assert!(state.contains(&first.to_string()), "state file: {}", state);
+
+    // Another set on the same output replaces the first swaybg ...
+    let second = set("TEST-1");
+    assert!(
+        wait_until(Duration::from_secs(10), || !is_running(first)),
+        "the earlier swaybg survived a second set"
+    );
+    // ... while one on another output keeps it.
+    let third = set("TEST-2");
+    assert!(is_running(second) && is_running(third));
+
+    assert!(harness.wallman(&["stop"]).status().unwrap().success());
+    assert!(
+        wait_until(Duration::from_secs(10), || !is_running(second)
+            && !is_running(third)),
+        "stop left the set's swaybg running"
+    );
+    assert!(!harness.root.join("daemon.state.json").exists());
+    fs::remove_dir_all(&harness.root).unwrap();
+}
+
+#[test]
+fn test_restart_keeps_the_wallpaper_up() {
+    let harness = Harness::new("wallman_test_daemon_restart");
+    let mut old = harness.wallman(&["start", "--foreground"]).spawn().unwrap();
+    assert!(
+        wait_until(Duration::from_secs(10), || harness.swaybg_pids().len() == 1),
+        "daemon did not start swaybg"
+    );
+    let swaybg = harness.swaybg_pids()[0];
+    // Give the daemon a moment to record its swaybg in the state file.
+    thread::sleep(Duration::from_millis(500));
+
+    assert!(harness.wallman(&["restart"]).status().unwrap().success());
+    assert!(old.wait().unwrap().success());
+    let new_pid = || {
+        fs::read_to_string(harness.root.join("daemon.pid"))
+            .ok()
+            .and_then(|pid| pid.trim().parse::<i32>().ok())
+            .filter(|pid| *pid != old.id() as i32)
+    };
+    assert!(
+        wait_until(Duration::from_secs(10), || new_pid().is_some()),
+        "no new daemon after restart"
+    );
+    thread::sleep(Duration::from_secs(1));
+    assert_eq!(harness.swaybg_pids(), [swaybg], "the wallpaper was redrawn");
+    assert!(is_running(swaybg), "swaybg did not survive the restart");
+
+    assert!(harness.wallman(&["stop"]).status().unwrap().success());
+    assert!(
+        wait_until(Duration::from_secs(10), || !is_running(swaybg)),
+        "swaybg survived the stop after a restart"
+    );
+    fs::remove_dir_all(&harness.root).unwrap();
+}
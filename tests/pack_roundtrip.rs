@@ -0,0 +1,144 @@
+//! Packs a scaffolded theme with `Packager` and installs it with
+//! `PackInstaller`, checking the on-disk layout an installed theme gets:
+//!
+//! ```text
+//! <themes>/<sanitized manifest name>/
+//! ├── manifest.toml
+//! └── images/
+//!     └── <every image from the theme's images/ folder>
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use wallman::{
+    BackgroundConfig, Config, DayTimeConfig,
+    format::{install::PackInstaller, pack::Packager, verify::verify_pack},
+};
+
+/// Fresh temp directory for one test.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A theme directory named "Moon Wall!" with two tiny PNGs.
+fn scaffold_theme(root: &Path) -> PathBuf {
+    let theme = root.join("moon-src");
+    fs::create_dir_all(theme.join("images")).unwrap();
+    image::RgbImage::from_pixel(2, 2, image::Rgb([10, 20, 30]))
+        .save(theme.join("images/day.png"))
+        .unwrap();
+    image::RgbImage::from_pixel(3, 1, image::Rgb([200, 100, 0]))
+        .save(theme.join("images/night.png"))
+        .unwrap();
+    theme
+}
+
+fn manifest() -> Config {
+    Config {
+        name: Some("Moon Wall!".to_string()),
+        author: Some("Jane Doe".to_string()),
+        background: Some(HashMap::from([(
+            "*".to_string(),
+            BackgroundConfig {
+                image: Some("day.png".to_string()),
+                fill_mode: None,
+                mode: None,
+                blur_sigma: None,
+                brightness: None,
+                fallback_color: None,
+            },
+        )])),
+        time_config: Some(HashMap::from([(
+            "*".to_string(),
+            DayTimeConfig {
+                day: "day.png".to_string(),
+                night: "night.png".to_string(),
+                rules: Vec::new(),
+                fallback_color: None,
+            },
+        )])),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_pack_then_install_round_trips_manifest_and_images() {
+    let root = temp_dir("wallman_it_pack_roundtrip");
+    let theme = scaffold_theme(&root);
+    let pack = root.join("moon.wallman");
+
+    Packager::new(manifest(), &theme).pack(&pack).unwrap();
+    let report = verify_pack(&pack).unwrap();
+    assert!(report.is_ok(), "{:?}", report.problems);
+    assert_eq!(report.entries, 3);
+
+    let themes = root.join("data/themes");
+    let mut installer = PackInstaller::new(&pack).with_dest_dir(&themes);
+    installer.install().unwrap();
+
+    // The folder is named after the sanitized manifest name, not the file.
+    let installed = themes.join("moon-wall");
+    assert_eq!(installer.installed_dir(), installed);
+    let mut entries: Vec<String> = fs::read_dir(&themes)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    entries.sort();
+    assert_eq!(entries, ["moon-wall"], "staging directory left behind");
+
+    let installed_manifest = Config::load(installed.join("manifest.toml")).unwrap();
+    assert_eq!(installed_manifest.name.as_deref(), Some("Moon Wall!"));
+    assert_eq!(installed_manifest.author.as_deref(), Some("Jane Doe"));
+    assert_eq!(installed_manifest.time_config, manifest().time_config);
+    assert_eq!(installed_manifest.background, manifest().background);
+
+    for image in ["day.png", "night.png"] {
+        assert_eq!(
+            fs::read(installed.join("images").join(image)).unwrap(),
+            fs::read(theme.join("images").join(image)).unwrap(),
+            "{} changed in transit",
+            image
+        );
+    }
+    let decoded = image::open(installed.join("images/night.png")).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (3, 1));
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_reinstall_replaces_previous_files() {
+    let root = temp_dir("wallman_it_pack_reinstall");
+    let theme = scaffold_theme(&root);
+    let pack = root.join("moon.wallman");
+    let themes = root.join("themes");
+
+    Packager::new(manifest(), &theme).pack(&pack).unwrap();
+    PackInstaller::new(&pack)
+        .with_dest_dir(&themes)
+        .install()
+        .unwrap();
+    fs::write(themes.join("moon-wall/images/stale.png"), b"old").unwrap();
+
+    // Repack without night.png and install over the old copy.
+    fs::remove_file(theme.join("images/night.png")).unwrap();
+    Packager::new(manifest(), &theme).pack(&pack).unwrap();
+    PackInstaller::new(&pack)
+        .with_dest_dir(&themes)
+        .install()
+        .unwrap();
+
+    let images = themes.join("moon-wall/images");
+    assert!(images.join("day.png").is_file());
+    assert!(!images.join("night.png").exists());
+    assert!(!images.join("stale.png").exists());
+
+    fs::remove_dir_all(&root).unwrap();
+}